@@ -1,9 +1,17 @@
-use crate::game::{Animation, TextureHelper};
+use crate::game::{animation_texture, sheet_animation};
 use crate::Sprite;
-use cgmath::{Vector2, Vector4};
+use cgmath::Vector2;
+use engine::animation::{Animation, AnimationPlayer};
+use engine::collision::Aabb;
+use engine::tween::{Easing, Timeline, Tween};
 use enum_map::{enum_map, Enum, EnumMap};
+use std::collections::HashMap;
 use winit::dpi::LogicalSize;
 
+// How high a collected bomb hops, in pixels, and how long it takes to go up.
+const HOP_HEIGHT: f32 = 12.0;
+const HOP_TIME: f32 = 0.15;
+
 #[derive(Copy, Clone, Enum, Eq, PartialEq)]
 pub enum State {
     Live,
@@ -13,40 +21,44 @@ pub enum State {
 pub struct Bomb {
     pub position: Vector2<f32>,
     pub size: LogicalSize<f32>,
-    pub texture: Vector4<f32>, //x0, y0, x1, y1 - or (0.0, 0.0, 1.0, 1.0)
     pub disarmed: bool,
     pub state: State,
-    animations: EnumMap<State, Animation>,
-    update_count: u32,
+    animations: EnumMap<State, AnimationPlayer>,
+    // Height above its position, played once collected.
+    hop: Tween<f32>,
 }
 
 impl Bomb {
-    pub fn new(x: f32, y: f32, texture_helper: &TextureHelper) -> Self {
+    pub fn new(x: f32, y: f32, animations: &HashMap<String, Animation>) -> Self {
         let animations = enum_map! {
-            State::Live => Animation::new(vec![texture_helper.texture_coord(601.0, 112.0, 35.0, 47.0)], false),
-            State::Collected  => Animation::new(vec![
-                texture_helper.texture_coord(600.0, 112.0, 48.0, 48.0),
-                texture_helper.texture_coord(1138.0, 42.0, 48.0, 48.0),
-                texture_helper.texture_coord(677.0, 112.0, 48.0, 48.0),
-                texture_helper.texture_coord(600.0, 160.0, 48.0, 48.0),
-                texture_helper.texture_coord(601.0, 64.0, 34.0, 47.0),
-            ], false),
+            State::Live => sheet_animation(animations, "bomb-live"),
+            State::Collected => sheet_animation(animations, "bomb-collected"),
         };
         Self {
             position: (x, y).into(),
             size: LogicalSize::new(36.0, 48.0),
-            texture: texture_helper.texture_coord(601.0, 112.0, 35.0, 47.0),
             disarmed: false,
             state: State::Live,
             animations,
-            update_count: 0,
+            hop: Tween::new(0.0, HOP_HEIGHT, HOP_TIME)
+                .with_easing(Easing::QuadOut)
+                .with_yoyo()
+                .with_repeat(2),
         }
     }
 
-    pub fn next_frame(&mut self) {
-        self.update_count += 1;
-        if self.update_count % 2 == 0 {
-            self.animations[self.state].next_frame();
+    pub fn aabb(&self) -> Aabb {
+        Aabb::from_position(self.position, self.size)
+    }
+
+    pub fn collect(&mut self) {
+        self.state = State::Collected;
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.animations[self.state].update(dt);
+        if self.state == State::Collected {
+            self.hop.advance(dt);
         }
     }
 }
@@ -54,9 +66,9 @@ impl Bomb {
 impl From<&Bomb> for Sprite {
     fn from(bomb: &Bomb) -> Self {
         Sprite {
-            position: bomb.position,
+            position: bomb.position + Vector2::new(0.0, bomb.hop.value()),
             size: bomb.size,
-            texture: bomb.animations[bomb.state].current_frame(),
+            texture: animation_texture(&bomb.animations[bomb.state]),
         }
     }
 }
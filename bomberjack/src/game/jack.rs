@@ -1,7 +1,9 @@
-use crate::game::{Animation, TextureHelper};
+use crate::game::{animation_texture, sheet_animation};
 use crate::Sprite;
 use cgmath::Vector2;
+use engine::animation::{Animation, AnimationPlayer};
 use enum_map::{enum_map, Enum, EnumMap};
+use std::collections::HashMap;
 use winit::dpi::LogicalSize;
 
 #[derive(Copy, Clone, Enum, Eq, PartialEq)]
@@ -20,25 +22,19 @@ pub struct Jack {
     pub size: LogicalSize<f32>,
     pub thrust: f32,
     pub direction: Direction,
-    texture_map: EnumMap<Direction, Animation>,
+    animations: EnumMap<Direction, AnimationPlayer>,
 }
 
 impl Jack {
-    pub fn new(texture_helper: &TextureHelper) -> Self {
-        let texture_map = enum_map! {
-            Direction::Idle => Animation::new(vec![texture_helper.texture_coord(601.0, 256.0, 39.0, 45.0)], false),
-            Direction::Up => Animation::new(vec![texture_helper.texture_coord(600.0, 208.0, 40.0, 48.0)], false),
-            Direction::Down => Animation::new(vec![texture_helper.texture_coord(636.0, 64.0, 40.0, 48.0)], false),
-            Direction::Left => Animation::new(vec![
-                texture_helper.texture_coord(600.0, 301.0, 40.0, 48.0),
-                texture_helper.texture_coord(639.0, 256.0, 40.0, 48.0),
-            ], true),
-            Direction::Right => Animation::new(vec![
-                texture_helper.texture_coord(640.0, 208.0, 40.0, 48.0),
-                texture_helper.texture_coord(648.0, 160.0, 40.0, 48.0),
-            ], true),
-            Direction::UpRight => Animation::new(vec![texture_helper.texture_coord(676.0, 64.0, 40.0, 48.0)], false),
-            Direction::UpLeft => Animation::new(vec![texture_helper.texture_coord(636.0, 112.0, 40.0, 48.0)], false),
+    pub fn new(animations: &HashMap<String, Animation>) -> Self {
+        let animations = enum_map! {
+            Direction::Idle => sheet_animation(animations, "jack-idle"),
+            Direction::Up => sheet_animation(animations, "jack-up"),
+            Direction::Down => sheet_animation(animations, "jack-down"),
+            Direction::Left => sheet_animation(animations, "jack-left"),
+            Direction::Right => sheet_animation(animations, "jack-right"),
+            Direction::UpRight => sheet_animation(animations, "jack-up-right"),
+            Direction::UpLeft => sheet_animation(animations, "jack-up-left"),
         };
 
         Self {
@@ -46,12 +42,13 @@ impl Jack {
             size: (39.0, 45.0).into(),
             thrust: 0.0,
             direction: Direction::Idle,
-            texture_map,
+            animations,
         }
     }
 
-    pub fn next_frame(&mut self) {
-        self.texture_map[self.direction].next_frame();
+    // Plays the animation of the direction Jack is going in.
+    pub fn update(&mut self, dt: f32) {
+        self.animations[self.direction].update(dt);
     }
 }
 
@@ -60,7 +57,7 @@ impl From<&Jack> for Sprite {
         Sprite {
             position: jack.position,
             size: jack.size,
-            texture: jack.texture_map[jack.direction].current_frame(),
+            texture: animation_texture(&jack.animations[jack.direction]),
         }
     }
 }
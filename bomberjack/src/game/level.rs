@@ -0,0 +1,17 @@
+use serde::Deserialize;
+
+pub const LEVEL_FILE: &str = "levels/level1.ron";
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct Platform {
+    // Name of the frame in the atlas.
+    pub frame: String,
+    pub position: (f32, f32),
+}
+
+// The layout of a level, loaded from a RON file.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct Level {
+    pub platforms: Vec<Platform>,
+    pub bombs: Vec<(f32, f32)>,
+}
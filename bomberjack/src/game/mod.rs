@@ -1,185 +1,181 @@
 mod bomb;
 mod jack;
+mod level;
 
 use crate::game::jack::Direction;
-use crate::InputState;
-use engine::{
-    rendering::Canvas,
-    rendering::shapes::Sprite,
-};
+use crate::input::Action;
 use bomb::Bomb;
-use cgmath::{Vector2, Vector4};
+use cgmath::{Vector2, Vector4, VectorSpace};
+use engine::animation::{Animation, AnimationPlayer, PlayMode};
+use engine::assets::{Assets, Atlas, AtlasLayout, Handle};
+use engine::collision::Aabb;
+use engine::input::{GracePeriod, Input};
+use engine::rendering::Graphics;
+use engine::{rendering::shapes::Sprite, rendering::Canvas};
 use jack::Jack;
-use std::time::{Duration, Instant};
-use winit::dpi::LogicalSize;
+use level::{Level, LEVEL_FILE};
+use std::collections::HashMap;
+use std::time::Duration;
 
 pub const CANVAS_WIDTH: f32 = 600.0;
 pub const CANVAS_HEIGHT: f32 = 650.0;
 
-pub struct Animation {
-    frames: Vec<Vector4<f32>>,
-    current_frame: usize,
-    looping: bool,
-}
+// The game updates at a fixed 60 ticks per second.
+pub const TICK: Duration = Duration::from_nanos(1_000_000_000 / 60);
 
-impl Animation {
-    pub fn new(frames: Vec<Vector4<f32>>, looping: bool) -> Self {
-        Self {
-            frames,
-            current_frame: 0,
-            looping,
-        }
-    }
+// How long a jump pressed before landing is remembered for, in seconds.
+const JUMP_BUFFER: f32 = 0.1;
+// How long Jack can still jump after walking off a platform, in seconds.
+const COYOTE_TIME: f32 = 0.1;
 
-    pub fn next_frame(&mut self) -> Vector4<f32> {
-        let frame = self.frames[self.current_frame];
-        self.current_frame = if self.current_frame == self.frames.len() - 1 {
-            if self.looping {
-                0
-            } else {
-                self.current_frame
-            }
-        } else {
-            self.current_frame + 1
-        };
-        frame
-    }
+// The animation of the sprite sheet called `name`, or one without frames if there's no such
+// animation.
+fn sheet_animation(animations: &HashMap<String, Animation>, name: &str) -> AnimationPlayer {
+    let animation = animations.get(name).cloned().unwrap_or_else(|| {
+        log::warn!("No animation {} in the sprite sheet", name);
+        Animation::new(vec![], PlayMode::Forward)
+    });
+    AnimationPlayer::new(animation)
+}
 
-    pub fn current_frame(&self) -> Vector4<f32> {
-        self.frames[self.current_frame]
-    }
+// The texture coordinates of the frame shown by `player`.
+fn animation_texture(player: &AnimationPlayer) -> Vector4<f32> {
+    player
+        .frame()
+        .map(|frame| frame.texture)
+        .unwrap_or_else(|| (0.0, 0.0, 0.0, 0.0).into())
 }
 
-pub struct TextureHelper {
-    size: LogicalSize<f32>,
+// Things that happened during an update, for the sounds and effects that aren't part of the
+// game state. `x` is where it happened.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum GameEvent {
+    Jumped { x: f32 },
+    BombCollected { x: f32 },
 }
 
-impl TextureHelper {
-    pub fn new(width: f32, height: f32) -> Self {
-        Self {
-            size: LogicalSize::new(width, height),
-        }
+pub const ATLAS_FILE: &str = "texture.json";
+// The animations of Jack and the bombs, as exported by Aseprite.
+pub const SPRITES_FILE: &str = "sprites.json";
+
+// The assets levels are built from. Clones share the same assets.
+#[derive(Clone)]
+pub struct GameAssets {
+    pub atlas: Handle<Atlas>,
+    pub sprites: Handle<Atlas>,
+    pub level: Handle<Level>,
+}
+
+impl GameAssets {
+    pub fn load(assets: &mut Assets, graphics: &Graphics) -> anyhow::Result<Self> {
+        Ok(Self {
+            atlas: assets.load_atlas(graphics, ATLAS_FILE)?,
+            sprites: assets.load_aseprite(graphics, SPRITES_FILE)?,
+            level: assets.load_data(LEVEL_FILE)?,
+        })
     }
 
-    pub fn texture_coord(&self, x: f32, y: f32, width: f32, height: f32) -> Vector4<f32> {
+    // Changes when any of the assets is reloaded.
+    fn version(&self) -> (u32, u32, u32) {
         (
-            x / self.size.width,
-            y / self.size.height,
-            (x + width) / self.size.width,
-            (y + height) / self.size.height,
+            self.atlas.version(),
+            self.sprites.version(),
+            self.level.version(),
         )
-            .into()
     }
 }
 
-#[derive(Copy, Clone)]
-pub struct Rect {
-    pub bottom_left: Vector2<f32>,
-    pub top_right: Vector2<f32>,
+// The frame of the atlas called `name`, or nothing if there's no such frame.
+fn atlas_sprite(atlas: &AtlasLayout, name: &str, position: Vector2<f32>) -> Sprite {
+    atlas.sprite(name, position).unwrap_or_else(|| {
+        log::warn!("No frame {} in the atlas", name);
+        Sprite {
+            position,
+            size: (0.0, 0.0).into(),
+            texture: (0.0, 0.0, 0.0, 0.0).into(),
+        }
+    })
 }
 
 pub struct BombJackGame {
     pub background: Sprite,
     pub jack: Jack,
-    pub game_bounds: Rect,
+    // Where Jack was before the last update, to interpolate his position when rendering.
+    previous_jack_position: Vector2<f32>,
+    pub game_bounds: Aabb,
     pub platforms: Vec<Sprite>,
     pub bombs: Vec<Bomb>,
-    last_update: Instant,
-    frame: u32,
+    coyote_time: GracePeriod,
+    // Seconds since the level started.
+    elapsed: f32,
     pub score: u32,
+    events: Vec<GameEvent>,
+    // Version of the assets the level was built from, if built from `GameAssets`.
+    assets_version: Option<(u32, u32, u32)>,
 }
 
 impl BombJackGame {
-    pub fn new() -> Self {
-        let texture_helper = TextureHelper::new(1162.0, 650.0);
+    pub fn new(assets: &GameAssets) -> Self {
+        let mut game = Self::from_data(
+            &assets.atlas.get(),
+            &assets.sprites.get().animations,
+            &assets.level.get(),
+        );
+        game.assets_version = Some(assets.version());
+        game
+    }
+
+    // Builds the level from the frames of the atlas and the animations of the sprite sheet,
+    // without their textures, so it can run without a GPU.
+    pub fn from_data(
+        atlas: &AtlasLayout,
+        animations: &HashMap<String, Animation>,
+        level: &Level,
+    ) -> Self {
+        let jack = Jack::new(animations);
         Self {
-            background: Sprite {
-                position: (0.0, 0.0).into(),
-                size: (600.0, 650.0).into(),
-                texture: texture_helper.texture_coord(0.0, 0.0, CANVAS_WIDTH, CANVAS_HEIGHT),
-            },
-            jack: Jack::new(&texture_helper),
-            game_bounds: Rect {
-                bottom_left: (20.0, 20.0).into(),
-                top_right: (580.0, 580.0).into(),
-            },
-            platforms: vec![
-                Sprite {
-                    position: (325.0, 459.0).into(),
-                    size: (150.0, 22.0).into(),
-                    texture: texture_helper.texture_coord(780.0, 42.0, 150.0, 22.0),
-                },
-                Sprite {
-                    position: (330.0, 59.0).into(),
-                    size: (180.0, 22.0).into(),
-                    texture: texture_helper.texture_coord(600.0, 42.0, 180.0, 22.0),
-                },
-                Sprite {
-                    position: (261.0, 199.0).into(),
-                    size: (117.0, 22.0).into(),
-                    texture: texture_helper.texture_coord(930.0, 42.0, 117.0, 22.0),
-                },
-                Sprite {
-                    position: (135.0, 389.0).into(),
-                    size: (91.0, 22.0).into(),
-                    texture: texture_helper.texture_coord(1047.0, 42.0, 91.0, 22.0),
-                },
-                Sprite {
-                    position: (75.0, 129.0).into(),
-                    size: (91.0, 22.0).into(),
-                    texture: texture_helper.texture_coord(1047.0, 42.0, 91.0, 22.0),
-                },
-            ],
-            bombs: vec![
-                // Top left bombs.
-                Bomb::new(92.0, 531.0, &texture_helper),
-                Bomb::new(154.0, 531.0, &texture_helper),
-                Bomb::new(214.0, 531.0, &texture_helper),
-                // Top Right bombs.
-                Bomb::new(414.0, 531.0, &texture_helper),
-                Bomb::new(474.0, 531.0, &texture_helper),
-                Bomb::new(534.0, 531.0, &texture_helper),
-                // Left column of bombs.
-                Bomb::new(24.0, 336.0, &texture_helper),
-                Bomb::new(24.0, 276.0, &texture_helper),
-                Bomb::new(24.0, 216.0, &texture_helper),
-                Bomb::new(24.0, 156.0, &texture_helper),
-                // Right column of bombs.
-                Bomb::new(544.0, 336.0, &texture_helper),
-                Bomb::new(544.0, 276.0, &texture_helper),
-                Bomb::new(544.0, 216.0, &texture_helper),
-                Bomb::new(544.0, 156.0, &texture_helper),
-                // Bottom left
-                Bomb::new(94.0, 21.0, &texture_helper),
-                Bomb::new(154.0, 21.0, &texture_helper),
-                Bomb::new(204.0, 21.0, &texture_helper),
-                // Bottom right
-                Bomb::new(344.0, 81.0, &texture_helper),
-                Bomb::new(404.0, 81.0, &texture_helper),
-                Bomb::new(464.0, 81.0, &texture_helper),
-            ],
-            last_update: Instant::now(),
-            frame: 0,
+            background: atlas_sprite(atlas, "background.png", (0.0, 0.0).into()),
+            previous_jack_position: jack.position,
+            jack,
+            game_bounds: Aabb::new((20.0, 20.0).into(), (580.0, 580.0).into()),
+            platforms: level
+                .platforms
+                .iter()
+                .map(|platform| atlas_sprite(atlas, &platform.frame, platform.position.into()))
+                .collect(),
+            bombs: level
+                .bombs
+                .iter()
+                .map(|&(x, y)| Bomb::new(x, y, animations))
+                .collect(),
+            coyote_time: GracePeriod::new(COYOTE_TIME),
+            elapsed: 0.0,
             score: 0,
+            events: vec![],
+            assets_version: None,
         }
     }
 
-    pub fn update(&mut self, input_state: &InputState) {
-        if self
-            .last_update
-            .elapsed()
-            .lt(&Duration::from_millis(1000 / 60))
-        {
-            return;
-        }
+    // Whether the assets were reloaded since the level was built.
+    pub fn is_outdated(&self, assets: &GameAssets) -> bool {
+        self.assets_version != Some(assets.version())
+    }
+
+    // Advances the game by one `TICK`, `dt` seconds.
+    pub fn update(&mut self, input: &Input<Action>, dt: f32) {
+        self.previous_jack_position = self.jack.position;
         self.jack.direction = Direction::Idle;
-        self.last_update = Instant::now();
 
-        self.frame = self.frame.wrapping_add(1); // Wraps around on overflow.
         let original_y = self.jack.position.y;
 
         // Update game
         let on_ground = self.jack.thrust == 0.0 && self.jack_on_ground();
+        if on_ground {
+            self.coyote_time.refresh();
+        } else {
+            self.coyote_time.tick(dt);
+        }
+        let can_jump = self.jack.thrust == 0.0 && self.coyote_time.is_open();
 
         // TODO: Improve this so bomberjack doesn't land in the middle of a platform.
         if !on_ground {
@@ -187,21 +183,25 @@ impl BombJackGame {
         }
         self.jack.position.y += self.jack.thrust;
 
-        self.jack.thrust = if on_ground {
-            if input_state.up_pressed {
-                20.0
-            } else {
-                self.jack.thrust
-            }
+        // The press that started the level mustn't make Jack jump straight away.
+        let jump_buffer = JUMP_BUFFER.min(self.elapsed);
+        self.jack.thrust = if can_jump && input.pressed_within(Action::Jump, jump_buffer) {
+            self.coyote_time.close();
+            self.events.push(GameEvent::Jumped {
+                x: self.jack.position.x,
+            });
+            20.0
+        } else if on_ground {
+            self.jack.thrust
         } else {
-            (self.jack.thrust - 0.4).max(00.0).min(20.0)
+            (self.jack.thrust - 0.4).clamp(0.0, 20.0)
         };
 
         self.jack.position.y = self
             .jack
             .position
             .y
-            .min(self.game_bounds.top_right.y - self.jack.size.height);
+            .min(self.game_bounds.max.y - self.jack.size.height);
 
         if self.jack.position.y > original_y {
             self.jack.direction = Direction::Up;
@@ -209,7 +209,7 @@ impl BombJackGame {
             self.jack.direction = Direction::Down;
         }
 
-        if input_state.left_pressed && self.jack.position.x > self.game_bounds.bottom_left.x {
+        if input.is_down(Action::MoveLeft) && self.jack.position.x > self.game_bounds.min.x {
             self.jack.position.x -= 2.0;
             self.jack.direction = if self.jack.position.y == original_y && on_ground {
                 Direction::Left
@@ -218,8 +218,8 @@ impl BombJackGame {
             }
         }
 
-        if input_state.right_pressed
-            && (self.jack.position.x + self.jack.size.width) < self.game_bounds.top_right.x
+        if input.is_down(Action::MoveRight)
+            && (self.jack.position.x + self.jack.size.width) < self.game_bounds.max.x
         {
             self.jack.position.x += 2.0;
             self.jack.direction = if self.jack.position.y == original_y && on_ground {
@@ -229,26 +229,39 @@ impl BombJackGame {
             };
         }
 
+        // Swept from where Jack was, so he can't jump through a bomb in one update.
+        let jack_start = Aabb::from_position(self.previous_jack_position, self.jack.size);
+        let jack_movement = self.jack.position - self.previous_jack_position;
         for bomb in &mut self.bombs {
-            if self.jack.position.x < bomb.position.x + bomb.size.width
-                && self.jack.position.x + self.jack.size.width > bomb.position.x
-                && self.jack.position.y < bomb.position.y + bomb.size.height
-                && self.jack.position.y + self.jack.size.height > bomb.position.y
-                && bomb.state != bomb::State::Collected
+            if bomb.state != bomb::State::Collected
+                && jack_start.sweep(jack_movement, &bomb.aabb()).is_some()
             {
-                bomb.state = bomb::State::Collected;
+                bomb.collect();
                 self.score += 100;
+                self.events
+                    .push(GameEvent::BombCollected { x: bomb.position.x });
             }
-            bomb.next_frame();
+            bomb.update(dt);
         }
 
-        if self.frame % 2 == 0 {
-            self.jack.next_frame();
-        }
+        self.jack.update(dt);
+        self.elapsed += dt;
+    }
+
+    // The events since the previous call.
+    pub fn take_events(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    // Whether every bomb has been collected.
+    pub fn is_cleared(&self) -> bool {
+        self.bombs
+            .iter()
+            .all(|bomb| bomb.state == bomb::State::Collected)
     }
 
     fn jack_on_ground(&self) -> bool {
-        if self.jack.position.y <= self.game_bounds.bottom_left.y {
+        if self.jack.position.y <= self.game_bounds.min.y {
             return true;
         }
 
@@ -256,19 +269,19 @@ impl BombJackGame {
             self.jack.position.x + self.jack.size.width / 2.0,
             self.jack.position.y,
         );
-        for platform in &self.platforms {
-            if x >= platform.position.x
-                && x <= platform.position.x + platform.size.width
-                && y >= platform.position.y + platform.size.height - 4.0
-                && y <= platform.position.y + platform.size.height
-            {
-                return true;
-            }
-        }
-        false
+        // Jack stands on the top few pixels of a platform.
+        self.platforms.iter().any(|platform| {
+            let top = platform.position.y + platform.size.height;
+            Aabb::new(
+                (platform.position.x, top - 4.0).into(),
+                (platform.position.x + platform.size.width, top).into(),
+            )
+            .contains_point((x, y).into())
+        })
     }
 
-    pub fn render(&mut self, canvas: &mut Canvas) {
+    // `alpha` is how far along the next update is, see `engine::app::Game::render`.
+    pub fn render(&mut self, canvas: &mut Canvas, alpha: f32) {
         canvas.draw_sprite(&self.background);
 
         for platform in &self.platforms {
@@ -279,6 +292,79 @@ impl BombJackGame {
             canvas.draw_sprite(&bomb.into());
         }
 
-        canvas.draw_sprite(&Sprite::from(&self.jack));
+        let mut jack = Sprite::from(&self.jack);
+        jack.position = self.previous_jack_position.lerp(self.jack.position, alpha);
+        canvas.draw_sprite(&jack);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine::app::Game;
+    use engine::assets::AsepriteSheet;
+    use engine::clock::Time;
+    use engine::headless::{HeadlessRunner, InputScript};
+    use engine::input::bindings::InputMap;
+
+    // Runs a level on its own, without scenes, sounds or textures.
+    struct Level1(BombJackGame);
+
+    impl Game for Level1 {
+        type Action = Action;
+
+        fn update(&mut self, input: &Input<Action>, time: &Time) {
+            self.0.update(input, time.delta());
+        }
+
+        fn render(&mut self, _canvas: &mut Canvas, _alpha: f32) {}
+    }
+
+    fn runner() -> HeadlessRunner<Level1> {
+        let mut assets = Assets::new(concat!(env!("CARGO_MANIFEST_DIR"), "/src/assets"));
+        let read = |assets: &Assets, path| String::from_utf8(assets.read(path).unwrap()).unwrap();
+        let atlas = AtlasLayout::from_json(&read(&assets, ATLAS_FILE)).unwrap();
+        let sprites = AsepriteSheet::from_json(&read(&assets, SPRITES_FILE)).unwrap();
+        let level = assets.load_data::<Level>(LEVEL_FILE).unwrap();
+        let game = BombJackGame::from_data(&atlas, &sprites.animations, &level.get());
+        HeadlessRunner::new(Level1(game), Input::new(InputMap::new()), TICK)
+    }
+
+    #[test]
+    fn falls_onto_a_platform() {
+        let mut runner = runner();
+        runner.run_script(&InputScript::new().wait_until(60));
+        let game = &runner.game.0;
+        assert_eq!(game.jack.position, Vector2::new(300.0, 220.0));
+        assert_eq!(game.score, 0);
+    }
+
+    #[test]
+    fn walks_off_the_platform_onto_bombs() {
+        let mut runner = runner();
+        let script = InputScript::new()
+            .hold(60, 140, Action::MoveLeft)
+            .wait_until(260);
+        assert_eq!(runner.run_script(&script), 260);
+        let game = &mut runner.game.0;
+        // Stopped by the left wall, on the ground.
+        assert_eq!(game.jack.position, Vector2::new(20.0, 20.0));
+        assert_eq!(game.score, 200);
+        assert_eq!(
+            game.take_events(),
+            vec![
+                GameEvent::BombCollected { x: 154.0 },
+                GameEvent::BombCollected { x: 94.0 },
+            ]
+        );
+    }
+
+    #[test]
+    fn jumps() {
+        let mut runner = runner();
+        runner.run_script(&InputScript::new().tap(60, Action::Jump).wait_until(70));
+        let game = &mut runner.game.0;
+        assert!(game.jack.position.y > 220.0);
+        assert_eq!(game.take_events(), vec![GameEvent::Jumped { x: 300.0 }]);
     }
 }
@@ -1,35 +1,69 @@
-use winit::event::{ElementState, KeyboardInput, VirtualKeyCode};
+use engine::input::bindings::{Binding, GamepadAxis, GamepadButton, InputMap};
+use serde::{Deserialize, Serialize};
+use winit::event::VirtualKeyCode;
 
-pub struct InputState {
-    pub up_pressed: bool,
-    pub down_pressed: bool,
-    pub left_pressed: bool,
-    pub right_pressed: bool,
+pub const BINDINGS_FILE: &str = "input.ron";
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+pub enum Action {
+    Jump,
+    Down,
+    MoveLeft,
+    MoveRight,
+    Pause,
 }
 
-impl InputState {
-    pub fn new() -> Self {
-        Self {
-            up_pressed: false,
-            down_pressed: false,
-            left_pressed: false,
-            right_pressed: false,
-        }
-    }
+pub fn default_input_map() -> InputMap<Action> {
+    InputMap::new()
+        .with_action(
+            Action::Jump,
+            &[
+                Binding::Key(VirtualKeyCode::Up),
+                Binding::GamepadButton(GamepadButton::South),
+            ],
+        )
+        .with_action(
+            Action::Down,
+            &[
+                Binding::Key(VirtualKeyCode::Down),
+                Binding::GamepadButton(GamepadButton::DPadDown),
+            ],
+        )
+        .with_action(
+            Action::MoveLeft,
+            &[
+                Binding::Key(VirtualKeyCode::Left),
+                Binding::GamepadButton(GamepadButton::DPadLeft),
+                Binding::GamepadStick {
+                    axis: GamepadAxis::LeftStickX,
+                    positive: false,
+                },
+            ],
+        )
+        .with_action(
+            Action::MoveRight,
+            &[
+                Binding::Key(VirtualKeyCode::Right),
+                Binding::GamepadButton(GamepadButton::DPadRight),
+                Binding::GamepadStick {
+                    axis: GamepadAxis::LeftStickX,
+                    positive: true,
+                },
+            ],
+        )
+        .with_action(
+            Action::Pause,
+            &[
+                Binding::Key(VirtualKeyCode::Escape),
+                Binding::GamepadButton(GamepadButton::Start),
+            ],
+        )
+}
 
-    pub fn update(&mut self, input: &KeyboardInput) {
-        match input.virtual_keycode {
-            Some(VirtualKeyCode::Up) => self.up_pressed = input.state.eq(&ElementState::Pressed),
-            Some(VirtualKeyCode::Down) => {
-                self.down_pressed = input.state.eq(&ElementState::Pressed)
-            }
-            Some(VirtualKeyCode::Left) => {
-                self.left_pressed = input.state.eq(&ElementState::Pressed)
-            }
-            Some(VirtualKeyCode::Right) => {
-                self.right_pressed = input.state.eq(&ElementState::Pressed)
-            }
-            _ => {}
-        }
-    }
+// Loads the player's bindings, if they have customised them.
+pub fn load_input_map() -> InputMap<Action> {
+    InputMap::load_or(BINDINGS_FILE, default_input_map()).unwrap_or_else(|e| {
+        log::warn!("{}, using the default bindings", e);
+        default_input_map()
+    })
 }
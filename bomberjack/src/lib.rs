@@ -1,70 +1,148 @@
 mod game;
 mod input;
+mod replay;
+mod scenes;
+mod sounds;
 
-use winit::{
-    dpi::LogicalSize,
-    event::*,
-    event_loop::{ControlFlow, EventLoop},
-    window::WindowBuilder,
-};
+use winit::dpi::LogicalSize;
+use winit::event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent};
 
+use engine::app::{App, AppConfig, Game};
+use engine::assets::{Archive, Assets};
+use engine::audio::Audio;
+use engine::clock::Time;
+use engine::input::Input;
 use engine::rendering::shapes::Sprite;
 use engine::rendering::{Canvas, Graphics};
-use game::BombJackGame;
-use input::InputState;
+use engine::scene::SceneStack;
+use engine::window::{Cursor, WindowConfig};
+use game::GameAssets;
+use input::Action;
+use replay::Session;
+use scenes::TitleScene;
+use sounds::Sounds;
+
+const SLOW_MOTION: f32 = 0.25;
+
+const FONT_FILE: &str = "Inconsolata-Regular.ttf";
+
+struct BombJack {
+    scenes: SceneStack<Action>,
+    session: Session,
+    assets: Assets,
+    game_assets: GameAssets,
+}
+
+impl Game for BombJack {
+    type Action = Action;
+
+    fn begin_tick(&mut self, input: &mut Input<Action>, time: &Time) -> bool {
+        self.session.begin_tick(input, time.delta())
+    }
+
+    fn update(&mut self, input: &Input<Action>, time: &Time) {
+        self.scenes.update(input, time);
+    }
+
+    fn render(&mut self, canvas: &mut Canvas, alpha: f32) {
+        let atlas_version = self.game_assets.atlas.version();
+        self.assets.reload_changed(canvas.graphics());
+        // The atlas may now point to another image.
+        if self.game_assets.atlas.version() != atlas_version {
+            let texture = self.game_assets.atlas.get().texture.clone();
+            canvas.graphics().set_texture(texture);
+        }
+        self.scenes.render(canvas, alpha);
+    }
+
+    // F2 toggles slow motion, for debugging.
+    fn on_event(&mut self, event: &WindowEvent, time: &mut Time) {
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    virtual_keycode: Some(VirtualKeyCode::F2),
+                    state: ElementState::Pressed,
+                    ..
+                },
+            ..
+        } = event
+        {
+            let time_scale = if time.time_scale() < 1.0 {
+                1.0
+            } else {
+                SLOW_MOTION
+            };
+            time.set_time_scale(time_scale);
+        }
+    }
+
+    fn on_exit(&mut self) {
+        self.scenes.clear();
+        self.session.finish();
+    }
+
+    fn should_exit(&self) -> bool {
+        self.scenes.is_empty()
+    }
+}
+
+// The assets of release builds, packed by the release workflow with the engine's pack-assets.
+#[cfg(feature = "embed-assets")]
+fn open_assets() -> anyhow::Result<Assets> {
+    let archive = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/assets.pak"));
+    Ok(Assets::from_source(Archive::embedded(archive)?))
+}
+
+// The assets from an assets.pak next to the executable, or else the asset directory of the
+// source tree, which is watched for changes in debug builds.
+#[cfg(not(feature = "embed-assets"))]
+fn open_assets() -> anyhow::Result<Assets> {
+    let archive = std::env::current_exe()?.with_file_name("assets.pak");
+    if archive.is_file() {
+        return Ok(Assets::from_source(Archive::load(archive)?));
+    }
+    let mut assets = Assets::new(concat!(env!("CARGO_MANIFEST_DIR"), "/src/assets"));
+    if cfg!(debug_assertions) {
+        assets.watch();
+    }
+    Ok(assets)
+}
+
+// Loads the assets, giving the sprite texture and the font to `graphics`.
+fn load_assets(assets: &mut Assets, graphics: &mut Graphics) -> anyhow::Result<GameAssets> {
+    let game_assets = GameAssets::load(assets, graphics)?;
+    graphics.set_texture(game_assets.atlas.get().texture.clone());
+    graphics.set_font(assets.load_font(FONT_FILE)?);
+    Ok(game_assets)
+}
 
 pub async fn run() {
-    let event_loop = EventLoop::new();
-    let window = WindowBuilder::new()
-        .with_resizable(false)
-        .with_inner_size(LogicalSize::new(game::CANVAS_WIDTH, game::CANVAS_HEIGHT))
-        .build(&event_loop)
-        .unwrap();
-
-    let mut input_state = InputState::new();
-    let mut graphics = Graphics::new(&window).await.unwrap();
-    let mut game = BombJackGame::new();
-
-    event_loop.run(move |event, _, control_flow| {
-        match event {
-            Event::WindowEvent {
-                ref event,
-                window_id,
-            } => {
-                if window_id == window.id() {
-                    match event {
-                        WindowEvent::CloseRequested => {
-                            *control_flow = ControlFlow::Exit;
-                        }
-                        WindowEvent::Resized(physical_size) => {
-                            graphics.resize(*physical_size);
-                        }
-                        WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
-                            // new_inner_size is &&mut so w have to dereference it twice
-                            graphics.resize(**new_inner_size);
-                        }
-                        WindowEvent::KeyboardInput { input, .. } => input_state.update(input),
-                        _ => {}
-                    }
-                }
-            }
-            Event::RedrawRequested(window_id) => {
-                if window_id != window.id() {
-                    return;
-                }
-                // Update game state.
-                game.update(&input_state);
-
-                // Render game
-                let mut canvas = Canvas::new(&mut graphics);
-                game.render(&mut canvas);
+    let session = Session::from_args().unwrap();
+    let size = LogicalSize::new(game::CANVAS_WIDTH, game::CANVAS_HEIGHT);
+    let config = AppConfig {
+        window: WindowConfig {
+            title: "Bomb Jack".to_string(),
+            size,
+            min_size: Some(size),
+            icon: Some(include_bytes!("assets/jackf.png").to_vec()),
+            cursor: Cursor::Hidden,
+            virtual_resolution: Some(size.to_physical(1.0)),
+            ..Default::default()
+        },
+        tick: game::TICK,
+        ..Default::default()
+    };
+    App::new(config)
+        .run(Input::new(input::load_input_map()), |graphics| {
+            let mut assets = open_assets().unwrap();
+            let game_assets = load_assets(&mut assets, graphics).unwrap();
+            let sounds = Sounds::new(Audio::new(), &assets);
+            BombJack {
+                scenes: SceneStack::new(Box::new(TitleScene::new(game_assets.clone(), sounds))),
+                session,
+                assets,
+                game_assets,
             }
-            Event::MainEventsCleared => {
-                // RedrawRequested will only trigger once, unless we manually
-                // request it.
-                window.request_redraw();
-            }
-            _ => {}
-        }
-    });
+        })
+        .await
 }
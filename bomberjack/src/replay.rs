@@ -0,0 +1,80 @@
+use crate::game;
+use crate::input::Action;
+use engine::input::replay::{Recording, Replay};
+use engine::input::Input;
+use engine::random::Rng;
+use std::path::PathBuf;
+
+const BUILD_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// How the game's input is driven, picked from the command line:
+// `--record <file>` saves the session when the window is closed, `--replay <file>` plays one back.
+pub enum Session {
+    Play,
+    Record(PathBuf, Recording<Action>),
+    Replay(Replay<Action>),
+}
+
+impl Session {
+    pub fn from_args() -> anyhow::Result<Self> {
+        let mut args = std::env::args().skip(1);
+        let Some(arg) = args.next() else {
+            return Ok(Self::Play);
+        };
+        match arg.as_str() {
+            "--record" => {
+                let path = args
+                    .next()
+                    .ok_or_else(|| anyhow::anyhow!("--record needs a file"))?;
+                // Bomb Jack has no randomness yet, but the seed is recorded so replays stay
+                // valid once it does.
+                let recording = Recording::new(
+                    BUILD_VERSION,
+                    Rng::seed_from_time(),
+                    game::TICK.as_secs_f32(),
+                );
+                Ok(Self::Record(path.into(), recording))
+            }
+            "--replay" => {
+                let path = args
+                    .next()
+                    .ok_or_else(|| anyhow::anyhow!("--replay needs a file"))?;
+                let recording = Recording::load(path)?;
+                if recording.build_version != BUILD_VERSION {
+                    log::warn!(
+                        "Recording made with version {}, replaying on {}, it may diverge",
+                        recording.build_version,
+                        BUILD_VERSION
+                    );
+                }
+                Ok(Self::Replay(Replay::new(recording)))
+            }
+            _ => anyhow::bail!("Unknown argument {}", arg),
+        }
+    }
+
+    // Starts the next tick, returning false once a replay is over.
+    pub fn begin_tick(&mut self, input: &mut Input<Action>, dt: f32) -> bool {
+        match self {
+            Self::Play => input.begin_tick(dt),
+            Self::Record(_, recording) => {
+                input.begin_tick(dt);
+                recording.record(input.last_tick());
+            }
+            Self::Replay(replay) => match replay.next_tick() {
+                Some(tick) => input.replay_tick(tick, dt),
+                None => return false,
+            },
+        }
+        true
+    }
+
+    pub fn finish(&self) {
+        if let Self::Record(path, recording) = self {
+            match recording.save(path) {
+                Ok(()) => log::info!("Saved {} ticks to {}", recording.len(), path.display()),
+                Err(e) => log::error!("{}", e),
+            }
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use crate::game::{BombJackGame, GameAssets, CANVAS_HEIGHT, CANVAS_WIDTH};
+use crate::input::Action;
+use crate::sounds::Sounds;
+use engine::clock::Time;
+use engine::input::Input;
+use engine::prelude::*;
+use engine::scene::{Scene, Transition};
+use engine::tween::{Easing, Timeline, Tween};
+
+// How long the level takes to fade in, in seconds.
+const FADE_IN_TIME: f32 = 0.5;
+// How long the level clear overlay takes to brighten or dim, in seconds.
+const GLOW_TIME: f32 = 0.6;
+
+fn overlay(canvas: &mut Canvas, color: Color) {
+    canvas.draw_rect(&Square {
+        position: (0.0, 0.0).into(),
+        size: (CANVAS_WIDTH, CANVAS_HEIGHT).into(),
+        color,
+    });
+}
+
+// Shows the level behind a dark overlay until the player jumps.
+pub struct TitleScene {
+    level: BombJackGame,
+    assets: GameAssets,
+    sounds: Sounds,
+}
+
+impl TitleScene {
+    pub fn new(assets: GameAssets, sounds: Sounds) -> Self {
+        Self {
+            level: BombJackGame::new(&assets),
+            assets,
+            sounds,
+        }
+    }
+}
+
+impl Scene<Action> for TitleScene {
+    fn update(&mut self, input: &Input<Action>, _time: &Time) -> Transition<Action> {
+        if self.level.is_outdated(&self.assets) {
+            self.level = BombJackGame::new(&self.assets);
+        }
+        if input.just_pressed(Action::Jump) {
+            Transition::Replace(Box::new(GameplayScene::new(
+                self.assets.clone(),
+                self.sounds.clone(),
+            )))
+        } else {
+            Transition::None
+        }
+    }
+
+    fn render(&mut self, canvas: &mut Canvas, alpha: f32) {
+        self.level.render(canvas, alpha);
+        overlay(canvas, Color::BLACK.with_alpha(0.6));
+    }
+
+    fn on_enter(&mut self) {
+        self.sounds.play_title_music();
+    }
+}
+
+pub struct GameplayScene {
+    game: BombJackGame,
+    assets: GameAssets,
+    sounds: Sounds,
+    // Fades the level in from the title screen's overlay.
+    fade: Tween<Color>,
+}
+
+impl GameplayScene {
+    pub fn new(assets: GameAssets, sounds: Sounds) -> Self {
+        Self {
+            game: BombJackGame::new(&assets),
+            assets,
+            sounds,
+            fade: Tween::new(
+                Color::BLACK.with_alpha(0.6),
+                Color::TRANSPARENT,
+                FADE_IN_TIME,
+            )
+            .with_easing(Easing::QuadOut),
+        }
+    }
+}
+
+impl Scene<Action> for GameplayScene {
+    fn update(&mut self, input: &Input<Action>, time: &Time) -> Transition<Action> {
+        // Edited level files restart the level.
+        if self.game.is_outdated(&self.assets) {
+            self.game = BombJackGame::new(&self.assets);
+        }
+        if input.just_pressed(Action::Pause) {
+            return Transition::Push(Box::new(PauseScene));
+        }
+        self.game.update(input, time.delta());
+        self.fade.update(time);
+        for event in self.game.take_events() {
+            self.sounds.play(&event);
+        }
+        if self.game.is_cleared() {
+            Transition::Push(Box::new(LevelClearScene::new(
+                self.assets.clone(),
+                self.sounds.clone(),
+            )))
+        } else {
+            Transition::None
+        }
+    }
+
+    fn render(&mut self, canvas: &mut Canvas, alpha: f32) {
+        self.game.render(canvas, alpha);
+        if !self.fade.is_finished() {
+            overlay(canvas, self.fade.value());
+        }
+    }
+
+    fn on_enter(&mut self) {
+        self.sounds.play_round_music();
+    }
+}
+
+// Freezes the level under a translucent overlay.
+pub struct PauseScene;
+
+impl Scene<Action> for PauseScene {
+    fn update(&mut self, input: &Input<Action>, _time: &Time) -> Transition<Action> {
+        if input.just_pressed(Action::Pause) {
+            Transition::Pop
+        } else {
+            Transition::None
+        }
+    }
+
+    fn render(&mut self, canvas: &mut Canvas, _alpha: f32) {
+        overlay(canvas, Color::BLACK.with_alpha(0.4));
+    }
+
+    fn is_transparent(&self) -> bool {
+        true
+    }
+}
+
+// Shown over the finished level, jumping goes back to the title screen.
+pub struct LevelClearScene {
+    assets: GameAssets,
+    sounds: Sounds,
+    // Opacity of the overlay, which slowly pulses.
+    glow: Tween<f32>,
+}
+
+impl LevelClearScene {
+    pub fn new(assets: GameAssets, sounds: Sounds) -> Self {
+        Self {
+            assets,
+            sounds,
+            glow: Tween::new(0.2, 0.4, GLOW_TIME)
+                .with_easing(Easing::QuadInOut)
+                .with_yoyo()
+                .looping(),
+        }
+    }
+}
+
+impl Scene<Action> for LevelClearScene {
+    fn update(&mut self, input: &Input<Action>, time: &Time) -> Transition<Action> {
+        self.glow.update(time);
+        if input.just_pressed(Action::Jump) {
+            Transition::ReplaceAll(Box::new(TitleScene::new(
+                self.assets.clone(),
+                self.sounds.clone(),
+            )))
+        } else {
+            Transition::None
+        }
+    }
+
+    fn render(&mut self, canvas: &mut Canvas, _alpha: f32) {
+        overlay(canvas, Color::YELLOW.with_alpha(self.glow.value()));
+    }
+
+    fn is_transparent(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,100 @@
+use crate::game::{GameEvent, CANVAS_WIDTH};
+use engine::assets::Assets;
+use engine::audio::{Audio, Music, PlayParams, Sound, SynthParams, Waveform, SAMPLE_RATE};
+
+pub const TITLE_MUSIC_FILE: &str = "music/title.ogg";
+pub const ROUND_MUSIC_FILE: &str = "music/round.ogg";
+
+// Seconds to crossfade the music over when changing scenes.
+const MUSIC_FADE: f32 = 1.0;
+
+// The game's sound effects and music, played on `Audio`.
+#[derive(Clone)]
+pub struct Sounds {
+    audio: Audio,
+    jump: Sound,
+    bomb: Sound,
+    title_music: Option<Music>,
+    round_music: Option<Music>,
+}
+
+impl Sounds {
+    pub fn new(audio: Audio, assets: &Assets) -> Self {
+        Self {
+            audio,
+            jump: jump_params().synthesize(SAMPLE_RATE).with_max_voices(1),
+            bomb: bomb_params().synthesize(SAMPLE_RATE).with_max_voices(4),
+            title_music: load_music(assets, TITLE_MUSIC_FILE),
+            round_music: load_music(assets, ROUND_MUSIC_FILE),
+        }
+    }
+
+    pub fn play_title_music(&self) {
+        self.play_music(self.title_music.as_ref());
+    }
+
+    pub fn play_round_music(&self) {
+        self.play_music(self.round_music.as_ref());
+    }
+
+    fn play_music(&self, music: Option<&Music>) {
+        match music {
+            Some(music) => {
+                if let Err(e) = self.audio.play_music(music, MUSIC_FADE) {
+                    log::warn!("{}", e);
+                }
+            }
+            None => self.audio.stop_music(MUSIC_FADE),
+        }
+    }
+
+    pub fn play(&self, event: &GameEvent) {
+        let (sound, x) = match event {
+            GameEvent::Jumped { x } => (&self.jump, x),
+            GameEvent::BombCollected { x } => (&self.bomb, x),
+        };
+        // Pan a little towards where it happened.
+        let pan = (x / CANVAS_WIDTH * 2.0 - 1.0) * 0.5;
+        self.audio
+            .play(sound, PlayParams::default().with_volume(0.3).with_pan(pan));
+    }
+}
+
+// The music is optional, the game plays without it.
+fn load_music(assets: &Assets, path: &str) -> Option<Music> {
+    if !assets.exists(path) {
+        log::info!("No {}, playing without music", path);
+        return None;
+    }
+    assets
+        .load_music(path)
+        .map_err(|e| log::warn!("{}", e))
+        .ok()
+}
+
+// A rising square wave.
+fn jump_params() -> SynthParams {
+    SynthParams {
+        waveform: Waveform::Square,
+        frequency: 220.0,
+        slide: 8.0,
+        duty: 0.3,
+        sustain: 0.05,
+        decay: 0.1,
+        ..SynthParams::default()
+    }
+}
+
+// A short two-note chime.
+fn bomb_params() -> SynthParams {
+    SynthParams {
+        waveform: Waveform::Square,
+        frequency: 880.0,
+        change_amount: 1.5,
+        change_time: 0.04,
+        sustain: 0.05,
+        punch: 0.5,
+        decay: 0.1,
+        ..SynthParams::default()
+    }
+}
@@ -0,0 +1,167 @@
+pub mod player;
+
+pub use player::AnimationPlayer;
+
+use cgmath::Vector4;
+use serde::{Deserialize, Serialize};
+use winit::dpi::LogicalSize;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Frame {
+    // Texture coordinates, as used by `Sprite::texture`.
+    pub texture: Vector4<f32>,
+    // Size in pixels of the frame in the texture.
+    pub size: LogicalSize<f32>,
+    // In seconds.
+    pub duration: f32,
+}
+
+// The order frames are played in.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PlayMode {
+    #[default]
+    Forward,
+    Reverse,
+    // Forward then backward, without repeating the first and last frames.
+    PingPong,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Animation {
+    pub frames: Vec<Frame>,
+    pub mode: PlayMode,
+    // How many times the frames are played, forever if `None` or 0.
+    pub repeat: Option<u32>,
+    // Names reported by `AnimationPlayer` when a frame is shown, as (frame index, name).
+    pub events: Vec<(usize, String)>,
+}
+
+impl Animation {
+    // Loops forever.
+    pub fn new(frames: Vec<Frame>, mode: PlayMode) -> Self {
+        Self {
+            frames,
+            mode,
+            repeat: None,
+            events: vec![],
+        }
+    }
+
+    // Plays the frames `repeat` times, or forever if it's 0, like Aseprite's tags.
+    pub fn with_repeat(mut self, repeat: u32) -> Self {
+        self.repeat = Some(repeat).filter(|repeat| *repeat != 0);
+        self
+    }
+
+    // Reports `name` every time frame `index` is shown, eg. a footstep.
+    pub fn with_event(mut self, index: usize, name: impl Into<String>) -> Self {
+        self.events.push((index, name.into()));
+        self
+    }
+
+    // The events of frame `index`.
+    pub fn events_at(&self, index: usize) -> impl Iterator<Item = &str> {
+        self.events
+            .iter()
+            .filter(move |(frame, _)| *frame == index)
+            .map(|(_, name)| name.as_str())
+    }
+
+    // The indices of the frames played in one cycle.
+    pub fn sequence(&self) -> Vec<usize> {
+        let len = self.frames.len();
+        match self.mode {
+            PlayMode::Forward => (0..len).collect(),
+            PlayMode::Reverse => (0..len).rev().collect(),
+            PlayMode::PingPong => (0..len).chain((1..len.saturating_sub(1)).rev()).collect(),
+        }
+    }
+
+    // Duration of one cycle, in seconds.
+    pub fn cycle_duration(&self) -> f32 {
+        self.sequence()
+            .into_iter()
+            .map(|index| self.frames[index].duration)
+            .sum()
+    }
+
+    // Duration of the whole animation, infinite when it loops forever.
+    pub fn duration(&self) -> f32 {
+        match self.repeat {
+            Some(repeat) if repeat > 0 => self.cycle_duration() * repeat as f32,
+            _ => f32::INFINITY,
+        }
+    }
+
+    // Index of the frame shown once finished: the last one, or back to the first for ping-pong.
+    pub fn end_index(&self) -> usize {
+        match self.mode {
+            PlayMode::Forward => self.frames.len().saturating_sub(1),
+            PlayMode::Reverse | PlayMode::PingPong => 0,
+        }
+    }
+
+    // Index of the frame shown `time` seconds in.
+    pub fn index_at(&self, time: f32) -> usize {
+        let sequence = self.sequence();
+        let cycle = self.cycle_duration();
+        if sequence.is_empty() || cycle <= 0.0 {
+            return 0;
+        }
+        if time >= self.duration() {
+            return self.end_index();
+        }
+        let mut time = time.max(0.0) % cycle;
+        for index in &sequence {
+            let duration = self.frames[*index].duration;
+            if time < duration {
+                return *index;
+            }
+            time -= duration;
+        }
+        sequence[sequence.len() - 1]
+    }
+
+    pub fn frame_at(&self, time: f32) -> Option<&Frame> {
+        self.frames.get(self.index_at(time))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn animation(durations: &[f32]) -> Animation {
+        let frames = durations
+            .iter()
+            .map(|duration| Frame {
+                texture: Vector4::new(0.0, 0.0, 1.0, 1.0),
+                size: LogicalSize::new(8.0, 8.0),
+                duration: *duration,
+            })
+            .collect();
+        Animation::new(frames, PlayMode::Forward)
+    }
+
+    #[test]
+    fn repeat_and_duration() {
+        let frames = animation(&[0.1, 0.2]);
+        assert_eq!(frames.duration(), f32::INFINITY);
+        assert!((frames.clone().with_repeat(2).duration() - 0.6).abs() < 1e-6);
+
+        let forever = frames.clone().with_repeat(0);
+        assert_eq!(forever.repeat, None);
+        assert_eq!(forever.duration(), f32::INFINITY);
+        assert_eq!(forever.index_at(10.15), 1);
+
+        // Set directly rather than with `with_repeat`.
+        let forever = Animation {
+            repeat: Some(0),
+            ..frames
+        };
+        assert_eq!(forever.duration(), f32::INFINITY);
+        let mut player = AnimationPlayer::new(forever);
+        player.update(10.0);
+        assert!(!player.is_finished());
+    }
+}
@@ -0,0 +1,260 @@
+use crate::animation::{Animation, Frame};
+
+// Plays an animation as time passes, frame by frame, reporting the events of the frames it
+// shows. Update it with the tick's delta time so it plays the same at any frame rate.
+#[derive(Clone, Debug)]
+pub struct AnimationPlayer {
+    animation: Animation,
+    sequence: Vec<usize>,
+    // Of `animation`, kept as computing it allocates.
+    cycle_duration: f32,
+    // Position in `sequence`.
+    step: usize,
+    // Number of cycles played through.
+    cycles: u32,
+    // Time spent on the current frame, in seconds.
+    frame_time: f32,
+    speed: f32,
+    paused: bool,
+    finished: bool,
+    events: Vec<String>,
+}
+
+impl AnimationPlayer {
+    // Starts playing `animation` from its first frame.
+    pub fn new(animation: Animation) -> Self {
+        let mut player = Self {
+            sequence: animation.sequence(),
+            cycle_duration: animation.cycle_duration(),
+            animation,
+            step: 0,
+            cycles: 0,
+            frame_time: 0.0,
+            speed: 1.0,
+            paused: false,
+            finished: false,
+            events: vec![],
+        };
+        player.restart();
+        player
+    }
+
+    pub fn animation(&self) -> &Animation {
+        &self.animation
+    }
+
+    // Plays another animation from the start, keeping the speed.
+    pub fn play(&mut self, animation: Animation) {
+        self.sequence = animation.sequence();
+        self.cycle_duration = animation.cycle_duration();
+        self.animation = animation;
+        self.restart();
+    }
+
+    pub fn restart(&mut self) {
+        self.step = 0;
+        self.cycles = 0;
+        self.frame_time = 0.0;
+        self.paused = false;
+        self.finished = self.sequence.is_empty();
+        self.enter_frame();
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    // How fast the animation plays, 1 being its normal speed.
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.max(0.0);
+    }
+
+    // Whether the animation played as many times as it repeats. Looping animations never
+    // finish.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    // Advances by `dt` seconds, scaled by the speed.
+    pub fn update(&mut self, dt: f32) {
+        if self.paused || self.finished || self.cycle_duration <= 0.0 {
+            return;
+        }
+        self.frame_time += dt * self.speed;
+        loop {
+            let duration = self.animation.frames[self.sequence[self.step]].duration;
+            if self.frame_time < duration {
+                break;
+            }
+            self.frame_time -= duration;
+            self.step += 1;
+            if self.step == self.sequence.len() {
+                self.step = 0;
+                self.cycles += 1;
+                if Some(self.cycles) == self.animation.repeat {
+                    self.finished = true;
+                    self.frame_time = 0.0;
+                    if self.index() != self.sequence[self.sequence.len() - 1] {
+                        self.enter_frame();
+                    }
+                    break;
+                }
+            }
+            self.enter_frame();
+        }
+    }
+
+    // Index of the frame shown.
+    pub fn index(&self) -> usize {
+        if self.finished {
+            self.animation.end_index()
+        } else {
+            self.sequence.get(self.step).copied().unwrap_or(0)
+        }
+    }
+
+    // The frame shown, if the animation has any.
+    pub fn frame(&self) -> Option<&Frame> {
+        self.animation.frames.get(self.index())
+    }
+
+    // The events of the frames shown since the previous call, in order.
+    pub fn take_events(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.events)
+    }
+
+    fn enter_frame(&mut self) {
+        let index = self.index();
+        self.events
+            .extend(self.animation.events_at(index).map(str::to_string));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::PlayMode;
+    use cgmath::Vector4;
+    use winit::dpi::LogicalSize;
+
+    // Frames of a quarter of a second, each reporting its index as an event.
+    fn animation(frames: usize, mode: PlayMode) -> Animation {
+        let frame = Frame {
+            texture: Vector4::new(0.0, 0.0, 1.0, 1.0),
+            size: LogicalSize::new(8.0, 8.0),
+            duration: 0.25,
+        };
+        (0..frames).fold(
+            Animation::new(vec![frame; frames], mode),
+            |animation, index| animation.with_event(index, index.to_string()),
+        )
+    }
+
+    // The frames shown over `steps` updates of `dt`.
+    fn indices(player: &mut AnimationPlayer, steps: usize, dt: f32) -> Vec<usize> {
+        (0..steps)
+            .map(|_| {
+                player.update(dt);
+                player.index()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn play_modes() {
+        let mut player = AnimationPlayer::new(animation(3, PlayMode::Forward));
+        assert_eq!(player.index(), 0);
+        assert_eq!(indices(&mut player, 4, 0.25), [1, 2, 0, 1]);
+
+        player.play(animation(3, PlayMode::Reverse));
+        assert_eq!(player.index(), 2);
+        assert_eq!(indices(&mut player, 4, 0.25), [1, 0, 2, 1]);
+
+        player.play(animation(4, PlayMode::PingPong));
+        assert_eq!(player.index(), 0);
+        assert_eq!(indices(&mut player, 7, 0.25), [1, 2, 3, 2, 1, 0, 1]);
+        assert!(!player.is_finished());
+    }
+
+    #[test]
+    fn speed_and_pause() {
+        let mut player = AnimationPlayer::new(animation(3, PlayMode::Forward));
+        player.set_speed(2.0);
+        assert_eq!(indices(&mut player, 2, 0.125), [1, 2]);
+
+        player.set_speed(-1.0);
+        assert_eq!(player.speed(), 0.0);
+        assert_eq!(indices(&mut player, 2, 1.0), [2, 2]);
+
+        player.set_speed(1.0);
+        player.pause();
+        assert!(player.is_paused());
+        assert_eq!(indices(&mut player, 2, 1.0), [2, 2]);
+        player.resume();
+        assert_eq!(indices(&mut player, 1, 0.25), [0]);
+    }
+
+    #[test]
+    fn finishing() {
+        let mut player = AnimationPlayer::new(animation(3, PlayMode::Forward).with_repeat(2));
+        assert_eq!(indices(&mut player, 5, 0.25), [1, 2, 0, 1, 2]);
+        assert!(!player.is_finished());
+        player.update(0.25);
+        assert!(player.is_finished());
+        assert_eq!(player.index(), 2);
+        assert_eq!(player.frame(), player.animation().frames.get(2));
+        assert_eq!(indices(&mut player, 1, 1.0), [2]);
+
+        player.restart();
+        assert!(!player.is_finished());
+        assert_eq!(player.index(), 0);
+    }
+
+    #[test]
+    fn events() {
+        let mut player = AnimationPlayer::new(animation(3, PlayMode::Forward));
+        assert_eq!(player.take_events(), ["0"]);
+        assert!(player.take_events().is_empty());
+
+        player.update(0.1);
+        assert!(player.take_events().is_empty());
+        // Through several frames at once, and around the loop.
+        player.update(0.9);
+        assert_eq!(player.take_events(), ["1", "2", "0", "1"]);
+
+        player.restart();
+        assert_eq!(player.take_events(), ["0"]);
+        player.update(0.75);
+        assert_eq!(player.take_events(), ["1", "2", "0"]);
+    }
+
+    #[test]
+    fn ping_pong_finishes_on_the_first_frame() {
+        let mut player = AnimationPlayer::new(animation(3, PlayMode::PingPong).with_repeat(1));
+        player.take_events();
+        player.update(10.0);
+        assert!(player.is_finished());
+        assert_eq!(player.index(), 0);
+        assert_eq!(player.take_events(), ["1", "2", "1", "0"]);
+        player.update(1.0);
+        assert!(player.take_events().is_empty());
+
+        // Finishing on the frame already shown doesn't report it again.
+        let mut player = AnimationPlayer::new(animation(3, PlayMode::Forward).with_repeat(1));
+        player.take_events();
+        player.update(10.0);
+        assert_eq!(player.take_events(), ["1", "2"]);
+    }
+}
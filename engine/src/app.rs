@@ -0,0 +1,212 @@
+use crate::clock::{Clock, FixedTimestep, RealClock, Time};
+use crate::input::bindings::Action;
+use crate::input::gamepad::{GamepadBackend, VirtualGamepads};
+use crate::input::Input;
+use crate::rendering::{Canvas, Graphics};
+use crate::window::WindowConfig;
+use std::time::Duration;
+use winit::event::{
+    ElementState, Event, KeyboardInput, ModifiersState, VirtualKeyCode, WindowEvent,
+};
+use winit::event_loop::{ControlFlow, EventLoop};
+
+// A game driven by `App`. Games are updated at a fixed rate, and rendered as often as the display
+// allows, so rendering may happen between two updates.
+pub trait Game: 'static {
+    type Action: Action;
+
+    // Samples the input for the next tick. Override to feed recorded input instead, returning
+    // false to stop the game.
+    fn begin_tick(&mut self, input: &mut Input<Self::Action>, time: &Time) -> bool {
+        input.begin_tick(time.delta());
+        true
+    }
+
+    // Advances the game by one tick, `time.delta()` seconds.
+    fn update(&mut self, input: &Input<Self::Action>, time: &Time);
+
+    // `alpha` is how far the current time is between the last update and the next one, from 0.0
+    // to 1.0. Interpolating positions with it keeps motion smooth on high refresh rate displays.
+    fn render(&mut self, canvas: &mut Canvas, alpha: f32);
+
+    // Called for every window event, after the input has seen it. `time` can be paused or slowed
+    // down from here, eg. for debugging.
+    fn on_event(&mut self, _event: &WindowEvent, _time: &mut Time) {}
+
+    // Called once before the window closes.
+    fn on_exit(&mut self) {}
+
+    // Checked after every update, the app closes once it returns true.
+    fn should_exit(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct AppConfig {
+    pub window: WindowConfig,
+    // Duration of a game update.
+    pub tick: Duration,
+    // Most updates run in a single frame. When the game falls further behind, eg. after the
+    // window was dragged, the lost time is dropped rather than running a burst of updates.
+    pub max_ticks_per_frame: u32,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            window: WindowConfig::default(),
+            tick: Duration::from_nanos(1_000_000_000 / 60),
+            max_ticks_per_frame: 5,
+        }
+    }
+}
+
+// Owns the window and the event loop, and runs a `Game` on a fixed timestep.
+pub struct App {
+    config: AppConfig,
+    gamepads: Box<dyn GamepadBackend>,
+    clock: Box<dyn Clock>,
+}
+
+impl App {
+    pub fn new(config: AppConfig) -> Self {
+        Self {
+            config,
+            gamepads: default_gamepads(),
+            clock: Box::new(RealClock::new()),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: Box<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn with_gamepads(mut self, gamepads: Box<dyn GamepadBackend>) -> Self {
+        self.gamepads = gamepads;
+        self
+    }
+
+    // Opens the window and runs the game until it's closed. `create_game` is called once the
+    // graphics are ready, so the game can load its textures.
+    pub async fn run<G: Game>(
+        self,
+        mut input: Input<G::Action>,
+        create_game: impl FnOnce(&mut Graphics) -> G,
+    ) -> ! {
+        let Self {
+            config,
+            mut gamepads,
+            mut clock,
+        } = self;
+        let event_loop = EventLoop::new();
+        let window = config.window.build(&event_loop).unwrap();
+
+        input.set_scale_factor(window.scale_factor());
+        let mut graphics = Graphics::new(&window).await.unwrap();
+        graphics.virtual_resolution = config.window.virtual_resolution;
+        let mut game = create_game(&mut graphics);
+
+        let mut time = Time::new(config.tick);
+        let mut timestep = FixedTimestep::new(config.tick, config.max_ticks_per_frame);
+        // Don't count the time spent creating the window and the game.
+        clock.elapsed();
+        let mut modifiers = ModifiersState::empty();
+        // Whether the time was paused because the window lost focus, rather than by the game.
+        let mut paused_by_focus = false;
+
+        event_loop.run(move |event, _, control_flow| match event {
+            Event::WindowEvent {
+                ref event,
+                window_id,
+            } if window_id == window.id() => {
+                match event {
+                    WindowEvent::CloseRequested => {
+                        game.on_exit();
+                        *control_flow = ControlFlow::Exit;
+                        return;
+                    }
+                    WindowEvent::Resized(physical_size) => {
+                        graphics.resize(*physical_size);
+                    }
+                    WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                        // new_inner_size is &&mut so w have to dereference it twice
+                        graphics.resize(**new_inner_size);
+                    }
+                    WindowEvent::ModifiersChanged(state) => modifiers = *state,
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                virtual_keycode: Some(VirtualKeyCode::Return),
+                                state: ElementState::Pressed,
+                                ..
+                            },
+                        ..
+                    } if modifiers.alt() && config.window.fullscreen_toggle => {
+                        config.window.toggle_fullscreen(&window);
+                    }
+                    WindowEvent::Focused(false)
+                        if config.window.pause_on_focus_loss && !time.is_paused() =>
+                    {
+                        time.set_paused(true);
+                        paused_by_focus = true;
+                    }
+                    WindowEvent::Focused(true) if paused_by_focus => {
+                        time.set_paused(false);
+                        paused_by_focus = false;
+                    }
+                    _ => {}
+                }
+                input.handle_event(event);
+                game.on_event(event, &mut time);
+            }
+            Event::RedrawRequested(window_id) if window_id == window.id() => {
+                let ticks = timestep.add_frame(clock.elapsed(), &time);
+
+                input.poll_gamepads(gamepads.as_mut());
+                for _ in 0..ticks {
+                    time.advance();
+                    if !game.begin_tick(&mut input, &time) {
+                        game.on_exit();
+                        *control_flow = ControlFlow::Exit;
+                        return;
+                    }
+                    game.update(&input, &time);
+                    if game.should_exit() {
+                        game.on_exit();
+                        *control_flow = ControlFlow::Exit;
+                        return;
+                    }
+                }
+
+                let alpha = timestep.alpha(&time);
+                let mut canvas = Canvas::new(&mut graphics);
+                game.render(&mut canvas, alpha);
+            }
+            Event::MainEventsCleared => {
+                // RedrawRequested will only trigger once, unless we manually
+                // request it.
+                window.request_redraw();
+            }
+            _ => {}
+        })
+    }
+}
+
+#[cfg(feature = "gilrs")]
+fn default_gamepads() -> Box<dyn GamepadBackend> {
+    match crate::input::gamepad::GilrsBackend::new() {
+        Ok(backend) => Box::new(backend),
+        Err(e) => {
+            log::warn!("{}, gamepads are disabled", e);
+            Box::new(VirtualGamepads::new())
+        }
+    }
+}
+
+// Without gilrs no real gamepads are available, so nothing is ever connected.
+#[cfg(not(feature = "gilrs"))]
+fn default_gamepads() -> Box<dyn GamepadBackend> {
+    Box::new(VirtualGamepads::new())
+}
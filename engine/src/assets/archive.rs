@@ -0,0 +1,322 @@
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8; 4] = b"BJPK";
+// Bumped whenever the layout changes, archives are rebuilt rather than converted.
+const FORMAT_VERSION: u32 = 1;
+// Magic, format version and index length.
+const HEADER_LEN: usize = 16;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Compression {
+    None,
+    Deflate,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct Entry {
+    // Offset of the blob from the end of the index.
+    offset: u64,
+    compressed_len: u64,
+    len: u64,
+    compression: Compression,
+    // CRC-32 of the uncompressed content.
+    hash: u32,
+}
+
+impl Entry {
+    // Where the blob is in the archive, `None` if it can't be addressed.
+    fn blob_range(&self, blobs_start: usize) -> Option<Range<usize>> {
+        let start = blobs_start.checked_add(usize::try_from(self.offset).ok()?)?;
+        Some(start..start.checked_add(usize::try_from(self.compressed_len).ok()?)?)
+    }
+}
+
+// Many asset files packed into one: a header, an index of the files, then their contents, each
+// compressed on its own so any file can be read without the others.
+pub struct Archive {
+    data: Cow<'static, [u8]>,
+    index: BTreeMap<String, Entry>,
+    blobs_start: usize,
+}
+
+impl Archive {
+    pub fn from_bytes(data: impl Into<Cow<'static, [u8]>>) -> anyhow::Result<Self> {
+        let data = data.into();
+        if data.len() < HEADER_LEN || &data[..4] != MAGIC {
+            anyhow::bail!("Not an asset archive");
+        }
+        let version = u32::from_le_bytes(data[4..8].try_into()?);
+        if version != FORMAT_VERSION {
+            anyhow::bail!(
+                "Unsupported archive version {}, expected {}",
+                version,
+                FORMAT_VERSION
+            );
+        }
+        let index_len = u64::from_le_bytes(data[8..16].try_into()?);
+        let blobs_start = usize::try_from(index_len)
+            .ok()
+            .and_then(|len| HEADER_LEN.checked_add(len))
+            .ok_or_else(|| anyhow::anyhow!("Truncated archive index"))?;
+        let index = bincode::deserialize(
+            data.get(HEADER_LEN..blobs_start)
+                .ok_or_else(|| anyhow::anyhow!("Truncated archive index"))?,
+        )?;
+        Ok(Self {
+            data,
+            index,
+            blobs_start,
+        })
+    }
+
+    // An archive included in the executable, eg. with `include_bytes!`.
+    pub fn embedded(data: &'static [u8]) -> anyhow::Result<Self> {
+        Self::from_bytes(data)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let data = fs::read(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+        Self::from_bytes(data)
+            .map_err(|e| anyhow::anyhow!("Failed to open {}: {}", path.display(), e))
+    }
+
+    pub fn contains(&self, path: &str) -> bool {
+        self.index.contains_key(path)
+    }
+
+    // The paths of the files, sorted.
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.index.keys().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    // Decompresses a file, checking it against its hash.
+    pub fn read(&self, path: &str) -> anyhow::Result<Vec<u8>> {
+        let entry = self
+            .index
+            .get(path)
+            .ok_or_else(|| anyhow::anyhow!("No such file in the archive"))?;
+        let blob = entry
+            .blob_range(self.blobs_start)
+            .and_then(|range| self.data.get(range))
+            .ok_or_else(|| anyhow::anyhow!("Truncated archive"))?;
+        let bytes = match entry.compression {
+            Compression::None => blob.to_vec(),
+            Compression::Deflate => {
+                // The length comes from the file so it isn't used to allocate. Decompressing
+                // stops one byte past it, which is enough to tell it was wrong.
+                let mut bytes = vec![];
+                DeflateDecoder::new(blob)
+                    .take(entry.len.saturating_add(1))
+                    .read_to_end(&mut bytes)?;
+                bytes
+            }
+        };
+        if bytes.len() as u64 != entry.len || crc32fast::hash(&bytes) != entry.hash {
+            anyhow::bail!("Corrupted file in the archive");
+        }
+        Ok(bytes)
+    }
+}
+
+// Builds an archive.
+#[derive(Default)]
+pub struct ArchiveWriter {
+    files: BTreeMap<String, Vec<u8>>,
+}
+
+impl ArchiveWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Adds a file, `path` being how assets will refer to it, eg. "levels/level1.ron".
+    pub fn add(&mut self, path: impl Into<String>, bytes: Vec<u8>) {
+        self.files.insert(path.into(), bytes);
+    }
+
+    // Adds all the files under `dir`, recursively.
+    pub fn add_dir(&mut self, dir: impl AsRef<Path>) -> anyhow::Result<()> {
+        let dir = dir.as_ref();
+        for path in files_under(dir)? {
+            let bytes = fs::read(&path)
+                .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+            self.add(archive_path(path.strip_prefix(dir)?), bytes);
+        }
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    // Compresses the files and lays out the archive. Files that don't compress are stored as
+    // they are.
+    pub fn finish(&self) -> anyhow::Result<Vec<u8>> {
+        let mut index = BTreeMap::new();
+        let mut blobs = vec![];
+        for (path, bytes) in &self.files {
+            let mut encoder = DeflateEncoder::new(vec![], flate2::Compression::best());
+            encoder.write_all(bytes)?;
+            let deflated = encoder.finish()?;
+            let (compression, blob) = if deflated.len() < bytes.len() {
+                (Compression::Deflate, &deflated[..])
+            } else {
+                (Compression::None, &bytes[..])
+            };
+            index.insert(
+                path.clone(),
+                Entry {
+                    offset: blobs.len() as u64,
+                    compressed_len: blob.len() as u64,
+                    len: bytes.len() as u64,
+                    compression,
+                    hash: crc32fast::hash(bytes),
+                },
+            );
+            blobs.extend_from_slice(blob);
+        }
+
+        let index = bincode::serialize(&index)?;
+        let mut data = Vec::with_capacity(HEADER_LEN + index.len() + blobs.len());
+        data.extend_from_slice(MAGIC);
+        data.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        data.extend_from_slice(&(index.len() as u64).to_le_bytes());
+        data.extend_from_slice(&index);
+        data.extend_from_slice(&blobs);
+        Ok(data)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        fs::write(path, self.finish()?)
+            .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))
+    }
+}
+
+// How an archive refers to a relative path: with forward slashes on every platform.
+pub(crate) fn archive_path(path: &Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn files_under(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    let entries = fs::read_dir(dir)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", dir.display(), e))?;
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_dir() {
+            files.extend(files_under(&path)?);
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn archive() -> Vec<u8> {
+        let mut writer = ArchiveWriter::new();
+        writer.add("levels/level1.ron", b"(name: \"one\")".repeat(20));
+        writer.add("empty.txt", vec![]);
+        writer.add("noise.bin", (0..=255).collect());
+        writer.finish().unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let archive = Archive::from_bytes(archive()).unwrap();
+        assert_eq!(archive.len(), 3);
+        assert_eq!(
+            archive.paths().collect::<Vec<_>>(),
+            ["empty.txt", "levels/level1.ron", "noise.bin"]
+        );
+        assert_eq!(
+            archive.index["levels/level1.ron"].compression,
+            Compression::Deflate
+        );
+        assert_eq!(archive.index["noise.bin"].compression, Compression::None);
+        assert_eq!(
+            archive.read("levels/level1.ron").unwrap(),
+            b"(name: \"one\")".repeat(20)
+        );
+        assert_eq!(archive.read("empty.txt").unwrap(), b"");
+        assert_eq!(
+            archive.read("noise.bin").unwrap(),
+            (0..=255).collect::<Vec<u8>>()
+        );
+        assert!(!archive.contains("missing.txt"));
+        assert!(archive.read("missing.txt").is_err());
+    }
+
+    #[test]
+    fn bad_magic() {
+        let mut data = archive();
+        data[0] = b'X';
+        assert!(Archive::from_bytes(data).is_err());
+        assert!(Archive::from_bytes(&b"BJ"[..]).is_err());
+    }
+
+    #[test]
+    fn truncated() {
+        let data = archive();
+        // Into the index.
+        assert!(Archive::from_bytes(data[..HEADER_LEN + 4].to_vec()).is_err());
+        // Into the last file.
+        let archive = Archive::from_bytes(data[..data.len() - 1].to_vec()).unwrap();
+        assert!(archive.read("levels/level1.ron").is_ok());
+        assert!(archive.read("noise.bin").is_err());
+    }
+
+    #[test]
+    fn huge_lengths() {
+        let mut data = archive();
+        data[8..16].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(Archive::from_bytes(data).is_err());
+
+        let mut archive = Archive::from_bytes(archive()).unwrap();
+        let entry = archive.index.get_mut("noise.bin").unwrap();
+        entry.offset = u64::MAX;
+        assert!(archive.read("noise.bin").is_err());
+        let entry = archive.index.get_mut("levels/level1.ron").unwrap();
+        entry.len = u64::MAX;
+        assert!(archive.read("levels/level1.ron").is_err());
+    }
+
+    #[test]
+    fn crc_mismatch() {
+        let mut data = archive();
+        // The last byte of the last file, which is stored uncompressed.
+        *data.last_mut().unwrap() ^= 1;
+        let archive = Archive::from_bytes(data).unwrap();
+        assert!(archive.read("levels/level1.ron").is_ok());
+        assert!(archive.read("noise.bin").is_err());
+    }
+}
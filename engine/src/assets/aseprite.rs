@@ -0,0 +1,177 @@
+use crate::animation::{Animation, Frame, PlayMode};
+use crate::assets::atlas::{AtlasFrame, AtlasLayout};
+use serde::de::{MapAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+use std::fmt;
+use winit::dpi::LogicalSize;
+
+#[derive(Deserialize)]
+struct JsonFrame {
+    // Missing from the array format of older versions.
+    #[serde(default)]
+    filename: String,
+    frame: AtlasFrame,
+    // In milliseconds.
+    duration: f32,
+}
+
+// Aseprite can export frames as an array or as an object keyed by name. The order matters as
+// tags refer to frames by index, so objects are read in file order.
+struct JsonFrames(Vec<JsonFrame>);
+
+impl<'de> Deserialize<'de> for JsonFrames {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct FramesVisitor;
+
+        impl<'de> Visitor<'de> for FramesVisitor {
+            type Value = JsonFrames;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an array or a map of frames")
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> Result<JsonFrames, A::Error> {
+                let mut frames = vec![];
+                while let Some(frame) = seq.next_element()? {
+                    frames.push(frame);
+                }
+                Ok(JsonFrames(frames))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<JsonFrames, A::Error> {
+                let mut frames = vec![];
+                while let Some((filename, frame)) = map.next_entry::<String, JsonFrame>()? {
+                    frames.push(JsonFrame { filename, ..frame });
+                }
+                Ok(JsonFrames(frames))
+            }
+        }
+
+        deserializer.deserialize_any(FramesVisitor)
+    }
+}
+
+#[derive(Deserialize)]
+struct JsonTag {
+    name: String,
+    from: usize,
+    to: usize,
+    #[serde(default)]
+    direction: String,
+    // A number, as a string. Missing, or 0, when the tag loops forever.
+    repeat: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct JsonSize {
+    w: f32,
+    h: f32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonMeta {
+    image: String,
+    size: JsonSize,
+    #[serde(default)]
+    frame_tags: Vec<JsonTag>,
+}
+
+#[derive(Deserialize)]
+struct JsonSheet {
+    frames: JsonFrames,
+    meta: JsonMeta,
+}
+
+// A sprite sheet exported by Aseprite (File > Export Sprite Sheet, with JSON data and tags
+// enabled): the layout of its frames, and an animation for each tag.
+pub struct AsepriteSheet {
+    pub layout: AtlasLayout,
+    pub animations: HashMap<String, Animation>,
+}
+
+impl AsepriteSheet {
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        let sheet: JsonSheet = serde_json::from_str(json)?;
+        let layout = AtlasLayout {
+            image: sheet.meta.image,
+            width: sheet.meta.size.w,
+            height: sheet.meta.size.h,
+            frames: sheet
+                .frames
+                .0
+                .iter()
+                .enumerate()
+                .map(|(index, frame)| (frame_name(index, frame), frame.frame))
+                .collect(),
+        };
+        let frames: Vec<_> = sheet
+            .frames
+            .0
+            .iter()
+            .enumerate()
+            .map(|(index, frame)| Frame {
+                texture: layout
+                    .texture_coords(&frame_name(index, frame))
+                    .unwrap_or_else(|| (0.0, 0.0, 0.0, 0.0).into()),
+                size: LogicalSize::new(frame.frame.width, frame.frame.height),
+                duration: frame.duration / 1000.0,
+            })
+            .collect();
+
+        let mut animations = HashMap::new();
+        for tag in sheet.meta.frame_tags {
+            if tag.from > tag.to || tag.to >= frames.len() {
+                anyhow::bail!(
+                    "Tag {} refers to frames {}-{} of {}",
+                    tag.name,
+                    tag.from,
+                    tag.to,
+                    frames.len()
+                );
+            }
+            let mut tag_frames = frames[tag.from..=tag.to].to_vec();
+            let mode = match tag.direction.as_str() {
+                "" | "forward" => PlayMode::Forward,
+                "reverse" => PlayMode::Reverse,
+                "pingpong" => PlayMode::PingPong,
+                // Ping-pong starting from the last frame.
+                "pingpong_reverse" => {
+                    tag_frames.reverse();
+                    PlayMode::PingPong
+                }
+                direction => anyhow::bail!("Unknown direction {} for tag {}", direction, tag.name),
+            };
+            let repeat = match tag.repeat {
+                Some(repeat) => Some(repeat.parse().map_err(|_| {
+                    anyhow::anyhow!("Invalid repeat count {} for tag {}", repeat, tag.name)
+                })?),
+                None => None,
+            }
+            .filter(|repeat| *repeat != 0);
+            animations.insert(
+                tag.name,
+                Animation {
+                    frames: tag_frames,
+                    mode,
+                    repeat,
+                    events: vec![],
+                },
+            );
+        }
+        Ok(Self { layout, animations })
+    }
+}
+
+// Frames without a name, in the array format, are named after their index.
+fn frame_name(index: usize, frame: &JsonFrame) -> String {
+    if frame.filename.is_empty() {
+        index.to_string()
+    } else {
+        frame.filename.clone()
+    }
+}
@@ -0,0 +1,118 @@
+use crate::animation::Animation;
+use crate::assets::Handle;
+use crate::rendering::shapes::Sprite;
+use crate::rendering::texture::Texture;
+use cgmath::{Vector2, Vector4};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+// Where a frame is in the atlas image, in pixels.
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize)]
+pub struct AtlasFrame {
+    pub x: f32,
+    pub y: f32,
+    #[serde(rename = "w")]
+    pub width: f32,
+    #[serde(rename = "h")]
+    pub height: f32,
+}
+
+#[derive(Deserialize)]
+struct JsonFrame {
+    frame: AtlasFrame,
+}
+
+#[derive(Deserialize)]
+struct JsonSize {
+    w: f32,
+    h: f32,
+}
+
+#[derive(Deserialize)]
+struct JsonMeta {
+    image: String,
+    size: JsonSize,
+}
+
+#[derive(Deserialize)]
+struct JsonAtlas {
+    frames: HashMap<String, JsonFrame>,
+    meta: JsonMeta,
+}
+
+// The frames of an atlas, as exported by TexturePacker and compatible packers in their JSON
+// hash format.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AtlasLayout {
+    // Path of the image, relative to the JSON file.
+    pub image: String,
+    pub width: f32,
+    pub height: f32,
+    pub frames: HashMap<String, AtlasFrame>,
+}
+
+impl AtlasLayout {
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        let atlas: JsonAtlas = serde_json::from_str(json)?;
+        Ok(Self {
+            image: atlas.meta.image,
+            width: atlas.meta.size.w,
+            height: atlas.meta.size.h,
+            frames: atlas
+                .frames
+                .into_iter()
+                .map(|(name, frame)| (name, frame.frame))
+                .collect(),
+        })
+    }
+
+    pub fn frame(&self, name: &str) -> Option<AtlasFrame> {
+        self.frames.get(name).copied()
+    }
+
+    // The texture coordinates of the frame, as used by `Sprite::texture`.
+    pub fn texture_coords(&self, name: &str) -> Option<Vector4<f32>> {
+        let frame = self.frame(name)?;
+        Some(
+            (
+                frame.x / self.width,
+                frame.y / self.height,
+                (frame.x + frame.width) / self.width,
+                (frame.y + frame.height) / self.height,
+            )
+                .into(),
+        )
+    }
+
+    // A sprite showing the frame at its size in pixels.
+    pub fn sprite(&self, name: &str, position: Vector2<f32>) -> Option<Sprite> {
+        let frame = self.frame(name)?;
+        Some(Sprite {
+            position,
+            size: (frame.width, frame.height).into(),
+            texture: self.texture_coords(name)?,
+        })
+    }
+}
+
+// A texture holding many frames, loaded with `Assets::load_atlas` or `Assets::load_aseprite`.
+pub struct Atlas {
+    pub layout: AtlasLayout,
+    pub texture: Handle<Texture>,
+    // Named after the tags of Aseprite sheets, empty for other atlases.
+    pub animations: HashMap<String, Animation>,
+}
+
+impl Atlas {
+    pub fn animation(&self, name: &str) -> Option<&Animation> {
+        self.animations.get(name)
+    }
+}
+
+impl std::ops::Deref for Atlas {
+    type Target = AtlasLayout;
+
+    fn deref(&self) -> &AtlasLayout {
+        &self.layout
+    }
+}
@@ -0,0 +1,430 @@
+pub mod archive;
+pub mod aseprite;
+pub mod atlas;
+pub mod source;
+
+pub use archive::{Archive, ArchiveWriter};
+pub use aseprite::AsepriteSheet;
+pub use atlas::{Atlas, AtlasFrame, AtlasLayout};
+pub use source::{AssetSource, Directory};
+
+use crate::animation::Animation;
+use crate::audio::{Music, Sound};
+use crate::rendering::texture::Texture;
+use crate::rendering::Graphics;
+use serde::de::DeserializeOwned;
+use std::any::{Any, TypeId};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant, SystemTime};
+use wgpu_glyph::ab_glyph::FontArc;
+
+// How often watched files are checked for changes.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+struct Slot<T> {
+    path: PathBuf,
+    value: RefCell<Rc<T>>,
+    version: Cell<u32>,
+}
+
+// A loaded asset. Clones share the same asset, which stays loaded as long as a handle to it
+// exists. Hot reloading swaps the asset behind the handle, so code that keeps derived data
+// around should compare `version`s.
+pub struct Handle<T> {
+    slot: Rc<Slot<T>>,
+}
+
+impl<T> Handle<T> {
+    // An asset made in code rather than loaded, `path` is only used to identify it.
+    pub fn new(path: impl Into<PathBuf>, value: T) -> Self {
+        Self {
+            slot: Rc::new(Slot {
+                path: path.into(),
+                value: RefCell::new(Rc::new(value)),
+                version: Cell::new(0),
+            }),
+        }
+    }
+
+    // The path the asset was loaded from, relative to the asset root.
+    pub fn path(&self) -> &Path {
+        &self.slot.path
+    }
+
+    // The current asset. Hold on to the result only briefly, it isn't updated by reloads.
+    pub fn get(&self) -> Rc<T> {
+        self.slot.value.borrow().clone()
+    }
+
+    // Changes every time the asset is reloaded.
+    pub fn version(&self) -> u32 {
+        self.slot.version.get()
+    }
+
+    // Whether both are handles to the same asset.
+    pub fn ptr_eq(&self, other: &Handle<T>) -> bool {
+        Rc::ptr_eq(&self.slot, &other.slot)
+    }
+
+    fn set(&self, value: T) {
+        *self.slot.value.borrow_mut() = Rc::new(value);
+        self.touch();
+    }
+
+    // Marks the asset as changed in place.
+    fn touch(&self) {
+        self.slot
+            .version
+            .set(self.slot.version.get().wrapping_add(1));
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            slot: self.slot.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Handle").field(&self.slot.path).finish()
+    }
+}
+
+// Lets the cache hold handles of any type.
+trait Cached {
+    fn as_any(&self) -> &dyn Any;
+
+    // Whether only the cache still holds the asset.
+    fn is_unused(&self) -> bool;
+}
+
+impl<T: 'static> Cached for Handle<T> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn is_unused(&self) -> bool {
+        Rc::strong_count(&self.slot) == 1
+    }
+}
+
+type Reload = Rc<dyn Fn(&mut Assets, &Graphics, Vec<u8>) -> anyhow::Result<()>>;
+
+// Reads the layout and animations of an atlas from its JSON file.
+type ParseSheet = fn(&str) -> anyhow::Result<(AtlasLayout, HashMap<String, Animation>)>;
+
+struct Entry {
+    handle: Box<dyn Cached>,
+    // Loads the file again into the handle.
+    reload: Reload,
+    modified: Option<SystemTime>,
+}
+
+// Loads assets from a source: the files under a directory, or an archive. Loading the same path
+// as the same type twice returns the same asset.
+pub struct Assets {
+    source: Box<dyn AssetSource>,
+    cache: HashMap<(TypeId, PathBuf), Entry>,
+    // When the files were last checked for changes, if watching.
+    last_poll: Option<Instant>,
+}
+
+impl Assets {
+    // Loads the files under `root`.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self::from_source(Directory::new(root))
+    }
+
+    pub fn from_source(source: impl AssetSource + 'static) -> Self {
+        Self {
+            source: Box::new(source),
+            cache: HashMap::new(),
+            last_poll: None,
+        }
+    }
+
+    pub fn exists(&self, path: impl AsRef<Path>) -> bool {
+        self.source.exists(path.as_ref())
+    }
+
+    // Number of assets in the cache.
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    // Reads a file, without caching it.
+    pub fn read(&self, path: impl AsRef<Path>) -> anyhow::Result<Vec<u8>> {
+        self.source.read(path.as_ref())
+    }
+
+    // How to name the file in errors.
+    fn describe(&self, path: &Path) -> PathBuf {
+        self.source
+            .file_path(path)
+            .unwrap_or_else(|| path.to_path_buf())
+    }
+
+    // Only files on disk can be watched.
+    fn modified(&self, path: &Path) -> Option<SystemTime> {
+        fs::metadata(self.source.file_path(path)?)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+
+    // Returns the cached asset, or reads the file and turns it into an asset with `load`.
+    // `reload` updates the handle when the file changes.
+    fn load<T: 'static>(
+        &mut self,
+        path: impl AsRef<Path>,
+        load: impl FnOnce(&mut Self, Vec<u8>) -> anyhow::Result<T>,
+        reload: impl Fn(&mut Self, &Graphics, &Handle<T>, Vec<u8>) -> anyhow::Result<()> + 'static,
+    ) -> anyhow::Result<Handle<T>> {
+        let path = path.as_ref().to_path_buf();
+        let key = (TypeId::of::<T>(), path.clone());
+        if let Some(entry) = self.cache.get(&key) {
+            if let Some(handle) = entry.handle.as_any().downcast_ref::<Handle<T>>() {
+                return Ok(handle.clone());
+            }
+        }
+
+        let modified = self.modified(&path);
+        let bytes = self.read(&path)?;
+        let value = load(self, bytes).map_err(|e| {
+            anyhow::anyhow!("Failed to load {}: {}", self.describe(&path).display(), e)
+        })?;
+        let handle = Handle::new(path, value);
+        // Weak, so the cache alone doesn't keep the asset in use.
+        let weak = Rc::downgrade(&handle.slot);
+        let reload: Reload = Rc::new(move |assets, graphics, bytes| match weak.upgrade() {
+            Some(slot) => reload(assets, graphics, &Handle { slot }, bytes),
+            None => Ok(()),
+        });
+        self.cache.insert(
+            key,
+            Entry {
+                handle: Box::new(handle.clone()),
+                reload,
+                modified,
+            },
+        );
+        Ok(handle)
+    }
+
+    // Loads an asset that doesn't depend on the graphics or other assets.
+    fn load_decoded<T: 'static>(
+        &mut self,
+        path: impl AsRef<Path>,
+        decode: fn(Vec<u8>) -> anyhow::Result<T>,
+    ) -> anyhow::Result<Handle<T>> {
+        self.load(
+            path,
+            |_, bytes| decode(bytes),
+            move |_, _, handle, bytes| {
+                handle.set(decode(bytes)?);
+                Ok(())
+            },
+        )
+    }
+
+    pub fn load_bytes(&mut self, path: impl AsRef<Path>) -> anyhow::Result<Handle<Vec<u8>>> {
+        self.load_decoded(path, Ok)
+    }
+
+    // Loads a PNG or JPEG image as a texture matching the surface of `graphics`. Reloading
+    // rewrites the same GPU texture when the size is unchanged.
+    pub fn load_texture(
+        &mut self,
+        graphics: &Graphics,
+        path: impl AsRef<Path>,
+    ) -> anyhow::Result<Handle<Texture>> {
+        let path = path.as_ref();
+        let label = path.to_string_lossy().into_owned();
+        let reload_label = label.clone();
+        self.load(
+            path,
+            |_, bytes| {
+                Texture::from_bytes(
+                    &graphics.device,
+                    &graphics.queue,
+                    &bytes,
+                    &label,
+                    Texture::format_for_surface(graphics.configuration.format),
+                )
+            },
+            move |_, graphics, handle, bytes| {
+                let image = image::load_from_memory(&bytes)?;
+                if handle.get().write(&graphics.queue, &image) {
+                    handle.touch();
+                } else {
+                    handle.set(Texture::from_image(
+                        &graphics.device,
+                        &graphics.queue,
+                        &image,
+                        Some(&reload_label),
+                        Texture::format_for_surface(graphics.configuration.format),
+                    )?);
+                }
+                Ok(())
+            },
+        )
+    }
+
+    // Loads an atlas from its JSON file, along with the image it refers to.
+    pub fn load_atlas(
+        &mut self,
+        graphics: &Graphics,
+        path: impl AsRef<Path>,
+    ) -> anyhow::Result<Handle<Atlas>> {
+        self.load_sheet(graphics, path, |json| {
+            Ok((AtlasLayout::from_json(json)?, HashMap::new()))
+        })
+    }
+
+    // Loads the JSON data and image exported by Aseprite as an atlas, with an animation for
+    // each tag. Shares the cache with `load_atlas`, so load a file with only one of them.
+    pub fn load_aseprite(
+        &mut self,
+        graphics: &Graphics,
+        path: impl AsRef<Path>,
+    ) -> anyhow::Result<Handle<Atlas>> {
+        self.load_sheet(graphics, path, |json| {
+            let sheet = AsepriteSheet::from_json(json)?;
+            Ok((sheet.layout, sheet.animations))
+        })
+    }
+
+    fn load_sheet(
+        &mut self,
+        graphics: &Graphics,
+        path: impl AsRef<Path>,
+        parse: ParseSheet,
+    ) -> anyhow::Result<Handle<Atlas>> {
+        let path = path.as_ref();
+        let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        let load = move |assets: &mut Self, graphics: &Graphics, bytes: Vec<u8>| {
+            let (layout, animations) = parse(std::str::from_utf8(&bytes)?)?;
+            let texture = assets.load_texture(graphics, dir.join(&layout.image))?;
+            Ok(Atlas {
+                layout,
+                texture,
+                animations,
+            })
+        };
+        let reload = load.clone();
+        self.load(
+            path,
+            |assets, bytes| load(assets, graphics, bytes),
+            move |assets, graphics, handle, bytes| {
+                handle.set(reload(assets, graphics, bytes)?);
+                Ok(())
+            },
+        )
+    }
+
+    // Loads a TrueType or OpenType font.
+    pub fn load_font(&mut self, path: impl AsRef<Path>) -> anyhow::Result<Handle<FontArc>> {
+        self.load_decoded(path, |bytes| Ok(FontArc::try_from_vec(bytes)?))
+    }
+
+    // Loads and decodes a WAV or Ogg Vorbis sound.
+    pub fn load_sound(&mut self, path: impl AsRef<Path>) -> anyhow::Result<Handle<Sound>> {
+        self.load_decoded(path, |bytes| Sound::from_bytes(&bytes))
+    }
+
+    // Opens an Ogg Vorbis file for streaming. Music isn't cached, since it's read as it plays:
+    // from the file when there's one, otherwise from its encoded bytes.
+    pub fn load_music(&self, path: impl AsRef<Path>) -> anyhow::Result<Music> {
+        let path = path.as_ref();
+        match self.source.file_path(path) {
+            Some(file) => Music::load(file),
+            None => Music::from_bytes(self.read(path)?)
+                .map_err(|e| anyhow::anyhow!("Failed to open {}: {}", path.display(), e)),
+        }
+    }
+
+    // Loads a RON data file.
+    pub fn load_data<T: DeserializeOwned + 'static>(
+        &mut self,
+        path: impl AsRef<Path>,
+    ) -> anyhow::Result<Handle<T>> {
+        self.load_decoded(path, |bytes| Ok(ron::de::from_bytes(&bytes)?))
+    }
+
+    // Drops the cached assets no handle refers to anymore, returning how many were dropped.
+    pub fn unload_unused(&mut self) -> usize {
+        let before = self.cache.len();
+        // Dropping an atlas frees its texture, so repeat until nothing changes.
+        loop {
+            let len = self.cache.len();
+            self.cache.retain(|_, entry| !entry.handle.is_unused());
+            if self.cache.len() == len {
+                break;
+            }
+        }
+        before - self.cache.len()
+    }
+
+    // Starts watching the loaded files, see `reload_changed`. Meant for development, so assets
+    // can be edited while the game runs. Archives can't be watched.
+    pub fn watch(&mut self) {
+        self.last_poll = Some(Instant::now());
+    }
+
+    pub fn is_watching(&self) -> bool {
+        self.last_poll.is_some()
+    }
+
+    // When watching, reloads the assets whose file changed since they were loaded, returning
+    // their paths. Files are checked at most every `POLL_INTERVAL`, so this can be called every
+    // frame. An asset that fails to reload keeps its previous value.
+    pub fn reload_changed(&mut self, graphics: &Graphics) -> Vec<PathBuf> {
+        match self.last_poll {
+            Some(last_poll) if last_poll.elapsed() >= POLL_INTERVAL => {
+                self.last_poll = Some(Instant::now())
+            }
+            _ => return vec![],
+        }
+
+        let changed: Vec<_> = self
+            .cache
+            .iter()
+            .filter_map(|(key, entry)| {
+                let modified = self.modified(&key.1);
+                (modified.is_some() && modified != entry.modified).then(|| key.clone())
+            })
+            .collect();
+        let mut reloaded = vec![];
+        for key in changed {
+            let path = key.1.clone();
+            let modified = self.modified(&path);
+            let Some(entry) = self.cache.get_mut(&key) else {
+                continue;
+            };
+            entry.modified = modified;
+            let reload = entry.reload.clone();
+            let result = self
+                .read(&path)
+                .and_then(|bytes| reload(self, graphics, bytes));
+            match result {
+                Ok(()) => {
+                    log::info!("Reloaded {}", path.display());
+                    reloaded.push(path);
+                }
+                Err(e) => log::warn!("Failed to reload {}: {}", self.describe(&path).display(), e),
+            }
+        }
+        reloaded
+    }
+}
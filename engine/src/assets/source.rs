@@ -0,0 +1,59 @@
+use crate::assets::archive::{archive_path, Archive};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Where `Assets` reads files from. Paths are relative to the asset root.
+pub trait AssetSource {
+    // Errors name the file.
+    fn read(&self, path: &Path) -> anyhow::Result<Vec<u8>>;
+
+    fn exists(&self, path: &Path) -> bool;
+
+    // The file on disk, when the source is made of loose files. Hot reloading and streaming
+    // music from disk need it.
+    fn file_path(&self, _path: &Path) -> Option<PathBuf> {
+        None
+    }
+}
+
+// Loose files under a root directory.
+pub struct Directory {
+    root: PathBuf,
+}
+
+impl Directory {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+}
+
+impl AssetSource for Directory {
+    fn read(&self, path: &Path) -> anyhow::Result<Vec<u8>> {
+        let path = self.root.join(path);
+        fs::read(&path).map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.root.join(path).is_file()
+    }
+
+    fn file_path(&self, path: &Path) -> Option<PathBuf> {
+        Some(self.root.join(path))
+    }
+}
+
+impl AssetSource for Archive {
+    fn read(&self, path: &Path) -> anyhow::Result<Vec<u8>> {
+        Archive::read(self, &archive_path(path)).map_err(|e| {
+            anyhow::anyhow!("Failed to read {} from the archive: {}", path.display(), e)
+        })
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.contains(&archive_path(path))
+    }
+}
@@ -0,0 +1,77 @@
+use crate::audio::Mixer;
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{SampleFormat, Stream};
+use std::sync::{Arc, Mutex};
+
+// Opens the default output device, with a mixer running at its sample rate.
+pub(super) fn open() -> anyhow::Result<(Arc<Mutex<Mixer>>, Stream)> {
+    let device = cpal::default_host()
+        .default_output_device()
+        .ok_or_else(|| anyhow::anyhow!("No audio output device"))?;
+    let supported = device.default_output_config()?;
+    let format = supported.sample_format();
+    let config: cpal::StreamConfig = supported.into();
+
+    let mixer = Arc::new(Mutex::new(Mixer::new(config.sample_rate.0)));
+    let mut feed = Feed {
+        mixer: mixer.clone(),
+        stereo: vec![],
+        channels: config.channels as usize,
+    };
+    let on_error = |e| log::error!("Audio stream error: {}", e);
+
+    let stream = match format {
+        SampleFormat::F32 => device.build_output_stream(
+            &config,
+            move |data: &mut [f32], _| feed.fill(data, |s| s),
+            on_error,
+            None,
+        )?,
+        SampleFormat::I16 => device.build_output_stream(
+            &config,
+            move |data: &mut [i16], _| feed.fill(data, |s| (s * i16::MAX as f32) as i16),
+            on_error,
+            None,
+        )?,
+        SampleFormat::U16 => device.build_output_stream(
+            &config,
+            move |data: &mut [u16], _| {
+                feed.fill(data, |s| ((s * 0.5 + 0.5) * u16::MAX as f32) as u16)
+            },
+            on_error,
+            None,
+        )?,
+        format => anyhow::bail!("Unsupported sample format {:?}", format),
+    };
+    stream.play()?;
+    Ok((mixer, stream))
+}
+
+// Runs the mixer on the device's audio thread.
+struct Feed {
+    mixer: Arc<Mutex<Mixer>>,
+    stereo: Vec<f32>,
+    channels: usize,
+}
+
+impl Feed {
+    // Copies stereo frames to a device with any number of channels: mono devices get the
+    // average, extra channels are silent.
+    fn fill<T: Copy>(&mut self, data: &mut [T], convert: impl Fn(f32) -> T) {
+        let frames = data.len() / self.channels;
+        self.stereo.resize(frames * 2, 0.0);
+        self.mixer.lock().unwrap().render(&mut self.stereo);
+        for (frame, lr) in data
+            .chunks_exact_mut(self.channels)
+            .zip(self.stereo.chunks_exact(2))
+        {
+            if self.channels == 1 {
+                frame[0] = convert((lr[0] + lr[1]) * 0.5);
+                continue;
+            }
+            for (channel, sample) in frame.iter_mut().enumerate() {
+                *sample = convert(lr.get(channel).copied().unwrap_or(0.0));
+            }
+        }
+    }
+}
@@ -0,0 +1,548 @@
+use crate::audio::music::{Music, MusicStream};
+use crate::audio::sound::Sound;
+use std::f32::consts::FRAC_PI_4;
+
+// Maximum number of voices playing at once, over all sounds.
+pub const MAX_VOICES: usize = 64;
+
+// Groups of sounds sharing a volume, so eg. music can be turned down without the effects.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Bus {
+    Music,
+    Sfx,
+}
+
+impl Bus {
+    pub const ALL: [Bus; 2] = [Bus::Music, Bus::Sfx];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PlayParams {
+    pub volume: f32,
+    // Playback speed: 2.0 plays an octave higher and twice as fast.
+    pub pitch: f32,
+    // -1.0 is fully left, 1.0 fully right.
+    pub pan: f32,
+    pub bus: Bus,
+}
+
+impl Default for PlayParams {
+    fn default() -> Self {
+        Self {
+            volume: 1.0,
+            pitch: 1.0,
+            pan: 0.0,
+            bus: Bus::Sfx,
+        }
+    }
+}
+
+impl PlayParams {
+    pub fn with_volume(mut self, volume: f32) -> Self {
+        self.volume = volume;
+        self
+    }
+
+    pub fn with_pitch(mut self, pitch: f32) -> Self {
+        self.pitch = pitch;
+        self
+    }
+
+    pub fn with_pan(mut self, pan: f32) -> Self {
+        self.pan = pan;
+        self
+    }
+
+    pub fn with_bus(mut self, bus: Bus) -> Self {
+        self.bus = bus;
+        self
+    }
+
+    // Constant power panning, so sounds don't get louder in the middle. Stereo sounds are
+    // balanced instead, keeping them at full volume when centred.
+    fn gains(&self, channels: u16) -> (f32, f32) {
+        let angle = (self.pan.clamp(-1.0, 1.0) + 1.0) * FRAC_PI_4;
+        let (left, right) = (angle.cos(), angle.sin());
+        if channels == 2 {
+            let norm = std::f32::consts::SQRT_2;
+            (
+                (left * norm).min(1.0) * self.volume,
+                (right * norm).min(1.0) * self.volume,
+            )
+        } else {
+            (left * self.volume, right * self.volume)
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct VoiceId(u64);
+
+struct Voice {
+    id: VoiceId,
+    sound: Sound,
+    // Position in frames of the sound, fractional when the pitch or sample rate differ.
+    position: f64,
+    step: f64,
+    gains: (f32, f32),
+    bus: Bus,
+}
+
+impl Voice {
+    fn is_finished(&self) -> bool {
+        self.position >= self.sound.frames() as f64
+    }
+
+    // Linear interpolation between the two nearest frames.
+    fn next_frame(&mut self) -> (f32, f32) {
+        let frame = self.position as usize;
+        let t = (self.position - frame as f64) as f32;
+        let sample = |channel| {
+            let a = self.sound.sample(frame, channel);
+            let b = self.sound.sample(frame + 1, channel);
+            a + (b - a) * t
+        };
+        let (left, right) = (sample(0), sample(1));
+        self.position += self.step;
+        (left * self.gains.0, right * self.gains.1)
+    }
+}
+
+// A streamed music track, fading towards `target` volume.
+struct Track {
+    stream: MusicStream,
+    // Fraction of the way from `previous` to `next`.
+    position: f64,
+    step: f64,
+    previous: [f32; 2],
+    next: [f32; 2],
+    volume: f32,
+    target: f32,
+    // Volume change per frame.
+    fade: f32,
+}
+
+impl Track {
+    fn new(mut stream: MusicStream, sample_rate: u32) -> Self {
+        let step = stream.music().sample_rate() as f64 / sample_rate as f64;
+        let previous = stream.next_frame().unwrap_or_default();
+        let next = stream.next_frame().unwrap_or_default();
+        Self {
+            stream,
+            position: 0.0,
+            step,
+            previous,
+            next,
+            volume: 1.0,
+            target: 1.0,
+            fade: 0.0,
+        }
+    }
+
+    fn fade_to(&mut self, target: f32, frames: f32) {
+        self.target = target;
+        if frames < 1.0 {
+            self.volume = target;
+        } else {
+            self.fade = (target - self.volume).abs() / frames;
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.stream.is_finished() || (self.target == 0.0 && self.volume == 0.0)
+    }
+
+    fn next_frame(&mut self) -> (f32, f32) {
+        let t = self.position as f32;
+        let sample = |channel: usize| {
+            let (a, b) = (self.previous[channel], self.next[channel]);
+            (a + (b - a) * t) * self.volume
+        };
+        let frame = (sample(0), sample(1));
+
+        self.position += self.step;
+        while self.position >= 1.0 {
+            self.position -= 1.0;
+            self.previous = self.next;
+            self.next = self.stream.next_frame().unwrap_or_default();
+        }
+        if self.volume < self.target {
+            self.volume = (self.volume + self.fade).min(self.target);
+        } else if self.volume > self.target {
+            self.volume = (self.volume - self.fade).max(self.target);
+        }
+        frame
+    }
+}
+
+// Mixes the playing voices and music into interleaved stereo samples. The mixer doesn't know
+// where the samples go: they're pulled by the sound card's thread, or by `Audio::render`.
+pub struct Mixer {
+    sample_rate: u32,
+    voices: Vec<Voice>,
+    next_id: u64,
+    // Fading out tracks come before the current one, which is last.
+    tracks: Vec<Track>,
+    bus_volumes: [f32; 2],
+    pub master_volume: f32,
+}
+
+impl Mixer {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            voices: vec![],
+            next_id: 0,
+            tracks: vec![],
+            bus_volumes: [1.0; 2],
+            master_volume: 1.0,
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn play(&mut self, sound: &Sound, params: PlayParams) -> VoiceId {
+        if let Some(max_voices) = sound.max_voices() {
+            let playing = self
+                .voices
+                .iter()
+                .filter(|v| v.sound.same_as(sound))
+                .count();
+            if playing >= max_voices {
+                // Voices are kept in start order, so the first one is the oldest.
+                let oldest = self.voices.iter().position(|v| v.sound.same_as(sound));
+                if let Some(index) = oldest {
+                    self.voices.remove(index);
+                }
+            }
+        }
+        if self.voices.len() >= MAX_VOICES {
+            self.voices.remove(0);
+        }
+
+        let id = VoiceId(self.next_id);
+        self.next_id += 1;
+        self.voices.push(Voice {
+            id,
+            sound: sound.clone(),
+            position: 0.0,
+            step: params.pitch.max(0.0) as f64 * sound.sample_rate() as f64
+                / self.sample_rate as f64,
+            gains: params.gains(sound.channels()),
+            bus: params.bus,
+        });
+        id
+    }
+
+    pub fn stop(&mut self, voice: VoiceId) {
+        self.voices.retain(|v| v.id != voice);
+    }
+
+    pub fn stop_all(&mut self) {
+        self.voices.clear();
+    }
+
+    pub fn is_playing(&self, voice: VoiceId) -> bool {
+        self.voices.iter().any(|v| v.id == voice)
+    }
+
+    pub fn active_voices(&self) -> usize {
+        self.voices.len()
+    }
+
+    pub fn bus_volume(&self, bus: Bus) -> f32 {
+        self.bus_volumes[bus.index()]
+    }
+
+    pub fn set_bus_volume(&mut self, bus: Bus, volume: f32) {
+        self.bus_volumes[bus.index()] = volume.max(0.0);
+    }
+
+    // Crossfades from the current music to `music` over `fade` seconds, or cuts straight to it
+    // when `fade` is 0. Does nothing if `music` is already the current music.
+    pub fn play_music(&mut self, music: &Music, fade: f32) -> anyhow::Result<()> {
+        if !self.is_current_music(music) {
+            self.play_stream(music.stream()?, fade);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn is_current_music(&self, music: &Music) -> bool {
+        self.current_track()
+            .is_some_and(|track| track.stream.music().same_as(music))
+    }
+
+    // Like `play_music`, with the stream opened beforehand.
+    pub(crate) fn play_stream(&mut self, stream: MusicStream, fade: f32) {
+        if self.is_current_music(stream.music()) {
+            return;
+        }
+        let mut track = Track::new(stream, self.sample_rate);
+        let frames = fade.max(0.0) * self.sample_rate as f32;
+        self.fade_out_tracks(frames);
+        track.volume = 0.0;
+        track.fade_to(1.0, frames);
+        self.tracks.push(track);
+    }
+
+    // Fades out the music over `fade` seconds.
+    pub fn stop_music(&mut self, fade: f32) {
+        self.fade_out_tracks(fade.max(0.0) * self.sample_rate as f32);
+    }
+
+    // Whether music is playing and not fading out.
+    pub fn is_music_playing(&self) -> bool {
+        self.current_track().is_some()
+    }
+
+    fn current_track(&self) -> Option<&Track> {
+        self.tracks.last().filter(|track| track.target > 0.0)
+    }
+
+    fn fade_out_tracks(&mut self, frames: f32) {
+        for track in &mut self.tracks {
+            track.fade_to(0.0, frames);
+        }
+        self.tracks.retain(|track| !track.is_finished());
+    }
+
+    // Fills `out` with interleaved stereo samples.
+    pub fn render(&mut self, out: &mut [f32]) {
+        out.fill(0.0);
+        for voice in &mut self.voices {
+            let volume = self.bus_volumes[voice.bus.index()];
+            for frame in out.chunks_exact_mut(2) {
+                if voice.is_finished() {
+                    break;
+                }
+                let (left, right) = voice.next_frame();
+                frame[0] += left * volume;
+                frame[1] += right * volume;
+            }
+        }
+        self.voices.retain(|v| !v.is_finished());
+
+        let volume = self.bus_volumes[Bus::Music.index()];
+        for track in &mut self.tracks {
+            track.stream.prepare();
+            for frame in out.chunks_exact_mut(2) {
+                if track.is_finished() {
+                    break;
+                }
+                let (left, right) = track.next_frame();
+                frame[0] += left * volume;
+                frame[1] += right * volume;
+            }
+        }
+        self.tracks.retain(|track| !track.is_finished());
+
+        for sample in out.iter_mut() {
+            *sample = (*sample * self.master_volume).clamp(-1.0, 1.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_1_SQRT_2;
+
+    const RATE: u32 = 100;
+
+    fn tone(value: f32, frames: usize, channels: u16) -> Sound {
+        Sound::from_samples(vec![value; frames * channels as usize], channels, RATE).unwrap()
+    }
+
+    // The first frame mixed.
+    fn first_frame(mixer: &mut Mixer) -> (f32, f32) {
+        let mut out = [0.0; 2];
+        mixer.render(&mut out);
+        (out[0], out[1])
+    }
+
+    fn assert_frame(actual: (f32, f32), expected: (f32, f32)) {
+        assert!(
+            (actual.0 - expected.0).abs() < 1e-5 && (actual.1 - expected.1).abs() < 1e-5,
+            "{:?} is not {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn max_voices_of_a_sound() {
+        let mut mixer = Mixer::new(RATE);
+        let sound = tone(0.1, 10, 1).with_max_voices(2);
+        let voices: Vec<_> = (0..3)
+            .map(|_| mixer.play(&sound, PlayParams::default()))
+            .collect();
+        assert!(!mixer.is_playing(voices[0]));
+        assert!(mixer.is_playing(voices[1]));
+        assert!(mixer.is_playing(voices[2]));
+
+        // Other sounds aren't limited by it.
+        let other = mixer.play(&tone(0.1, 10, 1), PlayParams::default());
+        assert!(mixer.is_playing(other));
+        assert_eq!(mixer.active_voices(), 3);
+    }
+
+    #[test]
+    fn oldest_voice_is_stolen() {
+        let mut mixer = Mixer::new(RATE);
+        let sound = tone(0.0, 10, 1);
+        let first = mixer.play(&sound, PlayParams::default());
+        let second = mixer.play(&sound, PlayParams::default());
+        for _ in 2..MAX_VOICES {
+            mixer.play(&sound, PlayParams::default());
+        }
+        assert_eq!(mixer.active_voices(), MAX_VOICES);
+        assert!(mixer.is_playing(first));
+
+        let last = mixer.play(&sound, PlayParams::default());
+        assert_eq!(mixer.active_voices(), MAX_VOICES);
+        assert!(!mixer.is_playing(first));
+        assert!(mixer.is_playing(second));
+        assert!(mixer.is_playing(last));
+    }
+
+    #[test]
+    fn finished_voices_stop() {
+        let mut mixer = Mixer::new(RATE);
+        let voice = mixer.play(&tone(0.5, 10, 1), PlayParams::default());
+        let mut out = [0.0; 40];
+        mixer.render(&mut out);
+        assert!(out[..20].iter().all(|sample| *sample > 0.0));
+        assert!(out[20..].iter().all(|sample| *sample == 0.0));
+        assert!(!mixer.is_playing(voice));
+    }
+
+    #[test]
+    fn pan_and_volume() {
+        let mut mixer = Mixer::new(RATE);
+        let mono = tone(1.0, 10, 1);
+        let stereo = tone(1.0, 10, 2);
+        let mut frame_of = |sound: &Sound, params: PlayParams| {
+            mixer.stop_all();
+            mixer.play(sound, params);
+            first_frame(&mut mixer)
+        };
+
+        // Mono sounds keep the same power wherever they're panned.
+        let centre = (FRAC_1_SQRT_2, FRAC_1_SQRT_2);
+        assert_frame(frame_of(&mono, PlayParams::default()), centre);
+        assert_frame(
+            frame_of(&mono, PlayParams::default().with_pan(-1.0)),
+            (1.0, 0.0),
+        );
+        assert_frame(
+            frame_of(&mono, PlayParams::default().with_pan(1.0)),
+            (0.0, 1.0),
+        );
+        assert_frame(
+            frame_of(&mono, PlayParams::default().with_pan(5.0)),
+            (0.0, 1.0),
+        );
+        assert_frame(
+            frame_of(&mono, PlayParams::default().with_volume(0.5)),
+            (0.5 * FRAC_1_SQRT_2, 0.5 * FRAC_1_SQRT_2),
+        );
+
+        // Stereo sounds play at full volume when centred.
+        assert_frame(frame_of(&stereo, PlayParams::default()), (1.0, 1.0));
+        assert_frame(
+            frame_of(&stereo, PlayParams::default().with_pan(1.0)),
+            (0.0, 1.0),
+        );
+        assert_frame(
+            frame_of(
+                &stereo,
+                PlayParams::default().with_pan(-0.5).with_volume(0.5),
+            ),
+            (
+                0.5,
+                0.5 * (FRAC_PI_4 / 2.0).sin() * std::f32::consts::SQRT_2,
+            ),
+        );
+    }
+
+    #[test]
+    fn bus_and_master_volumes() {
+        let mut mixer = Mixer::new(RATE);
+        let sound = tone(0.4, 10, 2);
+        mixer.set_bus_volume(Bus::Sfx, 0.5);
+        mixer.set_bus_volume(Bus::Music, 0.0);
+        mixer.play(&sound, PlayParams::default());
+        mixer.play(&sound, PlayParams::default().with_bus(Bus::Music));
+        assert_frame(first_frame(&mut mixer), (0.2, 0.2));
+
+        mixer.master_volume = 0.5;
+        assert_frame(first_frame(&mut mixer), (0.1, 0.1));
+
+        mixer.set_bus_volume(Bus::Sfx, -1.0);
+        assert_eq!(mixer.bus_volume(Bus::Sfx), 0.0);
+        assert_frame(first_frame(&mut mixer), (0.0, 0.0));
+    }
+
+    #[test]
+    fn output_is_clipped() {
+        let mut mixer = Mixer::new(RATE);
+        let sound = tone(0.8, 10, 2);
+        mixer.play(&sound, PlayParams::default());
+        mixer.play(&sound, PlayParams::default());
+        assert_frame(first_frame(&mut mixer), (1.0, 1.0));
+    }
+
+    // Music that loops a constant `value`.
+    fn music(value: f32) -> Music {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: RATE,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+        let mut bytes = std::io::Cursor::new(vec![]);
+        let mut writer = hound::WavWriter::new(&mut bytes, spec).unwrap();
+        for _ in 0..10 {
+            writer.write_sample(value).unwrap();
+        }
+        writer.finalize().unwrap();
+        Music::from_bytes(bytes.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn crossfade() {
+        let mut mixer = Mixer::new(RATE);
+        let (a, b) = (music(0.8), music(0.4));
+        mixer.play_music(&a, 0.0).unwrap();
+        assert_frame(first_frame(&mut mixer), (0.8, 0.8));
+
+        // Over 10 frames.
+        mixer.play_music(&b, 0.1).unwrap();
+        let mut out = [0.0; 30];
+        mixer.render(&mut out);
+        for (k, frame) in out.chunks_exact(2).enumerate() {
+            let t = (k as f32 / 10.0).min(1.0);
+            let expected = 0.8 * (1.0 - t) + 0.4 * t;
+            assert_frame((frame[0], frame[1]), (expected, expected));
+        }
+        assert_eq!(mixer.tracks.len(), 1);
+
+        // Playing it again doesn't restart it.
+        mixer.play_music(&b, 0.1).unwrap();
+        assert_frame(first_frame(&mut mixer), (0.4, 0.4));
+
+        mixer.stop_music(0.1);
+        assert!(!mixer.is_music_playing());
+        let mut out = [0.0; 30];
+        mixer.render(&mut out);
+        assert_frame((out[0], out[1]), (0.4, 0.4));
+        assert_frame((out[28], out[29]), (0.0, 0.0));
+        assert!(mixer.tracks.is_empty());
+    }
+}
@@ -0,0 +1,208 @@
+#[cfg(feature = "cpal")]
+mod device;
+pub mod mixer;
+pub mod music;
+pub mod sound;
+pub mod synth;
+
+pub use mixer::{Bus, Mixer, PlayParams, VoiceId};
+pub use music::Music;
+pub use sound::Sound;
+pub use synth::{Preset, SynthParams, Waveform};
+
+use std::any::Any;
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+// Sample rate of offline mixers.
+pub const SAMPLE_RATE: u32 = 44_100;
+
+// Plays sounds and music. Cheap to clone, all clones share the same mixer.
+//
+// Where the mixed samples go depends on how it was created: to the sound card (`new`, needs the
+// `cpal` feature), nowhere (`null`), or into buffers rendered on demand (`offline`), which lets
+// the mixing be checked without any audio hardware.
+#[derive(Clone)]
+pub struct Audio {
+    mixer: Option<Arc<Mutex<Mixer>>>,
+    // Keeps the device stream alive.
+    _output: Option<Rc<dyn Any>>,
+}
+
+impl Audio {
+    // Plays through the default output device, or stays silent if there's none.
+    pub fn new() -> Self {
+        #[cfg(feature = "cpal")]
+        match device::open() {
+            Ok((mixer, stream)) => {
+                return Self {
+                    mixer: Some(mixer),
+                    _output: Some(Rc::new(stream)),
+                };
+            }
+            Err(e) => log::warn!("{}, audio is disabled", e),
+        }
+        #[cfg(not(feature = "cpal"))]
+        log::info!("Built without the cpal feature, audio is disabled");
+        Self::null()
+    }
+
+    // Ignores everything.
+    pub fn null() -> Self {
+        Self {
+            mixer: None,
+            _output: None,
+        }
+    }
+
+    // Mixes only when `render` is called.
+    pub fn offline(sample_rate: u32) -> Self {
+        Self {
+            mixer: Some(Arc::new(Mutex::new(Mixer::new(sample_rate)))),
+            _output: None,
+        }
+    }
+
+    fn with_mixer<T>(&self, f: impl FnOnce(&mut Mixer) -> T) -> Option<T> {
+        self.mixer
+            .as_ref()
+            .map(|mixer| f(&mut mixer.lock().unwrap()))
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.with_mixer(|mixer| mixer.sample_rate())
+            .unwrap_or(SAMPLE_RATE)
+    }
+
+    pub fn play(&self, sound: &Sound, params: PlayParams) -> Option<VoiceId> {
+        self.with_mixer(|mixer| mixer.play(sound, params))
+    }
+
+    pub fn play_sound(&self, sound: &Sound) -> Option<VoiceId> {
+        self.play(sound, PlayParams::default())
+    }
+
+    pub fn stop(&self, voice: VoiceId) {
+        self.with_mixer(|mixer| mixer.stop(voice));
+    }
+
+    pub fn stop_all(&self) {
+        self.with_mixer(|mixer| mixer.stop_all());
+    }
+
+    pub fn is_playing(&self, voice: VoiceId) -> bool {
+        self.with_mixer(|mixer| mixer.is_playing(voice))
+            .unwrap_or(false)
+    }
+
+    pub fn active_voices(&self) -> usize {
+        self.with_mixer(|mixer| mixer.active_voices()).unwrap_or(0)
+    }
+
+    pub fn set_master_volume(&self, volume: f32) {
+        self.with_mixer(|mixer| mixer.master_volume = volume);
+    }
+
+    pub fn bus_volume(&self, bus: Bus) -> f32 {
+        self.with_mixer(|mixer| mixer.bus_volume(bus))
+            .unwrap_or(1.0)
+    }
+
+    pub fn set_bus_volume(&self, bus: Bus, volume: f32) {
+        self.with_mixer(|mixer| mixer.set_bus_volume(bus, volume));
+    }
+
+    // Crossfades to `music` over `fade` seconds, see `Mixer::play_music`. The music is opened
+    // before locking the mixer, so reading the file doesn't hold up the audio thread.
+    pub fn play_music(&self, music: &Music, fade: f32) -> anyhow::Result<()> {
+        if self
+            .with_mixer(|mixer| mixer.is_current_music(music))
+            .unwrap_or(true)
+        {
+            return Ok(());
+        }
+        let stream = music.stream()?;
+        self.with_mixer(|mixer| mixer.play_stream(stream, fade));
+        Ok(())
+    }
+
+    pub fn stop_music(&self, fade: f32) {
+        self.with_mixer(|mixer| mixer.stop_music(fade));
+    }
+
+    pub fn is_music_playing(&self) -> bool {
+        self.with_mixer(|mixer| mixer.is_music_playing())
+            .unwrap_or(false)
+    }
+
+    // Mixes the next `frames` frames, as interleaved stereo samples. Only meaningful for offline
+    // audio, since a device consumes the samples itself.
+    pub fn render(&self, frames: usize) -> Vec<f32> {
+        let mut samples = vec![0.0; frames * 2];
+        self.with_mixer(|mixer| mixer.render(&mut samples));
+        samples
+    }
+
+    // Mixes the next `seconds` and writes them to a WAV file.
+    pub fn render_to_wav(&self, path: impl AsRef<Path>, seconds: f32) -> anyhow::Result<()> {
+        let frames = (seconds * self.sample_rate() as f32) as usize;
+        write_wav(path, &self.render(frames), 2, self.sample_rate())
+    }
+}
+
+impl Default for Audio {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Writes interleaved samples to a 32-bit float WAV file.
+pub fn write_wav(
+    path: impl AsRef<Path>,
+    samples: &[f32],
+    channels: u16,
+    sample_rate: u32,
+) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    let spec = hound::WavSpec {
+        channels,
+        sample_rate,
+        bits_per_sample: 32,
+        sample_format: hound::SampleFormat::Float,
+    };
+    let write = || -> Result<(), hound::Error> {
+        let mut writer = hound::WavWriter::create(path, spec)?;
+        for sample in samples {
+            writer.write_sample(*sample)?;
+        }
+        writer.finalize()
+    };
+    write().map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_to_wav_length() {
+        let audio = Audio::offline(1000);
+        let sound = Sound::from_samples(vec![0.5; 100], 1, 1000).unwrap();
+        audio.play_sound(&sound);
+
+        let path = std::env::temp_dir().join(format!("engine-mix-{}.wav", std::process::id()));
+        audio.render_to_wav(&path, 0.25).unwrap();
+        let reader = hound::WavReader::open(&path).unwrap();
+        let spec = reader.spec();
+        let samples: Vec<f32> = reader.into_samples().map(Result::unwrap).collect();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(spec.channels, 2);
+        assert_eq!(spec.sample_rate, 1000);
+        assert_eq!(samples.len(), 2 * 250);
+        assert!(samples[..200].iter().all(|sample| *sample > 0.0));
+        assert!(samples[200..].iter().all(|sample| *sample == 0.0));
+        assert_eq!(audio.active_voices(), 0);
+    }
+}
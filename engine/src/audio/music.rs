@@ -0,0 +1,400 @@
+use crate::audio::sound::read_wav_samples;
+use lewton::inside_ogg::OggStreamReader;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+// Packets decoded per mix while preparing the loop, so the work is spread over several mixes.
+const PREPARE_PACKETS_PER_MIX: usize = 4;
+// Frames read at once from WAV files, which have no packets of their own.
+const WAV_PACKET_FRAMES: usize = 1024;
+
+trait ReadSeek: Read + Seek + Send {}
+
+impl<T: Read + Seek + Send> ReadSeek for T {}
+
+#[derive(Clone, Debug)]
+enum Source {
+    File(PathBuf),
+    Memory(Arc<[u8]>),
+}
+
+// An Ogg Vorbis or WAV track, decoded a little at a time while it plays rather than loaded
+// whole.
+#[derive(Clone, Debug)]
+pub struct Music {
+    source: Source,
+    channels: u16,
+    sample_rate: u32,
+    looping: bool,
+    // In frames.
+    loop_start: u64,
+    loop_end: Option<u64>,
+}
+
+impl Music {
+    // Streams from the file, which stays open while the music plays.
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        Self::new(Source::File(path.to_path_buf()))
+            .map_err(|e| anyhow::anyhow!("Failed to open {}: {}", path.display(), e))
+    }
+
+    // Streams from encoded bytes kept in memory, eg. `include_bytes!("title.ogg")`.
+    pub fn from_bytes(bytes: impl Into<Arc<[u8]>>) -> anyhow::Result<Self> {
+        Self::new(Source::Memory(bytes.into()))
+    }
+
+    fn new(source: Source) -> anyhow::Result<Self> {
+        let decoder = Decoder::open(&source)?;
+        Ok(Self {
+            source,
+            channels: decoder.channels,
+            sample_rate: decoder.sample_rate,
+            looping: true,
+            loop_start: 0,
+            loop_end: None,
+        })
+    }
+
+    // Plays once instead of looping.
+    pub fn once(mut self) -> Self {
+        self.looping = false;
+        self
+    }
+
+    // Loops between `start` and `end`, in seconds, so an intro before `start` only plays once.
+    // Without an end the loop goes to the end of the track.
+    pub fn with_loop(mut self, start: f32, end: Option<f32>) -> Self {
+        let to_frames = |seconds: f32| (seconds.max(0.0) * self.sample_rate as f32) as u64;
+        self.looping = true;
+        self.loop_start = to_frames(start);
+        self.loop_end = end.map(to_frames);
+        self
+    }
+
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    // Whether both stream the same file or bytes, whatever their loop points.
+    pub fn same_as(&self, other: &Music) -> bool {
+        match (&self.source, &other.source) {
+            (Source::File(a), Source::File(b)) => a == b,
+            (Source::Memory(a), Source::Memory(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+
+    // Opens the track, and a second time to loop it, so that playing never opens files on the
+    // audio thread.
+    pub(crate) fn stream(&self) -> anyhow::Result<MusicStream> {
+        Ok(MusicStream {
+            music: self.clone(),
+            current: Decoder::open(&self.source)?,
+            next_loop: None,
+            used: if self.looping {
+                Some(Decoder::open(&self.source)?)
+            } else {
+                None
+            },
+            finished: false,
+        })
+    }
+}
+
+enum Format {
+    Vorbis(Box<OggStreamReader<Box<dyn ReadSeek>>>),
+    Wav(hound::WavReader<Box<dyn ReadSeek>>),
+}
+
+struct Decoder {
+    format: Format,
+    channels: u16,
+    sample_rate: u32,
+    // Decoded frames not played yet, interleaved.
+    pending: VecDeque<f32>,
+    // Index of the first frame in `pending`.
+    frame: u64,
+    ended: bool,
+}
+
+impl Decoder {
+    fn open(source: &Source) -> anyhow::Result<Self> {
+        let input: Box<dyn ReadSeek> = match source {
+            Source::File(path) => Box::new(BufReader::new(File::open(path)?)),
+            Source::Memory(bytes) => Box::new(Cursor::new(bytes.clone())),
+        };
+        Self::from_reader(input)
+    }
+
+    // The format is detected from the header, like `Sound::from_bytes`.
+    fn from_reader(mut input: Box<dyn ReadSeek>) -> anyhow::Result<Self> {
+        let mut magic = [0; 4];
+        input.read_exact(&mut magic)?;
+        input.seek(SeekFrom::Start(0))?;
+        let (format, channels, sample_rate) = match &magic {
+            b"RIFF" => {
+                let reader = hound::WavReader::new(input)?;
+                let spec = reader.spec();
+                (Format::Wav(reader), spec.channels, spec.sample_rate)
+            }
+            b"OggS" => {
+                let reader = OggStreamReader::new(input)?;
+                let channels = reader.ident_hdr.audio_channels as u16;
+                let sample_rate = reader.ident_hdr.audio_sample_rate;
+                (Format::Vorbis(Box::new(reader)), channels, sample_rate)
+            }
+            _ => anyhow::bail!("Unsupported audio format, expected WAV or Ogg Vorbis"),
+        };
+        if channels != 1 && channels != 2 {
+            anyhow::bail!("Unsupported channel count {}", channels);
+        }
+        Ok(Self {
+            format,
+            channels,
+            sample_rate,
+            pending: VecDeque::new(),
+            frame: 0,
+            ended: false,
+        })
+    }
+
+    // Goes back to the start of the track, reusing the open file.
+    fn rewind(self) -> anyhow::Result<Self> {
+        let mut input = match self.format {
+            Format::Vorbis(reader) => reader.into_inner().into_inner(),
+            Format::Wav(reader) => reader.into_inner(),
+        };
+        input.seek(SeekFrom::Start(0))?;
+        Self::from_reader(input)
+    }
+
+    fn read_packet(&mut self) -> anyhow::Result<Option<Vec<f32>>> {
+        Ok(match &mut self.format {
+            Format::Vorbis(reader) => reader
+                .read_dec_packet_itl()?
+                .map(|packet| packet.into_iter().map(|s| s as f32 / 32768.0).collect()),
+            Format::Wav(reader) => {
+                let samples = WAV_PACKET_FRAMES * self.channels as usize;
+                Some(read_wav_samples(reader, samples)?).filter(|packet| !packet.is_empty())
+            }
+        })
+    }
+
+    // Decodes the next packet, returning false at the end of the track.
+    fn decode(&mut self) -> bool {
+        if self.ended {
+            return false;
+        }
+        match self.read_packet() {
+            Ok(Some(packet)) => {
+                self.pending.extend(packet);
+                true
+            }
+            Ok(None) => {
+                self.ended = true;
+                false
+            }
+            Err(e) => {
+                log::error!("Failed to decode music: {}", e);
+                self.ended = true;
+                false
+            }
+        }
+    }
+
+    fn next_frame(&mut self) -> Option<[f32; 2]> {
+        while self.pending.len() < self.channels as usize {
+            if !self.decode() {
+                return None;
+            }
+        }
+        let left = self.pending.pop_front()?;
+        let right = if self.channels == 2 {
+            self.pending.pop_front()?
+        } else {
+            left
+        };
+        self.frame += 1;
+        Some([left, right])
+    }
+
+    // Drops frames until `frame`, decoding at most `max_packets` packets. Returns whether
+    // `frame` was reached.
+    fn skip_to(&mut self, frame: u64, max_packets: usize) -> bool {
+        let mut packets = 0;
+        while self.frame < frame {
+            let channels = self.channels as usize;
+            let available = (self.pending.len() / channels) as u64;
+            let skip = available.min(frame - self.frame);
+            self.pending.drain(..skip as usize * channels);
+            self.frame += skip;
+            if self.frame == frame {
+                break;
+            }
+            if packets == max_packets || !self.decode() {
+                return self.ended;
+            }
+            packets += 1;
+        }
+        true
+    }
+}
+
+// A playing `Music`. Looping needs the track decoded from the loop start again, so a second
+// decoder is prepared a few packets at a time while the first one plays. Once the loop starts,
+// the first decoder is rewound to prepare the next one.
+pub(crate) struct MusicStream {
+    music: Music,
+    current: Decoder,
+    // Decoding from the loop start, or getting there.
+    next_loop: Option<Decoder>,
+    // Waiting to be rewound into `next_loop`.
+    used: Option<Decoder>,
+    finished: bool,
+}
+
+impl MusicStream {
+    pub(crate) fn music(&self) -> &Music {
+        &self.music
+    }
+
+    pub(crate) fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    // Does some of the work needed to loop, called once per mix.
+    pub(crate) fn prepare(&mut self) {
+        if !self.music.looping || self.finished {
+            return;
+        }
+        if self.next_loop.is_none() {
+            self.next_loop = self.rewind_used();
+        }
+        if let Some(decoder) = &mut self.next_loop {
+            decoder.skip_to(self.music.loop_start, PREPARE_PACKETS_PER_MIX);
+        }
+    }
+
+    fn rewind_used(&mut self) -> Option<Decoder> {
+        match self.used.take()?.rewind() {
+            Ok(decoder) => Some(decoder),
+            Err(e) => {
+                log::error!("Failed to loop music: {}", e);
+                None
+            }
+        }
+    }
+
+    pub(crate) fn next_frame(&mut self) -> Option<[f32; 2]> {
+        if self.finished {
+            return None;
+        }
+        let at_loop_end = self.music.loop_end == Some(self.current.frame);
+        if !at_loop_end {
+            if let Some(frame) = self.current.next_frame() {
+                return Some(frame);
+            }
+        }
+        if !self.music.looping {
+            self.finished = true;
+            return None;
+        }
+
+        // The loop wasn't prepared in time, finish preparing it now.
+        let Some(mut decoder) = self.next_loop.take().or_else(|| self.rewind_used()) else {
+            self.finished = true;
+            return None;
+        };
+        decoder.skip_to(self.music.loop_start, usize::MAX);
+        self.used = Some(std::mem::replace(&mut self.current, decoder));
+        let frame = self.current.next_frame();
+        if frame.is_none() {
+            // Empty loop.
+            self.finished = true;
+        }
+        frame
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 10;
+
+    // A mono track whose frame `i` is `i / 100`, so frames can be told apart.
+    fn music(frames: usize) -> Music {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: RATE,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+        let mut bytes = Cursor::new(vec![]);
+        let mut writer = hound::WavWriter::new(&mut bytes, spec).unwrap();
+        for i in 0..frames {
+            writer.write_sample(i as f32 / 100.0).unwrap();
+        }
+        writer.finalize().unwrap();
+        Music::from_bytes(bytes.into_inner()).unwrap()
+    }
+
+    // The indices of the next `count` frames, preparing the loop before each like the mixer.
+    fn play(stream: &mut MusicStream, count: usize, prepare: bool) -> Vec<usize> {
+        (0..count)
+            .map_while(|_| {
+                if prepare {
+                    stream.prepare();
+                }
+                stream.next_frame()
+            })
+            .map(|[left, right]| {
+                assert_eq!(left, right);
+                (left * 100.0).round() as usize
+            })
+            .collect()
+    }
+
+    #[test]
+    fn loop_points() {
+        let mut stream = music(10).with_loop(0.2, Some(0.6)).stream().unwrap();
+        assert_eq!(
+            play(&mut stream, 14, true),
+            [0, 1, 2, 3, 4, 5, 2, 3, 4, 5, 2, 3, 4, 5]
+        );
+        assert!(!stream.is_finished());
+    }
+
+    #[test]
+    fn loop_to_the_end() {
+        let mut stream = music(10).with_loop(0.5, None).stream().unwrap();
+        assert_eq!(
+            play(&mut stream, 20, true),
+            [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 5, 6, 7, 8, 9, 5, 6, 7, 8, 9]
+        );
+    }
+
+    #[test]
+    fn loop_without_preparing() {
+        let mut stream = music(10).with_loop(0.2, Some(0.6)).stream().unwrap();
+        assert_eq!(
+            play(&mut stream, 14, false),
+            [0, 1, 2, 3, 4, 5, 2, 3, 4, 5, 2, 3, 4, 5]
+        );
+    }
+
+    #[test]
+    fn once() {
+        let mut stream = music(10).once().stream().unwrap();
+        assert_eq!(play(&mut stream, 20, true), (0..10).collect::<Vec<_>>());
+        assert!(stream.is_finished());
+    }
+}
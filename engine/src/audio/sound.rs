@@ -0,0 +1,158 @@
+use std::fs;
+use std::io::{Cursor, Read};
+use std::path::Path;
+use std::sync::Arc;
+
+// Decoded audio, shared between all the voices playing it. Samples are interleaved, with one or
+// two channels.
+#[derive(Clone, Debug)]
+pub struct Sound {
+    samples: Arc<[f32]>,
+    channels: u16,
+    sample_rate: u32,
+    max_voices: Option<usize>,
+}
+
+impl Sound {
+    // Only mono and stereo sounds are supported.
+    pub fn from_samples(
+        samples: Vec<f32>,
+        channels: u16,
+        sample_rate: u32,
+    ) -> anyhow::Result<Self> {
+        if channels != 1 && channels != 2 {
+            anyhow::bail!("Unsupported channel count {}", channels);
+        }
+        Ok(Self {
+            samples: samples.into(),
+            channels,
+            sample_rate,
+            max_voices: None,
+        })
+    }
+
+    pub(crate) fn mono(samples: Vec<f32>, sample_rate: u32) -> Self {
+        Self {
+            samples: samples.into(),
+            channels: 1,
+            sample_rate,
+            max_voices: None,
+        }
+    }
+
+    // Decodes a WAV or Ogg Vorbis file, detected from its header.
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        if bytes.starts_with(b"RIFF") {
+            Self::from_wav(bytes)
+        } else if bytes.starts_with(b"OggS") {
+            Self::from_ogg(bytes)
+        } else {
+            anyhow::bail!("Unsupported audio format, expected WAV or Ogg Vorbis")
+        }
+    }
+
+    pub fn from_wav(bytes: &[u8]) -> anyhow::Result<Self> {
+        let mut reader = hound::WavReader::new(Cursor::new(bytes))?;
+        let spec = reader.spec();
+        let samples = read_wav_samples(&mut reader, usize::MAX)?;
+        Self::from_samples(samples, spec.channels, spec.sample_rate)
+    }
+
+    pub fn from_ogg(bytes: &[u8]) -> anyhow::Result<Self> {
+        let mut reader = lewton::inside_ogg::OggStreamReader::new(Cursor::new(bytes))?;
+        let channels = reader.ident_hdr.audio_channels as u16;
+        let sample_rate = reader.ident_hdr.audio_sample_rate;
+        let mut samples = vec![];
+        while let Some(packet) = reader.read_dec_packet_itl()? {
+            samples.extend(packet.into_iter().map(|s| s as f32 / 32768.0));
+        }
+        Self::from_samples(samples, channels, sample_rate)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let bytes = fs::read(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+        Self::from_bytes(&bytes)
+            .map_err(|e| anyhow::anyhow!("Failed to decode {}: {}", path.display(), e))
+    }
+
+    // Limits how many instances of this sound can play at once. Playing it again once the limit
+    // is reached stops the oldest instance, so rapid-fire effects don't drown everything else.
+    pub fn with_max_voices(mut self, max_voices: usize) -> Self {
+        self.max_voices = Some(max_voices.max(1));
+        self
+    }
+
+    pub fn max_voices(&self) -> Option<usize> {
+        self.max_voices
+    }
+
+    pub fn samples(&self) -> &[f32] {
+        &self.samples
+    }
+
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels as usize
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.frames() as f32 / self.sample_rate as f32
+    }
+
+    // Whether both are the same decoded audio, rather than equal samples.
+    pub fn same_as(&self, other: &Sound) -> bool {
+        Arc::ptr_eq(&self.samples, &other.samples)
+    }
+
+    // The sample of `channel` at `frame`, 0.0 past the end.
+    pub(crate) fn sample(&self, frame: usize, channel: usize) -> f32 {
+        let channel = channel.min(self.channels as usize - 1);
+        self.samples
+            .get(frame * self.channels as usize + channel)
+            .copied()
+            .unwrap_or(0.0)
+    }
+}
+
+// Reads up to `count` samples, converted to floats from -1.0 to 1.0.
+pub(crate) fn read_wav_samples<R: Read>(
+    reader: &mut hound::WavReader<R>,
+    count: usize,
+) -> Result<Vec<f32>, hound::Error> {
+    let spec = reader.spec();
+    match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().take(count).collect(),
+        hound::SampleFormat::Int => {
+            let scale = 1.0 / (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .take(count)
+                .map(|s| s.map(|s| s as f32 * scale))
+                .collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn channel_counts() {
+        let sound = Sound::from_samples(vec![0.0; 8], 2, 100).unwrap();
+        assert_eq!(sound.frames(), 4);
+        assert_eq!(sound.duration(), 0.04);
+        assert!(Sound::from_samples(vec![0.0; 8], 1, 100).is_ok());
+        assert!(Sound::from_samples(vec![0.0; 8], 0, 100).is_err());
+        assert!(Sound::from_samples(vec![0.0; 8], 4, 100).is_err());
+    }
+}
@@ -0,0 +1,387 @@
+use crate::audio::sound::Sound;
+use crate::random::Rng;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+use std::fs;
+use std::path::Path;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Waveform {
+    Square,
+    Sawtooth,
+    Sine,
+    Triangle,
+    Noise,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Preset {
+    Pickup,
+    Laser,
+    Explosion,
+    Powerup,
+    Hit,
+    Jump,
+    Blip,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 7] = [
+        Preset::Pickup,
+        Preset::Laser,
+        Preset::Explosion,
+        Preset::Powerup,
+        Preset::Hit,
+        Preset::Jump,
+        Preset::Blip,
+    ];
+}
+
+// Parameters of a synthesised 8-bit style effect, in the spirit of sfxr. Times are in seconds and
+// frequencies in Hz. Saved as RON, where missing fields take their default value.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SynthParams {
+    pub waveform: Waveform,
+    pub volume: f32,
+
+    // Envelope: rises over `attack`, holds for `sustain` and fades out over `decay`. `punch`
+    // starts the sustain louder, 1.0 being twice as loud.
+    pub attack: f32,
+    pub sustain: f32,
+    pub punch: f32,
+    pub decay: f32,
+
+    pub frequency: f32,
+    // The sound stops if the frequency slides below this.
+    pub min_frequency: f32,
+    // In octaves per second, and its change per second.
+    pub slide: f32,
+    pub delta_slide: f32,
+
+    // Depth as a fraction of the frequency.
+    pub vibrato_depth: f32,
+    pub vibrato_speed: f32,
+
+    // Multiplies the frequency once, `change_time` into the sound. 1.0 disables it.
+    pub change_amount: f32,
+    pub change_time: f32,
+
+    // Fraction of the period the square wave is high, and its change per second.
+    pub duty: f32,
+    pub duty_sweep: f32,
+
+    // Noise is generated from this, so the same parameters always give the same sound.
+    pub seed: u64,
+}
+
+impl Default for SynthParams {
+    fn default() -> Self {
+        Self {
+            waveform: Waveform::Square,
+            volume: 0.5,
+            attack: 0.0,
+            sustain: 0.1,
+            punch: 0.0,
+            decay: 0.2,
+            frequency: 440.0,
+            min_frequency: 0.0,
+            slide: 0.0,
+            delta_slide: 0.0,
+            vibrato_depth: 0.0,
+            vibrato_speed: 0.0,
+            change_amount: 1.0,
+            change_time: 0.0,
+            duty: 0.5,
+            duty_sweep: 0.0,
+            seed: 0,
+        }
+    }
+}
+
+impl SynthParams {
+    // A random variation of `preset`.
+    pub fn preset(preset: Preset, rng: &mut Rng) -> Self {
+        let mut params = Self {
+            seed: rng.next_u64(),
+            ..Self::default()
+        };
+        match preset {
+            Preset::Pickup => {
+                params.frequency = rng.range_f32(800.0..1600.0);
+                params.sustain = rng.range_f32(0.02..0.08);
+                params.punch = rng.range_f32(0.3..0.6);
+                params.decay = rng.range_f32(0.1..0.25);
+                if rng.chance(0.5) {
+                    params.change_amount = rng.range_f32(1.2..1.6);
+                    params.change_time = rng.range_f32(0.03..0.08);
+                }
+            }
+            Preset::Laser => {
+                params.waveform = if rng.chance(0.5) {
+                    Waveform::Sawtooth
+                } else {
+                    Waveform::Square
+                };
+                params.frequency = rng.range_f32(1000.0..3000.0);
+                params.min_frequency = rng.range_f32(100.0..300.0);
+                params.slide = rng.range_f32(-12.0..-6.0);
+                params.duty = rng.range_f32(0.2..0.5);
+                params.duty_sweep = rng.range_f32(0.0..1.0);
+                params.sustain = rng.range_f32(0.05..0.15);
+                params.decay = rng.range_f32(0.05..0.2);
+            }
+            Preset::Explosion => {
+                params.waveform = Waveform::Noise;
+                params.frequency = rng.range_f32(200.0..1500.0);
+                params.slide = rng.range_f32(-3.0..0.0);
+                params.sustain = rng.range_f32(0.1..0.3);
+                params.punch = rng.range_f32(0.2..0.8);
+                params.decay = rng.range_f32(0.3..0.6);
+                if rng.chance(0.5) {
+                    params.vibrato_depth = rng.range_f32(0.1..0.4);
+                    params.vibrato_speed = rng.range_f32(5.0..20.0);
+                }
+            }
+            Preset::Powerup => {
+                params.frequency = rng.range_f32(200.0..600.0);
+                params.slide = rng.range_f32(1.0..4.0);
+                if rng.chance(0.5) {
+                    params.waveform = Waveform::Sawtooth;
+                } else {
+                    params.duty = rng.range_f32(0.2..0.5);
+                    params.vibrato_depth = rng.range_f32(0.05..0.2);
+                    params.vibrato_speed = rng.range_f32(8.0..20.0);
+                }
+                params.sustain = rng.range_f32(0.1..0.3);
+                params.decay = rng.range_f32(0.1..0.3);
+            }
+            Preset::Hit => {
+                params.waveform = match rng.range_u32(0..3) {
+                    0 => Waveform::Square,
+                    1 => Waveform::Sawtooth,
+                    _ => Waveform::Noise,
+                };
+                params.frequency = rng.range_f32(300.0..1200.0);
+                params.min_frequency = 50.0;
+                params.slide = rng.range_f32(-10.0..-4.0);
+                params.sustain = rng.range_f32(0.01..0.05);
+                params.decay = rng.range_f32(0.05..0.15);
+            }
+            Preset::Jump => {
+                params.frequency = rng.range_f32(250.0..500.0);
+                params.slide = rng.range_f32(3.0..6.0);
+                params.duty = rng.range_f32(0.2..0.5);
+                params.sustain = rng.range_f32(0.05..0.15);
+                params.decay = rng.range_f32(0.05..0.15);
+            }
+            Preset::Blip => {
+                params.waveform = if rng.chance(0.5) {
+                    Waveform::Square
+                } else {
+                    Waveform::Sawtooth
+                };
+                params.frequency = rng.range_f32(400.0..1200.0);
+                params.duty = rng.range_f32(0.2..0.5);
+                params.sustain = rng.range_f32(0.03..0.08);
+                params.decay = rng.range_f32(0.01..0.05);
+            }
+        }
+        params
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.attack.max(0.0) + self.sustain.max(0.0) + self.decay.max(0.0)
+    }
+
+    // The envelope's volume `t` seconds in.
+    fn envelope(&self, t: f32) -> f32 {
+        let (attack, sustain, decay) = (self.attack.max(0.0), self.sustain.max(0.0), self.decay);
+        if t < attack {
+            t / attack
+        } else if t < attack + sustain {
+            1.0 + self.punch * (1.0 - (t - attack) / sustain)
+        } else {
+            (1.0 - (t - attack - sustain) / decay.max(f32::EPSILON)).max(0.0)
+        }
+    }
+
+    // Renders the effect as a mono sound.
+    pub fn synthesize(&self, sample_rate: u32) -> Sound {
+        let dt = 1.0 / sample_rate as f32;
+        let frames = (self.duration() * sample_rate as f32) as usize;
+        let mut rng = Rng::new(self.seed);
+        let mut samples = Vec::with_capacity(frames);
+
+        let mut frequency = self.frequency;
+        let mut slide = self.slide;
+        let mut changed = self.change_amount == 1.0;
+        let mut phase = 0.0f32;
+        let mut noise = rng.range_f32(-1.0..1.0);
+        for i in 0..frames {
+            let t = i as f32 * dt;
+            if !changed && t >= self.change_time {
+                frequency *= self.change_amount;
+                changed = true;
+            }
+            frequency *= (slide * dt).exp2();
+            slide += self.delta_slide * dt;
+            if frequency < self.min_frequency || frequency <= 0.0 {
+                break;
+            }
+
+            let vibrato = 1.0 + self.vibrato_depth * (TAU * self.vibrato_speed * t).sin();
+            phase += frequency * vibrato * dt;
+            if phase >= 1.0 {
+                phase = phase.fract();
+                noise = rng.range_f32(-1.0..1.0);
+            }
+
+            let duty = (self.duty + self.duty_sweep * t).clamp(0.05, 0.95);
+            let wave = match self.waveform {
+                Waveform::Square => {
+                    if phase < duty {
+                        1.0
+                    } else {
+                        -1.0
+                    }
+                }
+                Waveform::Sawtooth => 1.0 - phase * 2.0,
+                Waveform::Sine => (TAU * phase).sin(),
+                Waveform::Triangle => (phase * 4.0 - 2.0).abs() - 1.0,
+                Waveform::Noise => noise,
+            };
+            samples.push((wave * self.envelope(t) * self.volume).clamp(-1.0, 1.0));
+        }
+        Sound::mono(samples, sample_rate)
+    }
+
+    pub fn from_ron(ron: &str) -> anyhow::Result<Self> {
+        Ok(ron::from_str(ron)?)
+    }
+
+    pub fn to_ron(&self) -> anyhow::Result<String> {
+        Ok(ron::ser::to_string_pretty(
+            self,
+            ron::ser::PrettyConfig::default(),
+        )?)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let ron = fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+        Self::from_ron(&ron)
+            .map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", path.display(), e))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        fs::write(path, self.to_ron()?)
+            .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 8000;
+
+    #[test]
+    fn length() {
+        let params = SynthParams {
+            attack: 0.05,
+            sustain: 0.1,
+            decay: 0.1,
+            ..SynthParams::default()
+        };
+        let sound = params.synthesize(RATE);
+        assert_eq!((sound.channels(), sound.sample_rate()), (1, RATE));
+        assert_eq!(sound.frames(), 2000);
+
+        let mut rng = Rng::new(1);
+        for preset in Preset::ALL {
+            let params = SynthParams::preset(preset, &mut rng);
+            let frames = (params.duration() * RATE as f32) as usize;
+            let sound = params.synthesize(RATE);
+            // Sliding below the minimum frequency ends the sound early.
+            if params.min_frequency > 0.0 {
+                assert!(sound.frames() <= frames, "{:?}", preset);
+            } else {
+                assert_eq!(sound.frames(), frames, "{:?}", preset);
+            }
+        }
+    }
+
+    #[test]
+    fn same_seed_same_sound() {
+        for preset in Preset::ALL {
+            let a = SynthParams::preset(preset, &mut Rng::new(42));
+            let b = SynthParams::preset(preset, &mut Rng::new(42));
+            assert_eq!(a, b);
+            assert_eq!(a.synthesize(RATE).samples(), b.synthesize(RATE).samples());
+        }
+
+        let noise = SynthParams {
+            waveform: Waveform::Noise,
+            ..SynthParams::default()
+        };
+        let other_seed = SynthParams {
+            seed: 1,
+            ..noise.clone()
+        };
+        assert_ne!(
+            noise.synthesize(RATE).samples(),
+            other_seed.synthesize(RATE).samples()
+        );
+    }
+
+    #[test]
+    fn empty_envelope_stages() {
+        for (attack, sustain, decay) in [
+            (0.0, 0.1, 0.1),
+            (0.1, 0.0, 0.1),
+            (0.1, 0.1, 0.0),
+            (0.0, 0.0, 0.1),
+            (0.0, 0.0, 0.0),
+        ] {
+            let params = SynthParams {
+                attack,
+                sustain,
+                decay,
+                punch: 0.5,
+                ..SynthParams::default()
+            };
+            let sound = params.synthesize(RATE);
+            assert!(
+                sound
+                    .samples()
+                    .iter()
+                    .all(|s| s.is_finite() && s.abs() <= 1.0),
+                "{:?}",
+                (attack, sustain, decay)
+            );
+        }
+    }
+
+    #[test]
+    fn ron_round_trip() {
+        let params = SynthParams::preset(Preset::Laser, &mut Rng::new(7));
+        assert_eq!(
+            SynthParams::from_ron(&params.to_ron().unwrap()).unwrap(),
+            params
+        );
+
+        let params = SynthParams::from_ron("(waveform: Noise, frequency: 220.0)").unwrap();
+        assert_eq!(
+            params,
+            SynthParams {
+                waveform: Waveform::Noise,
+                frequency: 220.0,
+                ..SynthParams::default()
+            }
+        );
+        assert!(SynthParams::from_ron("(waveform: Kazoo)").is_err());
+    }
+}
@@ -0,0 +1,40 @@
+// Packs an asset directory into a single archive, for release builds:
+//
+//     cargo run --release -p engine --bin pack-assets -- <asset dir> <archive>
+use engine::assets::{Archive, ArchiveWriter};
+use std::env;
+use std::process;
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let [dir, archive] = args.as_slice() else {
+        eprintln!("Usage: pack-assets <asset dir> <archive>");
+        process::exit(2);
+    };
+    if let Err(e) = pack(dir, archive) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+fn pack(dir: &str, archive: &str) -> anyhow::Result<()> {
+    let mut writer = ArchiveWriter::new();
+    writer.add_dir(dir)?;
+    writer.save(archive)?;
+
+    // Read it back, so a broken archive is caught here rather than in the game.
+    let packed = Archive::load(archive)?;
+    let mut size = 0;
+    for path in packed.paths() {
+        size += packed.read(path)?.len();
+    }
+    let packed_size = std::fs::metadata(archive)?.len();
+    println!(
+        "Packed {} files, {} bytes into {} ({} bytes)",
+        packed.len(),
+        size,
+        archive,
+        packed_size
+    );
+    Ok(())
+}
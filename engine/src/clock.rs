@@ -0,0 +1,226 @@
+use std::time::{Duration, Instant};
+
+// Where time comes from. The app asks its clock how much time passed every frame, and runs as
+// many fixed ticks as fit in it; swapping the clock changes how fast the game runs without
+// touching game code.
+pub trait Clock {
+    // Time elapsed since the previous call.
+    fn elapsed(&mut self) -> Duration;
+}
+
+// Wall clock time.
+pub struct RealClock {
+    last: Instant,
+}
+
+impl RealClock {
+    pub fn new() -> Self {
+        Self {
+            last: Instant::now(),
+        }
+    }
+}
+
+impl Default for RealClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for RealClock {
+    fn elapsed(&mut self) -> Duration {
+        let now = Instant::now();
+        let elapsed = now - self.last;
+        self.last = now;
+        elapsed
+    }
+}
+
+// Advances by the same step every frame, whatever the real time, eg. to capture a video at a
+// steady frame rate.
+pub struct FixedStepClock {
+    step: Duration,
+}
+
+impl FixedStepClock {
+    pub fn new(step: Duration) -> Self {
+        Self { step }
+    }
+}
+
+impl Clock for FixedStepClock {
+    fn elapsed(&mut self) -> Duration {
+        self.step
+    }
+}
+
+// Only advances when told to.
+#[derive(Default)]
+pub struct ManualClock {
+    pending: Duration,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn advance(&mut self, duration: Duration) {
+        self.pending += duration;
+    }
+}
+
+impl Clock for ManualClock {
+    fn elapsed(&mut self) -> Duration {
+        std::mem::take(&mut self.pending)
+    }
+}
+
+// Game time, passed to every update. Time only moves in whole ticks, so games that use `delta`
+// behave the same at any frame rate, time scale, or in a replay.
+#[derive(Copy, Clone, Debug)]
+pub struct Time {
+    tick: Duration,
+    ticks: u64,
+    time_scale: f32,
+    paused: bool,
+}
+
+impl Time {
+    pub fn new(tick: Duration) -> Self {
+        Self {
+            tick,
+            ticks: 0,
+            time_scale: 1.0,
+            paused: false,
+        }
+    }
+
+    // Number of ticks run so far, including the current one.
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    // Seconds per tick.
+    pub fn delta(&self) -> f32 {
+        self.tick.as_secs_f32()
+    }
+
+    pub fn tick_duration(&self) -> Duration {
+        self.tick
+    }
+
+    // Game time elapsed since the start.
+    pub fn total(&self) -> Duration {
+        Duration::from_nanos((self.tick.as_nanos() * self.ticks as u128) as u64)
+    }
+
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale
+    }
+
+    // Values below 1.0 slow the game down, values above speed it up. Ticks keep the same
+    // duration, so only how many of them run per second changes.
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        self.time_scale = time_scale.max(0.0);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    // No ticks run while paused, but the game is still rendered.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    pub(crate) fn advance(&mut self) {
+        self.ticks += 1;
+    }
+
+    // How much game time `elapsed` real time is worth.
+    pub(crate) fn scale(&self, elapsed: Duration) -> Duration {
+        if self.paused {
+            Duration::ZERO
+        } else {
+            elapsed.mul_f32(self.time_scale)
+        }
+    }
+}
+
+// Turns the time given by a `Clock` into whole ticks.
+pub(crate) struct FixedTimestep {
+    accumulator: Duration,
+    max_frame_time: Duration,
+}
+
+impl FixedTimestep {
+    pub(crate) fn new(tick: Duration, max_ticks_per_frame: u32) -> Self {
+        Self {
+            accumulator: Duration::ZERO,
+            max_frame_time: tick * max_ticks_per_frame.max(1),
+        }
+    }
+
+    // Adds the time of a frame, returning how many ticks are due.
+    pub(crate) fn add_frame(&mut self, elapsed: Duration, time: &Time) -> u32 {
+        self.accumulator += time.scale(elapsed).min(self.max_frame_time);
+        let ticks = self.accumulator.as_nanos() / time.tick.as_nanos().max(1);
+        self.accumulator -= time.tick * ticks as u32;
+        ticks as u32
+    }
+
+    // How far the current time is between the last tick and the next one, from 0.0 to 1.0.
+    pub(crate) fn alpha(&self, time: &Time) -> f32 {
+        let delta = time.delta();
+        // Zero length ticks leave nothing to interpolate, the last one is the current time.
+        if delta <= 0.0 {
+            return 1.0;
+        }
+        self.accumulator.as_secs_f32() / delta
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TICK: Duration = Duration::from_millis(10);
+
+    #[test]
+    fn ticks_and_alpha() {
+        let time = Time::new(TICK);
+        let mut timestep = FixedTimestep::new(TICK, 5);
+        assert_eq!(timestep.add_frame(Duration::from_millis(25), &time), 2);
+        assert!((timestep.alpha(&time) - 0.5).abs() < 1e-4);
+        assert_eq!(timestep.add_frame(Duration::from_millis(5), &time), 1);
+        assert!(timestep.alpha(&time).abs() < 1e-4);
+    }
+
+    #[test]
+    fn long_frames_are_capped() {
+        let time = Time::new(TICK);
+        let mut timestep = FixedTimestep::new(TICK, 5);
+        assert_eq!(timestep.add_frame(Duration::from_secs(1), &time), 5);
+        assert_eq!(timestep.alpha(&time), 0.0);
+    }
+
+    #[test]
+    fn paused_and_scaled_time() {
+        let mut time = Time::new(TICK);
+        let mut timestep = FixedTimestep::new(TICK, 5);
+        time.set_paused(true);
+        assert_eq!(timestep.add_frame(Duration::from_millis(30), &time), 0);
+        time.set_paused(false);
+        time.set_time_scale(0.5);
+        assert_eq!(timestep.add_frame(Duration::from_millis(30), &time), 1);
+        assert!((timestep.alpha(&time) - 0.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn alpha_of_zero_length_ticks() {
+        let time = Time::new(Duration::ZERO);
+        let timestep = FixedTimestep::new(Duration::ZERO, 5);
+        assert_eq!(timestep.alpha(&time), 1.0);
+    }
+}
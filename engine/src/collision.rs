@@ -0,0 +1,424 @@
+use cgmath::{InnerSpace, Vector2, Zero};
+use winit::dpi::LogicalSize;
+
+// Where a moving shape first touches another.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Hit {
+    // Fraction of the movement done at the time of contact, from 0.0 to 1.0.
+    pub time: f32,
+    // Of the surface that was hit, pointing towards the moving shape. Zero when the shapes
+    // already overlapped.
+    pub normal: Vector2<f32>,
+}
+
+// An axis-aligned box. Boxes that only touch along an edge don't intersect.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vector2<f32>,
+    pub max: Vector2<f32>,
+}
+
+impl Aabb {
+    pub fn new(min: Vector2<f32>, max: Vector2<f32>) -> Self {
+        Self { min, max }
+    }
+
+    // The box of a sprite or rectangle, `position` being its bottom left corner.
+    pub fn from_position(position: Vector2<f32>, size: LogicalSize<f32>) -> Self {
+        Self::new(position, position + Vector2::new(size.width, size.height))
+    }
+
+    pub fn width(&self) -> f32 {
+        self.max.x - self.min.x
+    }
+
+    pub fn height(&self) -> f32 {
+        self.max.y - self.min.y
+    }
+
+    pub fn center(&self) -> Vector2<f32> {
+        (self.min + self.max) / 2.0
+    }
+
+    pub fn translate(&self, offset: Vector2<f32>) -> Self {
+        Self::new(self.min + offset, self.max + offset)
+    }
+
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.x < other.max.x
+            && self.max.x > other.min.x
+            && self.min.y < other.max.y
+            && self.max.y > other.min.y
+    }
+
+    // Points on the edges are inside.
+    pub fn contains_point(&self, point: Vector2<f32>) -> bool {
+        point.x >= self.min.x
+            && point.x <= self.max.x
+            && point.y >= self.min.y
+            && point.y <= self.max.y
+    }
+
+    pub fn contains(&self, other: &Aabb) -> bool {
+        self.contains_point(other.min) && self.contains_point(other.max)
+    }
+
+    // The smallest box holding both.
+    pub fn union(&self, other: &Aabb) -> Self {
+        Self::new(
+            Vector2::new(self.min.x.min(other.min.x), self.min.y.min(other.min.y)),
+            Vector2::new(self.max.x.max(other.max.x), self.max.y.max(other.max.y)),
+        )
+    }
+
+    // Grows every side by `amount`, or shrinks them if it's negative.
+    pub fn inflate(&self, amount: f32) -> Self {
+        let amount = Vector2::new(amount, amount);
+        Self::new(self.min - amount, self.max + amount)
+    }
+
+    // The shortest move that takes this box out of `other`, if they intersect.
+    pub fn penetration(&self, other: &Aabb) -> Option<Vector2<f32>> {
+        let overlap_x = self.max.x.min(other.max.x) - self.min.x.max(other.min.x);
+        let overlap_y = self.max.y.min(other.max.y) - self.min.y.max(other.min.y);
+        if overlap_x <= 0.0 || overlap_y <= 0.0 {
+            return None;
+        }
+        let away = self.center() - other.center();
+        let sign = |d: f32| if d < 0.0 { -1.0 } else { 1.0 };
+        Some(if overlap_x < overlap_y {
+            Vector2::new(overlap_x * sign(away.x), 0.0)
+        } else {
+            Vector2::new(0.0, overlap_y * sign(away.y))
+        })
+    }
+
+    // Moves this box by `velocity` and returns when it first hits `other`, so fast boxes
+    // can't go through thin ones between two updates.
+    pub fn sweep(&self, velocity: Vector2<f32>, other: &Aabb) -> Option<Hit> {
+        let mut entry = f32::NEG_INFINITY;
+        let mut exit = f32::INFINITY;
+        let mut normal = Vector2::zero();
+        for axis in 0..2 {
+            let (min, max) = (self.min[axis], self.max[axis]);
+            let (other_min, other_max) = (other.min[axis], other.max[axis]);
+            let speed = velocity[axis];
+            if speed == 0.0 {
+                if max <= other_min || min >= other_max {
+                    return None;
+                }
+                continue;
+            }
+            let (near, far) = if speed > 0.0 {
+                ((other_min - max) / speed, (other_max - min) / speed)
+            } else {
+                ((other_max - min) / speed, (other_min - max) / speed)
+            };
+            if near > entry {
+                entry = near;
+                normal = Vector2::zero();
+                normal[axis] = -speed.signum();
+            }
+            exit = exit.min(far);
+        }
+        if entry >= exit || entry > 1.0 || exit <= 0.0 {
+            return None;
+        }
+        Some(if entry < 0.0 {
+            Hit {
+                time: 0.0,
+                normal: Vector2::zero(),
+            }
+        } else {
+            Hit {
+                time: entry,
+                normal,
+            }
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Circle {
+    pub center: Vector2<f32>,
+    pub radius: f32,
+}
+
+impl Circle {
+    pub fn new(center: Vector2<f32>, radius: f32) -> Self {
+        Self { center, radius }
+    }
+
+    pub fn contains_point(&self, point: Vector2<f32>) -> bool {
+        (point - self.center).magnitude2() <= self.radius * self.radius
+    }
+
+    pub fn intersects(&self, other: &Circle) -> bool {
+        let radius = self.radius + other.radius;
+        (other.center - self.center).magnitude2() < radius * radius
+    }
+
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        let closest = Vector2::new(
+            self.center.x.clamp(aabb.min.x, aabb.max.x),
+            self.center.y.clamp(aabb.min.y, aabb.max.y),
+        );
+        (closest - self.center).magnitude2() < self.radius * self.radius
+    }
+}
+
+// A line from `start` to `end`, eg. a ray or the path of a bullet.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Segment {
+    pub start: Vector2<f32>,
+    pub end: Vector2<f32>,
+}
+
+impl Segment {
+    pub fn new(start: Vector2<f32>, end: Vector2<f32>) -> Self {
+        Self { start, end }
+    }
+
+    // The point of the segment nearest to `point`.
+    pub fn closest_point(&self, point: Vector2<f32>) -> Vector2<f32> {
+        let direction = self.end - self.start;
+        let length2 = direction.magnitude2();
+        if length2 == 0.0 {
+            return self.start;
+        }
+        let t = ((point - self.start).dot(direction) / length2).clamp(0.0, 1.0);
+        self.start + direction * t
+    }
+
+    // Where the segment first enters `aabb`, `time` being the fraction of the way from
+    // `start` to `end`.
+    pub fn cast(&self, aabb: &Aabb) -> Option<Hit> {
+        Aabb::new(self.start, self.start).sweep(self.end - self.start, aabb)
+    }
+
+    pub fn intersects_circle(&self, circle: &Circle) -> bool {
+        (self.closest_point(circle.center) - circle.center).magnitude2()
+            <= circle.radius * circle.radius
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aabb(min_x: f32, min_y: f32, max_x: f32, max_y: f32) -> Aabb {
+        Aabb::new(Vector2::new(min_x, min_y), Vector2::new(max_x, max_y))
+    }
+
+    fn assert_hit(hit: Option<Hit>, time: f32, normal: Vector2<f32>) {
+        let hit = hit.expect("no hit");
+        assert!(
+            (hit.time - time).abs() < 1e-5,
+            "{:?} is not at {}",
+            hit,
+            time
+        );
+        assert_eq!(hit.normal, normal);
+    }
+
+    #[test]
+    fn aabb_ops() {
+        let a = Aabb::from_position(Vector2::new(1.0, 2.0), LogicalSize::new(4.0, 2.0));
+        assert_eq!(a, aabb(1.0, 2.0, 5.0, 4.0));
+        assert_eq!((a.width(), a.height()), (4.0, 2.0));
+        assert_eq!(a.center(), Vector2::new(3.0, 3.0));
+        assert_eq!(
+            a.translate(Vector2::new(-1.0, 1.0)),
+            aabb(0.0, 3.0, 4.0, 5.0)
+        );
+        assert_eq!(a.inflate(1.0), aabb(0.0, 1.0, 6.0, 5.0));
+        assert_eq!(a.inflate(-0.5), aabb(1.5, 2.5, 4.5, 3.5));
+        assert_eq!(
+            a.union(&aabb(-1.0, 3.0, 2.0, 6.0)),
+            aabb(-1.0, 2.0, 5.0, 6.0)
+        );
+
+        assert!(a.contains(&aabb(2.0, 2.5, 5.0, 4.0)));
+        assert!(!a.contains(&aabb(2.0, 2.5, 6.0, 4.0)));
+        assert!(a.contains_point(Vector2::new(3.0, 3.0)));
+        assert!(a.contains_point(Vector2::new(5.0, 2.0)));
+        assert!(!a.contains_point(Vector2::new(5.1, 2.0)));
+    }
+
+    #[test]
+    fn intersection_and_penetration() {
+        let a = aabb(0.0, 0.0, 2.0, 2.0);
+        assert!(a.intersects(&aabb(1.0, 1.5, 3.0, 3.0)));
+        // Out along the least overlapping axis, away from the other box.
+        assert_eq!(
+            a.penetration(&aabb(1.0, 1.5, 3.0, 3.0)),
+            Some(Vector2::new(0.0, -0.5))
+        );
+        assert_eq!(
+            a.penetration(&aabb(-1.5, -1.0, 0.5, 3.0)),
+            Some(Vector2::new(0.5, 0.0))
+        );
+    }
+
+    #[test]
+    fn edge_and_corner_touches() {
+        let a = aabb(0.0, 0.0, 2.0, 2.0);
+        for other in [
+            aabb(2.0, 0.0, 4.0, 2.0),
+            aabb(0.0, -2.0, 2.0, 0.0),
+            aabb(2.0, 2.0, 3.0, 3.0),
+        ] {
+            assert!(!a.intersects(&other), "{:?}", other);
+            assert!(!other.intersects(&a), "{:?}", other);
+            assert_eq!(a.penetration(&other), None);
+            assert_eq!(a.sweep(Vector2::zero(), &other), None);
+        }
+
+        // Sliding along a floor.
+        let floor = aabb(2.0, -1.0, 10.0, 0.0);
+        assert_eq!(a.sweep(Vector2::new(5.0, 0.0), &floor), None);
+        // Moving away from a wall it touches.
+        let wall = aabb(2.0, 0.0, 3.0, 2.0);
+        assert_eq!(a.sweep(Vector2::new(-1.0, 0.0), &wall), None);
+        // Moving into it.
+        assert_hit(
+            a.sweep(Vector2::new(1.0, 0.0), &wall),
+            0.0,
+            Vector2::new(-1.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn sweep() {
+        let a = aabb(0.0, 0.0, 1.0, 1.0);
+        let wall = aabb(5.0, 0.0, 6.0, 1.0);
+        assert_hit(
+            a.sweep(Vector2::new(10.0, 0.0), &wall),
+            0.4,
+            Vector2::new(-1.0, 0.0),
+        );
+        // Falling onto a floor.
+        let above = a.translate(Vector2::new(0.0, 5.0));
+        assert_hit(
+            above.sweep(Vector2::new(0.0, -10.0), &aabb(-2.0, 0.0, 4.0, 1.0)),
+            0.4,
+            Vector2::new(0.0, 1.0),
+        );
+        // Diagonally into a corner, the x axis wins the tie.
+        assert_hit(
+            a.sweep(Vector2::new(2.0, 2.0), &aabb(2.0, 2.0, 3.0, 3.0)),
+            0.5,
+            Vector2::new(-1.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn sweep_misses() {
+        let a = aabb(0.0, 0.0, 1.0, 1.0);
+        // Passes above.
+        assert_eq!(
+            a.sweep(Vector2::new(10.0, 0.0), &aabb(5.0, 2.0, 6.0, 3.0)),
+            None
+        );
+        // Stops short.
+        assert_eq!(
+            a.sweep(Vector2::new(2.0, 0.0), &aabb(5.0, 0.0, 6.0, 1.0)),
+            None
+        );
+        // Moves away.
+        assert_eq!(
+            a.sweep(Vector2::new(-10.0, 0.0), &aabb(5.0, 0.0, 6.0, 1.0)),
+            None
+        );
+        // Not moving.
+        assert_eq!(a.sweep(Vector2::zero(), &aabb(5.0, 0.0, 6.0, 1.0)), None);
+    }
+
+    #[test]
+    fn sweep_from_overlap() {
+        let a = aabb(0.0, 0.0, 1.0, 1.0);
+        let other = aabb(0.5, 0.5, 2.0, 2.0);
+        for velocity in [
+            Vector2::new(1.0, 0.0),
+            Vector2::new(-1.0, 0.0),
+            Vector2::zero(),
+        ] {
+            assert_hit(a.sweep(velocity, &other), 0.0, Vector2::zero());
+        }
+    }
+
+    #[test]
+    fn circles() {
+        let circle = Circle::new(Vector2::new(0.0, 0.0), 1.0);
+        assert!(circle.contains_point(Vector2::new(0.0, 1.0)));
+        assert!(!circle.contains_point(Vector2::new(0.8, 0.8)));
+
+        assert!(circle.intersects(&Circle::new(Vector2::new(1.5, 0.0), 1.0)));
+        // Touching.
+        assert!(!circle.intersects(&Circle::new(Vector2::new(2.0, 0.0), 1.0)));
+
+        assert!(circle.intersects_aabb(&aabb(-0.5, -0.5, 0.5, 0.5)));
+        assert!(circle.intersects_aabb(&aabb(0.5, -3.0, 3.0, 3.0)));
+        // Touching an edge, then near a corner.
+        assert!(!circle.intersects_aabb(&aabb(1.0, -3.0, 3.0, 3.0)));
+        assert!(!circle.intersects_aabb(&aabb(0.75, 0.75, 3.0, 3.0)));
+        assert!(circle.intersects_aabb(&aabb(0.7, 0.7, 3.0, 3.0)));
+    }
+
+    #[test]
+    fn segments() {
+        let segment = Segment::new(Vector2::new(0.0, 0.0), Vector2::new(10.0, 0.0));
+        assert_eq!(
+            segment.closest_point(Vector2::new(5.0, 3.0)),
+            Vector2::new(5.0, 0.0)
+        );
+        assert_eq!(
+            segment.closest_point(Vector2::new(-5.0, 3.0)),
+            Vector2::new(0.0, 0.0)
+        );
+        assert_eq!(
+            segment.closest_point(Vector2::new(12.0, -1.0)),
+            Vector2::new(10.0, 0.0)
+        );
+        let point = Segment::new(Vector2::new(1.0, 1.0), Vector2::new(1.0, 1.0));
+        assert_eq!(
+            point.closest_point(Vector2::new(5.0, 3.0)),
+            Vector2::new(1.0, 1.0)
+        );
+
+        // Unlike boxes, touching circles count.
+        assert!(segment.intersects_circle(&Circle::new(Vector2::new(5.0, 1.0), 1.0)));
+        assert!(!segment.intersects_circle(&Circle::new(Vector2::new(5.0, 1.5), 1.0)));
+        assert!(segment.intersects_circle(&Circle::new(Vector2::new(11.0, 0.0), 1.0)));
+    }
+
+    #[test]
+    fn segment_casts() {
+        let target = aabb(4.0, -1.0, 6.0, 1.0);
+        assert_hit(
+            Segment::new(Vector2::new(0.0, 0.0), Vector2::new(10.0, 0.0)).cast(&target),
+            0.4,
+            Vector2::new(-1.0, 0.0),
+        );
+        assert_hit(
+            Segment::new(Vector2::new(5.0, 5.0), Vector2::new(5.0, -5.0)).cast(&target),
+            0.4,
+            Vector2::new(0.0, 1.0),
+        );
+        // From inside.
+        assert_hit(
+            Segment::new(Vector2::new(5.0, 0.0), Vector2::new(10.0, 0.0)).cast(&target),
+            0.0,
+            Vector2::zero(),
+        );
+        // Too short, then along an edge.
+        assert_eq!(
+            Segment::new(Vector2::new(0.0, 0.0), Vector2::new(3.0, 0.0)).cast(&target),
+            None
+        );
+        assert_eq!(
+            Segment::new(Vector2::new(0.0, 1.0), Vector2::new(10.0, 1.0)).cast(&target),
+            None
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use crate::app::Game;
+use crate::clock::{Clock, FixedTimestep, Time};
+use crate::input::bindings::Action;
+use crate::input::gamepad::VirtualGamepads;
+use crate::input::replay::{Recording, TickInput};
+use crate::input::Input;
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::Duration;
+
+// Input for a headless run, given as the ticks on which actions are pressed and released.
+pub struct InputScript<A: Action> {
+    changes: BTreeMap<u64, Vec<(A, bool)>>,
+    axes: BTreeMap<u64, Vec<(A, f32)>>,
+    length: u64,
+}
+
+impl<A: Action> Default for InputScript<A> {
+    fn default() -> Self {
+        Self {
+            changes: BTreeMap::new(),
+            axes: BTreeMap::new(),
+            length: 0,
+        }
+    }
+}
+
+impl<A: Action> InputScript<A> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn press(mut self, tick: u64, action: A) -> Self {
+        self.changes.entry(tick).or_default().push((action, true));
+        self.length = self.length.max(tick + 1);
+        self
+    }
+
+    pub fn release(mut self, tick: u64, action: A) -> Self {
+        self.changes.entry(tick).or_default().push((action, false));
+        self.length = self.length.max(tick + 1);
+        self
+    }
+
+    // Holds `action` down for `ticks` ticks, starting at `tick`.
+    pub fn hold(self, tick: u64, ticks: u64, action: A) -> Self {
+        self.press(tick, action)
+            .release(tick + ticks.max(1), action)
+    }
+
+    // Presses `action` for a single tick.
+    pub fn tap(self, tick: u64, action: A) -> Self {
+        self.hold(tick, 1, action)
+    }
+
+    // Sets `axis` to `value` from `tick` onwards.
+    pub fn set_axis(mut self, tick: u64, axis: A, value: f32) -> Self {
+        self.axes.entry(tick).or_default().push((axis, value));
+        self.length = self.length.max(tick + 1);
+        self
+    }
+
+    // Keeps the script going with no changes until `ticks` ticks have passed.
+    pub fn wait_until(mut self, ticks: u64) -> Self {
+        self.length = self.length.max(ticks);
+        self
+    }
+
+    pub fn len(&self) -> u64 {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    // The input of every tick of the script.
+    pub fn ticks(&self) -> Vec<TickInput<A>> {
+        let mut down = BTreeSet::new();
+        let mut axes = BTreeMap::new();
+        let mut ticks = Vec::with_capacity(self.length as usize);
+        for tick in 0..self.length {
+            let mut input = TickInput::default();
+            for (action, pressed) in self.changes.get(&tick).into_iter().flatten() {
+                if *pressed {
+                    if down.insert(*action) {
+                        input.pressed.push(*action);
+                    }
+                } else if down.remove(action) {
+                    input.released.push(*action);
+                }
+            }
+            for (axis, value) in self.axes.get(&tick).into_iter().flatten() {
+                axes.insert(*axis, *value);
+            }
+            input.down = down.iter().copied().collect();
+            input.axes = axes.iter().map(|(axis, value)| (*axis, *value)).collect();
+            ticks.push(input);
+        }
+        ticks
+    }
+}
+
+// Runs a `Game` without a window, GPU or real time: every call to `step` advances the game by
+// exactly one tick, and `frame` advances it by the time given by a `Clock`, like `App` does.
+// Useful to test game logic, or to check a recording reproduces the expected outcome.
+pub struct HeadlessRunner<G: Game> {
+    pub game: G,
+    pub input: Input<G::Action>,
+    // Feed gamepad events through here to exercise the live input path rather than a script.
+    pub gamepads: VirtualGamepads,
+    pub time: Time,
+    timestep: FixedTimestep,
+}
+
+impl<G: Game> HeadlessRunner<G> {
+    pub fn new(game: G, input: Input<G::Action>, tick: Duration) -> Self {
+        Self {
+            game,
+            input,
+            gamepads: VirtualGamepads::new(),
+            time: Time::new(tick),
+            timestep: FixedTimestep::new(tick, u32::MAX),
+        }
+    }
+
+    // Number of ticks run so far.
+    pub fn ticks(&self) -> u64 {
+        self.time.ticks()
+    }
+
+    // Runs the ticks that fit in the time elapsed on `clock`, honouring the time scale and pause.
+    // Returns false once the game wants to exit.
+    pub fn frame(&mut self, clock: &mut dyn Clock) -> bool {
+        let ticks = self.timestep.add_frame(clock.elapsed(), &self.time);
+        (0..ticks).all(|_| self.step())
+    }
+
+    // Runs one tick with the live input. Returns false once the game wants to exit.
+    pub fn step(&mut self) -> bool {
+        self.input.poll_gamepads(&mut self.gamepads);
+        self.time.advance();
+        if !self.game.begin_tick(&mut self.input, &self.time) {
+            return false;
+        }
+        self.update()
+    }
+
+    // Runs one tick with the given input instead of the live one.
+    pub fn step_with(&mut self, tick: &TickInput<G::Action>) -> bool {
+        self.time.advance();
+        self.input.replay_tick(tick, self.time.delta());
+        self.update()
+    }
+
+    fn update(&mut self) -> bool {
+        self.game.update(&self.input, &self.time);
+        !self.game.should_exit()
+    }
+
+    // Runs up to `ticks` ticks, returning how many ran before the game exited.
+    pub fn run(&mut self, ticks: u64) -> u64 {
+        let start = self.ticks();
+        for _ in 0..ticks {
+            if !self.step() {
+                break;
+            }
+        }
+        self.ticks() - start
+    }
+
+    // Runs until `condition` holds, for at most `max_ticks` ticks. Returns whether it held.
+    pub fn run_until(&mut self, max_ticks: u64, mut condition: impl FnMut(&G) -> bool) -> bool {
+        for _ in 0..max_ticks {
+            if condition(&self.game) {
+                return true;
+            }
+            if !self.step() {
+                break;
+            }
+        }
+        condition(&self.game)
+    }
+
+    pub fn run_script(&mut self, script: &InputScript<G::Action>) -> u64 {
+        self.run_ticks(script.ticks().iter())
+    }
+
+    pub fn run_recording(&mut self, recording: &Recording<G::Action>) -> u64 {
+        self.run_ticks(recording.ticks())
+    }
+
+    fn run_ticks<'a>(&mut self, ticks: impl Iterator<Item = &'a TickInput<G::Action>>) -> u64 {
+        let start = self.ticks();
+        for tick in ticks {
+            if !self.step_with(tick) {
+                break;
+            }
+        }
+        self.ticks() - start
+    }
+}
@@ -0,0 +1,308 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::fs;
+use std::hash::Hash;
+use std::path::Path;
+use winit::event::{MouseButton, VirtualKeyCode};
+
+// Identifies an action or an axis. Games usually implement this with an enum, eg:
+// `enum Action { Jump, MoveLeft, MoveRight }`, deriving the traits below.
+pub trait Action: Copy + Eq + Ord + Hash + Debug + Serialize + DeserializeOwned + 'static {}
+
+impl<T> Action for T where T: Copy + Eq + Ord + Hash + Debug + Serialize + DeserializeOwned + 'static
+{}
+
+// Buttons named after their position, so they map to any controller layout.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+pub enum GamepadButton {
+    South,
+    East,
+    West,
+    North,
+    LeftShoulder,
+    RightShoulder,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+impl GamepadButton {
+    pub const ALL: [GamepadButton; 16] = [
+        GamepadButton::South,
+        GamepadButton::East,
+        GamepadButton::West,
+        GamepadButton::North,
+        GamepadButton::LeftShoulder,
+        GamepadButton::RightShoulder,
+        GamepadButton::LeftTrigger,
+        GamepadButton::RightTrigger,
+        GamepadButton::Select,
+        GamepadButton::Start,
+        GamepadButton::LeftStick,
+        GamepadButton::RightStick,
+        GamepadButton::DPadUp,
+        GamepadButton::DPadDown,
+        GamepadButton::DPadLeft,
+        GamepadButton::DPadRight,
+    ];
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+}
+
+impl GamepadAxis {
+    pub const ALL: [GamepadAxis; 4] = [
+        GamepadAxis::LeftStickX,
+        GamepadAxis::LeftStickY,
+        GamepadAxis::RightStickX,
+        GamepadAxis::RightStickY,
+    ];
+}
+
+// A physical input that can trigger an action.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum Binding {
+    Key(VirtualKeyCode),
+    Mouse(MouseButton),
+    GamepadButton(GamepadButton),
+    // A stick pushed past half way, in the positive or negative direction.
+    GamepadStick { axis: GamepadAxis, positive: bool },
+}
+
+// A physical input producing a value between -1.0 and 1.0.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum AxisBinding {
+    // -1.0 while `negative` is down, 1.0 while `positive` is down.
+    Digital {
+        negative: Binding,
+        positive: Binding,
+    },
+    Analog(GamepadAxis),
+}
+
+// Maps actions and axes to the inputs that trigger them. Can be saved to and loaded from a RON
+// file, so players can rebind their controls.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct InputMap<A: Action> {
+    #[serde(default)]
+    actions: BTreeMap<A, Vec<Binding>>,
+    #[serde(default)]
+    axes: BTreeMap<A, Vec<AxisBinding>>,
+}
+
+impl<A: Action> Default for InputMap<A> {
+    fn default() -> Self {
+        Self {
+            actions: BTreeMap::new(),
+            axes: BTreeMap::new(),
+        }
+    }
+}
+
+impl<A: Action> InputMap<A> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_action(mut self, action: A, bindings: &[Binding]) -> Self {
+        for binding in bindings {
+            self.bind(action, *binding);
+        }
+        self
+    }
+
+    pub fn with_axis(mut self, axis: A, bindings: &[AxisBinding]) -> Self {
+        for binding in bindings {
+            self.bind_axis(axis, *binding);
+        }
+        self
+    }
+
+    pub fn bind(&mut self, action: A, binding: Binding) {
+        let bindings = self.actions.entry(action).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    pub fn unbind(&mut self, action: A, binding: Binding) {
+        if let Some(bindings) = self.actions.get_mut(&action) {
+            bindings.retain(|b| *b != binding);
+        }
+    }
+
+    // Replaces all the bindings of `action` with `binding`, removing `binding` from any other
+    // action so that a single input doesn't trigger two actions.
+    pub fn rebind(&mut self, action: A, binding: Binding) {
+        for bindings in self.actions.values_mut() {
+            bindings.retain(|b| *b != binding);
+        }
+        self.actions.insert(action, vec![binding]);
+    }
+
+    pub fn clear(&mut self, action: A) {
+        self.actions.remove(&action);
+        self.axes.remove(&action);
+    }
+
+    pub fn bind_axis(&mut self, axis: A, binding: AxisBinding) {
+        let bindings = self.axes.entry(axis).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    pub fn bindings(&self, action: A) -> &[Binding] {
+        self.actions.get(&action).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    pub fn axis_bindings(&self, axis: A) -> &[AxisBinding] {
+        self.axes.get(&axis).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    pub fn actions(&self) -> impl Iterator<Item = A> + '_ {
+        self.actions.keys().copied()
+    }
+
+    pub fn axes(&self) -> impl Iterator<Item = A> + '_ {
+        self.axes.keys().copied()
+    }
+
+    pub fn from_ron(ron: &str) -> anyhow::Result<Self> {
+        Ok(ron::from_str(ron)?)
+    }
+
+    pub fn to_ron(&self) -> anyhow::Result<String> {
+        Ok(ron::ser::to_string_pretty(
+            self,
+            ron::ser::PrettyConfig::default(),
+        )?)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let ron = fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+        Self::from_ron(&ron)
+            .map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", path.display(), e))
+    }
+
+    // Loads the bindings from `path`, falling back to `default` if the file doesn't exist.
+    pub fn load_or(path: impl AsRef<Path>, default: Self) -> anyhow::Result<Self> {
+        if path.as_ref().exists() {
+            Self::load(path)
+        } else {
+            Ok(default)
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        fs::write(path, self.to_ron()?)
+            .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+    enum TestAction {
+        Jump,
+        Fire,
+        MoveX,
+    }
+
+    const SPACE: Binding = Binding::Key(VirtualKeyCode::Space);
+    const SOUTH: Binding = Binding::GamepadButton(GamepadButton::South);
+    const CLICK: Binding = Binding::Mouse(MouseButton::Left);
+
+    fn map() -> InputMap<TestAction> {
+        InputMap::new()
+            .with_action(TestAction::Jump, &[SPACE, SOUTH])
+            .with_action(TestAction::Fire, &[CLICK])
+            .with_axis(
+                TestAction::MoveX,
+                &[
+                    AxisBinding::Digital {
+                        negative: Binding::Key(VirtualKeyCode::Left),
+                        positive: Binding::Key(VirtualKeyCode::Right),
+                    },
+                    AxisBinding::Analog(GamepadAxis::LeftStickX),
+                ],
+            )
+    }
+
+    fn assert_same(a: &InputMap<TestAction>, b: &InputMap<TestAction>) {
+        assert_eq!(
+            a.actions().collect::<Vec<_>>(),
+            b.actions().collect::<Vec<_>>()
+        );
+        assert_eq!(a.axes().collect::<Vec<_>>(), b.axes().collect::<Vec<_>>());
+        for action in a.actions() {
+            assert_eq!(a.bindings(action), b.bindings(action));
+        }
+        for axis in a.axes() {
+            assert_eq!(a.axis_bindings(axis), b.axis_bindings(axis));
+        }
+    }
+
+    #[test]
+    fn rebind_takes_the_binding_from_other_actions() {
+        let mut map = map();
+        map.rebind(TestAction::Fire, SPACE);
+        assert_eq!(map.bindings(TestAction::Fire), [SPACE]);
+        assert_eq!(map.bindings(TestAction::Jump), [SOUTH]);
+
+        // Binding twice doesn't duplicate it.
+        map.bind(TestAction::Jump, SOUTH);
+        assert_eq!(map.bindings(TestAction::Jump), [SOUTH]);
+        map.unbind(TestAction::Jump, SOUTH);
+        assert!(map.bindings(TestAction::Jump).is_empty());
+    }
+
+    #[test]
+    fn ron_round_trip() {
+        let map = map();
+        let ron = map.to_ron().unwrap();
+        assert_same(&InputMap::from_ron(&ron).unwrap(), &map);
+
+        // Missing sections are empty.
+        let actions_only =
+            InputMap::<TestAction>::from_ron("(actions: {Jump: [Key(Space)]})").unwrap();
+        assert_eq!(actions_only.bindings(TestAction::Jump), [SPACE]);
+        assert_eq!(actions_only.axes().count(), 0);
+        assert!(InputMap::<TestAction>::from_ron("(actions: {Dance: []})").is_err());
+    }
+
+    #[test]
+    fn load_or_default() {
+        let path = std::env::temp_dir().join(format!("engine-bindings-{}.ron", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let loaded = InputMap::load_or(&path, map()).unwrap();
+        assert_same(&loaded, &map());
+
+        let mut rebound = map();
+        rebound.rebind(TestAction::Fire, SOUTH);
+        rebound.save(&path).unwrap();
+        let loaded = InputMap::load_or(&path, map()).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_same(&loaded, &rebound);
+    }
+}
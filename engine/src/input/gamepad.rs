@@ -0,0 +1,198 @@
+use crate::input::bindings::{GamepadAxis, GamepadButton};
+use std::collections::VecDeque;
+
+pub type GamepadId = usize;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum GamepadEvent {
+    Connected(GamepadId),
+    Disconnected(GamepadId),
+    Button {
+        gamepad: GamepadId,
+        button: GamepadButton,
+        pressed: bool,
+    },
+    Axis {
+        gamepad: GamepadId,
+        axis: GamepadAxis,
+        value: f32,
+    },
+}
+
+// The device layer gamepad events come from. Games use the gilrs backend, while tests can drive
+// `VirtualGamepads` without any hardware.
+pub trait GamepadBackend {
+    // Returns the events that happened since the previous call.
+    fn poll(&mut self) -> Vec<GamepadEvent>;
+}
+
+// Gamepads controlled from code.
+#[derive(Default)]
+pub struct VirtualGamepads {
+    events: VecDeque<GamepadEvent>,
+}
+
+impl VirtualGamepads {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn connect(&mut self, gamepad: GamepadId) {
+        self.events.push_back(GamepadEvent::Connected(gamepad));
+    }
+
+    pub fn disconnect(&mut self, gamepad: GamepadId) {
+        self.events.push_back(GamepadEvent::Disconnected(gamepad));
+    }
+
+    pub fn press(&mut self, gamepad: GamepadId, button: GamepadButton) {
+        self.events.push_back(GamepadEvent::Button {
+            gamepad,
+            button,
+            pressed: true,
+        });
+    }
+
+    pub fn release(&mut self, gamepad: GamepadId, button: GamepadButton) {
+        self.events.push_back(GamepadEvent::Button {
+            gamepad,
+            button,
+            pressed: false,
+        });
+    }
+
+    pub fn move_axis(&mut self, gamepad: GamepadId, axis: GamepadAxis, value: f32) {
+        self.events.push_back(GamepadEvent::Axis {
+            gamepad,
+            axis,
+            value,
+        });
+    }
+}
+
+impl GamepadBackend for VirtualGamepads {
+    fn poll(&mut self) -> Vec<GamepadEvent> {
+        self.events.drain(..).collect()
+    }
+}
+
+// Radial dead zone applied to each stick. Movements below `inner` are ignored, and the range
+// between `inner` and `outer` is rescaled to 0.0 - 1.0, so worn sticks still reach full speed.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DeadZone {
+    pub inner: f32,
+    pub outer: f32,
+}
+
+impl Default for DeadZone {
+    fn default() -> Self {
+        Self {
+            inner: 0.15,
+            outer: 0.95,
+        }
+    }
+}
+
+impl DeadZone {
+    pub fn apply(&self, x: f32, y: f32) -> (f32, f32) {
+        let magnitude = (x * x + y * y).sqrt();
+        if magnitude <= self.inner {
+            return (0.0, 0.0);
+        }
+        let scaled = ((magnitude - self.inner) / (self.outer - self.inner)).min(1.0);
+        (x / magnitude * scaled, y / magnitude * scaled)
+    }
+}
+
+#[cfg(feature = "gilrs")]
+pub use self::gilrs_backend::GilrsBackend;
+
+#[cfg(feature = "gilrs")]
+mod gilrs_backend {
+    use super::{GamepadBackend, GamepadEvent};
+    use crate::input::bindings::{GamepadAxis, GamepadButton};
+    use gilrs::{Axis, Button, EventType, Gilrs};
+
+    pub struct GilrsBackend {
+        gilrs: Gilrs,
+        // Gamepads connected before the backend was created, reported on the first poll.
+        connected: Vec<GamepadEvent>,
+    }
+
+    impl GilrsBackend {
+        pub fn new() -> anyhow::Result<Self> {
+            let gilrs =
+                Gilrs::new().map_err(|e| anyhow::anyhow!("Failed to open gamepads: {}", e))?;
+            let connected = gilrs
+                .gamepads()
+                .map(|(id, _)| GamepadEvent::Connected(usize::from(id)))
+                .collect();
+            Ok(Self { gilrs, connected })
+        }
+    }
+
+    impl GamepadBackend for GilrsBackend {
+        fn poll(&mut self) -> Vec<GamepadEvent> {
+            let mut events = std::mem::take(&mut self.connected);
+            while let Some(event) = self.gilrs.next_event() {
+                let gamepad = usize::from(event.id);
+                match event.event {
+                    EventType::Connected => events.push(GamepadEvent::Connected(gamepad)),
+                    EventType::Disconnected => events.push(GamepadEvent::Disconnected(gamepad)),
+                    EventType::ButtonPressed(button, _) | EventType::ButtonReleased(button, _) => {
+                        if let Some(button) = map_button(button) {
+                            events.push(GamepadEvent::Button {
+                                gamepad,
+                                button,
+                                pressed: matches!(event.event, EventType::ButtonPressed(..)),
+                            });
+                        }
+                    }
+                    EventType::AxisChanged(axis, value, _) => {
+                        if let Some(axis) = map_axis(axis) {
+                            events.push(GamepadEvent::Axis {
+                                gamepad,
+                                axis,
+                                value,
+                            });
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            events
+        }
+    }
+
+    fn map_button(button: Button) -> Option<GamepadButton> {
+        Some(match button {
+            Button::South => GamepadButton::South,
+            Button::East => GamepadButton::East,
+            Button::West => GamepadButton::West,
+            Button::North => GamepadButton::North,
+            Button::LeftTrigger => GamepadButton::LeftShoulder,
+            Button::RightTrigger => GamepadButton::RightShoulder,
+            Button::LeftTrigger2 => GamepadButton::LeftTrigger,
+            Button::RightTrigger2 => GamepadButton::RightTrigger,
+            Button::Select => GamepadButton::Select,
+            Button::Start => GamepadButton::Start,
+            Button::LeftThumb => GamepadButton::LeftStick,
+            Button::RightThumb => GamepadButton::RightStick,
+            Button::DPadUp => GamepadButton::DPadUp,
+            Button::DPadDown => GamepadButton::DPadDown,
+            Button::DPadLeft => GamepadButton::DPadLeft,
+            Button::DPadRight => GamepadButton::DPadRight,
+            _ => return None,
+        })
+    }
+
+    fn map_axis(axis: Axis) -> Option<GamepadAxis> {
+        Some(match axis {
+            Axis::LeftStickX => GamepadAxis::LeftStickX,
+            Axis::LeftStickY => GamepadAxis::LeftStickY,
+            Axis::RightStickX => GamepadAxis::RightStickX,
+            Axis::RightStickY => GamepadAxis::RightStickY,
+            _ => return None,
+        })
+    }
+}
@@ -0,0 +1,701 @@
+pub mod bindings;
+pub mod gamepad;
+pub mod pointer;
+pub mod replay;
+
+use bindings::{Action, AxisBinding, Binding, GamepadAxis, GamepadButton, InputMap};
+use gamepad::{DeadZone, GamepadBackend, GamepadEvent, GamepadId};
+use pointer::Pointer;
+use replay::TickInput;
+use std::collections::{btree_map, BTreeMap, BTreeSet, HashMap, HashSet};
+use winit::event::{ElementState, KeyboardInput, MouseButton, TouchPhase, WindowEvent};
+
+#[derive(Copy, Clone, Debug, Default)]
+struct ActionState {
+    down: bool,
+    just_pressed: bool,
+    just_released: bool,
+    // Seconds the action has been held for.
+    held: f32,
+    // Seconds since the action was last pressed.
+    since_pressed: Option<f32>,
+}
+
+// Raw state of a connected gamepad, before dead zones are applied.
+#[derive(Clone, Debug, Default)]
+struct GamepadState {
+    buttons: HashSet<GamepadButton>,
+    axes: HashMap<GamepadAxis, f32>,
+}
+
+// How far a stick has to be pushed for a `Binding::GamepadStick` to count as pressed.
+const STICK_PRESS_THRESHOLD: f32 = 0.5;
+
+// Tracks the state of the physical inputs, and resolves them to the actions in the `InputMap`.
+// Games should query actions and axes, never keys or buttons directly.
+//
+// Actions are sampled by `begin_tick`, which should be called once before each game update, so
+// that queries like `just_pressed` are consistent for a whole tick no matter how many events
+// arrived in between.
+pub struct Input<A: Action> {
+    pub map: InputMap<A>,
+    pub dead_zone: DeadZone,
+    down: HashSet<Binding>,
+    pressed_since_tick: HashSet<Binding>,
+    released_since_tick: HashSet<Binding>,
+    gamepad_axes: HashMap<GamepadAxis, f32>,
+    gamepads: BTreeMap<GamepadId, GamepadState>,
+    pending_connections: Vec<GamepadEvent>,
+    connections: Vec<GamepadEvent>,
+    last_pressed: Option<Binding>,
+    actions: HashMap<A, ActionState>,
+    axes: HashMap<A, f32>,
+    last_tick: TickInput<A>,
+    pointer: Pointer,
+}
+
+impl<A: Action> Input<A> {
+    pub fn new(map: InputMap<A>) -> Self {
+        Self {
+            map,
+            dead_zone: DeadZone::default(),
+            down: HashSet::new(),
+            pressed_since_tick: HashSet::new(),
+            released_since_tick: HashSet::new(),
+            gamepad_axes: HashMap::new(),
+            gamepads: BTreeMap::new(),
+            pending_connections: vec![],
+            connections: vec![],
+            last_pressed: None,
+            actions: HashMap::new(),
+            axes: HashMap::new(),
+            last_tick: TickInput::default(),
+            pointer: Pointer::default(),
+        }
+    }
+
+    // Samples the actions and axes for the next tick, `dt` seconds after the previous one.
+    pub fn begin_tick(&mut self, dt: f32) {
+        self.pointer.begin_tick();
+        let tick = self.sample();
+        self.apply_tick(&tick, dt);
+        self.last_tick = tick;
+
+        self.pressed_since_tick.clear();
+        self.released_since_tick.clear();
+        self.connections = std::mem::take(&mut self.pending_connections);
+    }
+
+    // Starts the next tick with recorded input instead of the live one.
+    pub fn replay_tick(&mut self, tick: &TickInput<A>, dt: f32) {
+        self.apply_tick(tick, dt);
+        self.pointer.replay(tick.pointer, tick.wheel);
+        self.down
+            .retain(|binding| !matches!(binding, Binding::Mouse(_)));
+        self.down.extend(
+            tick.mouse_buttons
+                .iter()
+                .map(|button| Binding::Mouse(*button)),
+        );
+        self.last_tick = tick.clone();
+    }
+
+    // The input of the current tick, for recording.
+    pub fn last_tick(&self) -> &TickInput<A> {
+        &self.last_tick
+    }
+
+    fn sample(&self) -> TickInput<A> {
+        let mut tick = TickInput::default();
+        for action in self.map.actions() {
+            let bindings = self.map.bindings(action);
+            if bindings.iter().any(|b| self.down.contains(b)) {
+                tick.down.push(action);
+            }
+            if bindings.iter().any(|b| self.pressed_since_tick.contains(b)) {
+                tick.pressed.push(action);
+            }
+            if bindings
+                .iter()
+                .any(|b| self.released_since_tick.contains(b))
+            {
+                tick.released.push(action);
+            }
+        }
+        for axis in self.map.axes() {
+            tick.axes.push((axis, self.raw_axis(axis)));
+        }
+        tick.pointer = self.pointer.position();
+        tick.wheel = self.pointer.wheel().into();
+        for binding in &self.down {
+            if let Binding::Mouse(button) = binding {
+                tick.mouse_buttons.push(*button);
+            }
+        }
+        // In a fixed order, so runs of identical ticks are recorded once.
+        tick.mouse_buttons.sort_by_key(|button| match button {
+            MouseButton::Left => 0,
+            MouseButton::Right => 1,
+            MouseButton::Middle => 2,
+            MouseButton::Other(n) => 3 + *n as u32,
+        });
+        tick
+    }
+
+    fn apply_tick(&mut self, tick: &TickInput<A>, dt: f32) {
+        let mut actions: BTreeSet<A> = self.actions.keys().copied().collect();
+        actions.extend(tick.down.iter().chain(&tick.pressed).chain(&tick.released));
+        for action in actions {
+            let down = tick.down.contains(&action);
+            let pressed = tick.pressed.contains(&action);
+
+            let state = self.actions.entry(action).or_default();
+            // An action pressed and released between two ticks is still down for one tick, and
+            // released on the next.
+            let was_down = state.down;
+            state.down = down || pressed;
+            state.just_pressed = pressed;
+            state.just_released = was_down && !state.down;
+            state.held = match (state.down, pressed) {
+                (true, false) => state.held + dt,
+                _ => 0.0,
+            };
+            state.since_pressed = if pressed {
+                Some(0.0)
+            } else {
+                state.since_pressed.map(|t| t + dt)
+            };
+        }
+
+        self.axes = tick.axes.iter().copied().collect();
+    }
+
+    pub fn handle_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        virtual_keycode: Some(key),
+                        state,
+                        ..
+                    },
+                ..
+            } => self.set_binding(Binding::Key(*key), *state == ElementState::Pressed),
+            WindowEvent::MouseInput { button, state, .. } => {
+                self.set_mouse_button(*button, *state == ElementState::Pressed)
+            }
+            WindowEvent::CursorMoved { position, .. } => self.pointer.move_to(*position),
+            WindowEvent::CursorLeft { .. } => self.pointer.leave(),
+            WindowEvent::MouseWheel { delta, .. } => self.pointer.scroll(*delta),
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                self.pointer.set_scale_factor(*scale_factor)
+            }
+            // The primary touch acts as the left mouse button, so menus work on touch screens.
+            WindowEvent::Touch(touch) => {
+                let primary = self.pointer.touch(touch);
+                if primary {
+                    let pressed = matches!(touch.phase, TouchPhase::Started | TouchPhase::Moved);
+                    self.set_mouse_button(MouseButton::Left, pressed);
+                }
+            }
+            // Keys held while the window loses focus would otherwise stay down forever, as their
+            // release goes to another window. Gamepads don't depend on the focus.
+            WindowEvent::Focused(false) => {
+                let released: Vec<Binding> = self
+                    .down
+                    .iter()
+                    .copied()
+                    .filter(|binding| matches!(binding, Binding::Key(_) | Binding::Mouse(_)))
+                    .collect();
+                for binding in released {
+                    self.set_binding(binding, false);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn set_mouse_button(&mut self, button: MouseButton, pressed: bool) {
+        self.set_binding(Binding::Mouse(button), pressed);
+    }
+
+    // Feeds the events from `backend` into the input state. Call it once per frame, alongside
+    // `handle_event`.
+    pub fn poll_gamepads(&mut self, backend: &mut dyn GamepadBackend) {
+        for event in backend.poll() {
+            self.handle_gamepad_event(event);
+        }
+    }
+
+    // Any connected gamepad can trigger the bound actions, so players can switch controllers
+    // freely. Events of gamepads that aren't connected are ignored.
+    pub fn handle_gamepad_event(&mut self, event: GamepadEvent) {
+        match event {
+            GamepadEvent::Connected(gamepad) => {
+                // Some backends report gamepads connected at startup twice.
+                if let btree_map::Entry::Vacant(entry) = self.gamepads.entry(gamepad) {
+                    entry.insert(GamepadState::default());
+                    self.pending_connections.push(event);
+                }
+            }
+            // Buttons held on an unplugged gamepad are released.
+            GamepadEvent::Disconnected(gamepad) => {
+                if self.gamepads.remove(&gamepad).is_some() {
+                    self.pending_connections.push(event);
+                }
+            }
+            GamepadEvent::Button {
+                gamepad,
+                button,
+                pressed,
+            } => {
+                let Some(state) = self.gamepads.get_mut(&gamepad) else {
+                    return;
+                };
+                if pressed {
+                    state.buttons.insert(button);
+                } else {
+                    state.buttons.remove(&button);
+                }
+            }
+            GamepadEvent::Axis {
+                gamepad,
+                axis,
+                value,
+            } => {
+                let Some(state) = self.gamepads.get_mut(&gamepad) else {
+                    return;
+                };
+                state.axes.insert(axis, value.clamp(-1.0, 1.0));
+            }
+        }
+        self.update_gamepad_bindings();
+    }
+
+    // Recomputes the bindings and axes driven by gamepads from the state of every connected
+    // gamepad.
+    fn update_gamepad_bindings(&mut self) {
+        self.gamepad_axes.clear();
+        for state in self.gamepads.values() {
+            let sticks = [
+                (GamepadAxis::LeftStickX, GamepadAxis::LeftStickY),
+                (GamepadAxis::RightStickX, GamepadAxis::RightStickY),
+            ];
+            for (x_axis, y_axis) in sticks {
+                let x = state.axes.get(&x_axis).copied().unwrap_or(0.0);
+                let y = state.axes.get(&y_axis).copied().unwrap_or(0.0);
+                let (x, y) = self.dead_zone.apply(x, y);
+                for (axis, value) in [(x_axis, x), (y_axis, y)] {
+                    let current = self.gamepad_axes.entry(axis).or_insert(0.0);
+                    if value.abs() > current.abs() {
+                        *current = value;
+                    }
+                }
+            }
+        }
+
+        for button in GamepadButton::ALL {
+            let pressed = self
+                .gamepads
+                .values()
+                .any(|state| state.buttons.contains(&button));
+            self.set_binding(Binding::GamepadButton(button), pressed);
+        }
+        for axis in GamepadAxis::ALL {
+            let value = self.gamepad_axes.get(&axis).copied().unwrap_or(0.0);
+            for positive in [false, true] {
+                let pressed = if positive {
+                    value >= STICK_PRESS_THRESHOLD
+                } else {
+                    value <= -STICK_PRESS_THRESHOLD
+                };
+                self.set_binding(Binding::GamepadStick { axis, positive }, pressed);
+            }
+        }
+    }
+
+    // Should be set from `Window::scale_factor` at startup, it's kept up to date by
+    // `handle_event` afterwards.
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.pointer.set_scale_factor(scale_factor);
+    }
+
+    pub fn pointer(&self) -> &Pointer {
+        &self.pointer
+    }
+
+    pub fn connected_gamepads(&self) -> impl Iterator<Item = GamepadId> + '_ {
+        self.gamepads.keys().copied()
+    }
+
+    // The gamepads connected or disconnected since the previous tick.
+    pub fn gamepad_connections(&self) -> &[GamepadEvent] {
+        &self.connections
+    }
+
+    fn set_binding(&mut self, binding: Binding, pressed: bool) {
+        if pressed {
+            if self.down.insert(binding) {
+                self.pressed_since_tick.insert(binding);
+                self.last_pressed = Some(binding);
+            }
+        } else if self.down.remove(&binding) {
+            self.released_since_tick.insert(binding);
+        }
+    }
+
+    pub fn is_binding_down(&self, binding: Binding) -> bool {
+        self.down.contains(&binding)
+    }
+
+    fn state(&self, action: A) -> ActionState {
+        self.actions.get(&action).copied().unwrap_or_default()
+    }
+
+    // Whether any of the inputs bound to `action` is down.
+    pub fn is_down(&self, action: A) -> bool {
+        self.state(action).down
+    }
+
+    // Whether `action` was pressed since the previous tick.
+    pub fn just_pressed(&self, action: A) -> bool {
+        self.state(action).just_pressed
+    }
+
+    // Whether `action` was released since the previous tick.
+    pub fn just_released(&self, action: A) -> bool {
+        self.state(action).just_released
+    }
+
+    // Seconds `action` has been held down for, or 0.0 if it isn't down.
+    pub fn held_duration(&self, action: A) -> f32 {
+        self.state(action).held
+    }
+
+    // Whether `action` was pressed within the last `window` seconds, even if it has been
+    // released since. Buffering presses like this lets a jump pressed a few frames before
+    // landing still register.
+    pub fn pressed_within(&self, action: A, window: f32) -> bool {
+        self.state(action)
+            .since_pressed
+            .is_some_and(|t| t <= window)
+    }
+
+    // Forgets the last press of `action`, so a buffered press only triggers once.
+    pub fn consume(&mut self, action: A) {
+        if let Some(state) = self.actions.get_mut(&action) {
+            state.just_pressed = false;
+            state.since_pressed = None;
+        }
+    }
+
+    // The value of `axis` sampled by the last tick, between -1.0 and 1.0.
+    pub fn axis(&self, axis: A) -> f32 {
+        self.axes.get(&axis).copied().unwrap_or(0.0)
+    }
+
+    // When several bindings are active, the one furthest from the centre wins.
+    fn raw_axis(&self, axis: A) -> f32 {
+        self.map
+            .axis_bindings(axis)
+            .iter()
+            .map(|binding| match binding {
+                AxisBinding::Digital { negative, positive } => {
+                    let mut value = 0.0;
+                    if self.is_binding_down(*negative) {
+                        value -= 1.0;
+                    }
+                    if self.is_binding_down(*positive) {
+                        value += 1.0;
+                    }
+                    value
+                }
+                AxisBinding::Analog(axis) => self.gamepad_axes.get(axis).copied().unwrap_or(0.0),
+            })
+            .fold(0.0, |a: f32, b: f32| if b.abs() > a.abs() { b } else { a })
+    }
+
+    // Returns the last input pressed since the previous call, which lets a controls menu wait
+    // for the player to press the input to bind to an action.
+    pub fn take_last_pressed(&mut self) -> Option<Binding> {
+        self.last_pressed.take()
+    }
+}
+
+// A period of time during which something is still allowed, eg. jumping shortly after walking
+// off a platform ("coyote time"). Keep it refreshed while the condition holds, and tick it
+// otherwise.
+#[derive(Copy, Clone, Debug)]
+pub struct GracePeriod {
+    duration: f32,
+    remaining: f32,
+}
+
+impl GracePeriod {
+    pub fn new(duration: f32) -> Self {
+        Self {
+            duration,
+            remaining: 0.0,
+        }
+    }
+
+    pub fn refresh(&mut self) {
+        self.remaining = self.duration;
+    }
+
+    pub fn tick(&mut self, dt: f32) {
+        self.remaining = (self.remaining - dt).max(0.0);
+    }
+
+    pub fn close(&mut self) {
+        self.remaining = 0.0;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.remaining > 0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gamepad::VirtualGamepads;
+    use replay::Recording;
+    use serde::{Deserialize, Serialize};
+    use winit::dpi::PhysicalPosition;
+    use winit::event::{MouseScrollDelta, VirtualKeyCode};
+
+    #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+    enum TestAction {
+        Jump,
+        MoveX,
+    }
+
+    const DT: f32 = 0.1;
+    const JUMP_KEY: Binding = Binding::Key(VirtualKeyCode::Space);
+    const JUMP_BUTTON: Binding = Binding::GamepadButton(GamepadButton::South);
+
+    fn input() -> Input<TestAction> {
+        Input::new(
+            InputMap::new()
+                .with_action(TestAction::Jump, &[JUMP_KEY, JUMP_BUTTON])
+                .with_axis(
+                    TestAction::MoveX,
+                    &[AxisBinding::Analog(GamepadAxis::LeftStickX)],
+                ),
+        )
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-5,
+            "{} is not {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn tap_between_ticks() {
+        let mut input = input();
+        input.set_binding(JUMP_KEY, true);
+        input.set_binding(JUMP_KEY, false);
+
+        input.begin_tick(DT);
+        assert!(input.is_down(TestAction::Jump));
+        assert!(input.just_pressed(TestAction::Jump));
+        assert!(!input.just_released(TestAction::Jump));
+
+        input.begin_tick(DT);
+        assert!(!input.is_down(TestAction::Jump));
+        assert!(!input.just_pressed(TestAction::Jump));
+        assert!(input.just_released(TestAction::Jump));
+
+        input.begin_tick(DT);
+        assert!(!input.just_released(TestAction::Jump));
+    }
+
+    #[test]
+    fn hold_and_release() {
+        let mut input = input();
+        input.set_binding(JUMP_KEY, true);
+        input.begin_tick(DT);
+        assert!(input.just_pressed(TestAction::Jump));
+        assert_eq!(input.held_duration(TestAction::Jump), 0.0);
+
+        input.begin_tick(DT);
+        input.begin_tick(DT);
+        assert!(input.is_down(TestAction::Jump));
+        assert!(!input.just_pressed(TestAction::Jump));
+        assert_close(input.held_duration(TestAction::Jump), 2.0 * DT);
+
+        input.set_binding(JUMP_KEY, false);
+        input.begin_tick(DT);
+        assert!(!input.is_down(TestAction::Jump));
+        assert!(input.just_released(TestAction::Jump));
+        assert_eq!(input.held_duration(TestAction::Jump), 0.0);
+
+        input.begin_tick(DT);
+        assert!(!input.just_released(TestAction::Jump));
+    }
+
+    #[test]
+    fn pressed_within_buffers_presses() {
+        let mut input = input();
+        assert!(!input.pressed_within(TestAction::Jump, 1.0));
+
+        input.set_binding(JUMP_KEY, true);
+        input.set_binding(JUMP_KEY, false);
+        input.begin_tick(DT);
+        input.begin_tick(DT);
+        input.begin_tick(DT);
+        assert!(input.pressed_within(TestAction::Jump, 0.25));
+        assert!(!input.pressed_within(TestAction::Jump, 0.15));
+
+        input.consume(TestAction::Jump);
+        assert!(!input.pressed_within(TestAction::Jump, 0.25));
+    }
+
+    #[test]
+    fn gamepad_buttons_trigger_actions() {
+        let mut input = input();
+        let mut gamepads = VirtualGamepads::new();
+        gamepads.connect(0);
+        gamepads.press(0, GamepadButton::South);
+        input.poll_gamepads(&mut gamepads);
+        input.begin_tick(DT);
+        assert!(input.just_pressed(TestAction::Jump));
+        assert_eq!(input.gamepad_connections(), &[GamepadEvent::Connected(0)]);
+
+        // Either gamepad keeps the action down.
+        gamepads.connect(1);
+        gamepads.press(1, GamepadButton::South);
+        gamepads.release(0, GamepadButton::South);
+        input.poll_gamepads(&mut gamepads);
+        input.begin_tick(DT);
+        assert!(input.is_down(TestAction::Jump));
+        assert!(!input.just_released(TestAction::Jump));
+
+        // Unplugging releases its buttons.
+        gamepads.disconnect(1);
+        input.poll_gamepads(&mut gamepads);
+        input.begin_tick(DT);
+        assert!(input.just_released(TestAction::Jump));
+        assert_eq!(input.connected_gamepads().collect::<Vec<_>>(), vec![0]);
+    }
+
+    #[test]
+    fn events_before_connecting_are_ignored() {
+        let mut input = input();
+        let mut gamepads = VirtualGamepads::new();
+        gamepads.press(0, GamepadButton::South);
+        gamepads.move_axis(0, GamepadAxis::LeftStickX, 1.0);
+        input.poll_gamepads(&mut gamepads);
+        input.begin_tick(DT);
+        assert!(!input.is_down(TestAction::Jump));
+        assert_eq!(input.axis(TestAction::MoveX), 0.0);
+        assert_eq!(input.connected_gamepads().count(), 0);
+
+        gamepads.connect(0);
+        gamepads.press(0, GamepadButton::South);
+        input.poll_gamepads(&mut gamepads);
+        input.begin_tick(DT);
+        assert!(input.just_pressed(TestAction::Jump));
+        assert_eq!(input.gamepad_connections(), &[GamepadEvent::Connected(0)]);
+    }
+
+    #[test]
+    fn connecting_twice_keeps_the_state() {
+        let mut input = input();
+        let mut gamepads = VirtualGamepads::new();
+        gamepads.connect(0);
+        gamepads.press(0, GamepadButton::South);
+        gamepads.move_axis(0, GamepadAxis::LeftStickX, 1.0);
+        gamepads.connect(0);
+        input.poll_gamepads(&mut gamepads);
+        input.begin_tick(DT);
+        assert!(input.is_down(TestAction::Jump));
+        assert_eq!(input.axis(TestAction::MoveX), 1.0);
+        assert_eq!(input.gamepad_connections(), &[GamepadEvent::Connected(0)]);
+
+        gamepads.connect(0);
+        input.poll_gamepads(&mut gamepads);
+        input.begin_tick(DT);
+        assert!(input.is_down(TestAction::Jump));
+        assert!(input.gamepad_connections().is_empty());
+    }
+
+    #[test]
+    fn dead_zone_scaling() {
+        let mut input = input();
+        let mut gamepads = VirtualGamepads::new();
+        gamepads.connect(0);
+        let mut axis_at = |value: f32| {
+            gamepads.move_axis(0, GamepadAxis::LeftStickX, value);
+            input.poll_gamepads(&mut gamepads);
+            input.begin_tick(DT);
+            input.axis(TestAction::MoveX)
+        };
+        assert_eq!(axis_at(0.1), 0.0);
+        assert_eq!(axis_at(0.15), 0.0);
+        assert_close(axis_at(0.55), 0.5);
+        assert_close(axis_at(-0.55), -0.5);
+        assert_close(axis_at(0.95), 1.0);
+        assert_close(axis_at(1.0), 1.0);
+
+        // The dead zone is radial, so the other axis of the stick counts.
+        assert_eq!(DeadZone::default().apply(0.1, 0.1), (0.0, 0.0));
+        let (x, y) = DeadZone::default().apply(0.3, 0.4);
+        assert_close(x, 0.6 * 0.4375);
+        assert_close(y, 0.8 * 0.4375);
+    }
+
+    #[test]
+    fn losing_focus_releases_keys_only() {
+        let mut input = input();
+        let mut gamepads = VirtualGamepads::new();
+        gamepads.connect(0);
+        gamepads.press(0, GamepadButton::South);
+        input.poll_gamepads(&mut gamepads);
+        input.set_binding(JUMP_KEY, true);
+        input.begin_tick(DT);
+
+        input.handle_event(&WindowEvent::Focused(false));
+        assert!(!input.is_binding_down(JUMP_KEY));
+        assert!(input.is_binding_down(JUMP_BUTTON));
+        input.begin_tick(DT);
+        assert!(input.is_down(TestAction::Jump));
+
+        // The gamepad is still tracked, so releasing it is seen.
+        gamepads.release(0, GamepadButton::South);
+        input.poll_gamepads(&mut gamepads);
+        input.begin_tick(DT);
+        assert!(input.just_released(TestAction::Jump));
+    }
+
+    #[test]
+    fn replay_restores_pointer() {
+        let mut live = input();
+        live.pointer.move_to(PhysicalPosition::new(12.0, 34.0));
+        live.pointer.scroll(MouseScrollDelta::LineDelta(0.0, 2.0));
+        live.set_mouse_button(MouseButton::Right, true);
+        live.set_mouse_button(MouseButton::Left, true);
+        live.begin_tick(DT);
+
+        let mut recording = Recording::new("test", 0, DT);
+        recording.record(live.last_tick());
+        let recording = Recording::from_bytes(&recording.to_bytes().unwrap()).unwrap();
+
+        let mut replayed = input();
+        replayed.replay_tick(recording.ticks().next().unwrap(), DT);
+        assert_eq!(replayed.last_tick(), live.last_tick());
+        assert_eq!(
+            replayed.pointer().position(),
+            Some(PhysicalPosition::new(12.0, 34.0))
+        );
+        assert_eq!(replayed.pointer().wheel(), cgmath::Vector2::new(0.0, 2.0));
+        assert!(replayed.is_binding_down(Binding::Mouse(MouseButton::Left)));
+        assert!(replayed.is_binding_down(Binding::Mouse(MouseButton::Right)));
+        assert!(!replayed.is_binding_down(Binding::Mouse(MouseButton::Middle)));
+    }
+}
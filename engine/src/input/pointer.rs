@@ -0,0 +1,279 @@
+use crate::rendering::camera::Camera2d;
+use crate::rendering::viewport::ScreenRect;
+use cgmath::Vector2;
+use std::collections::BTreeMap;
+use winit::dpi::{LogicalPosition, PhysicalPosition};
+use winit::event::{MouseScrollDelta, Touch, TouchPhase};
+
+// Pixel scroll deltas (touchpads) are converted to lines using this many pixels per line.
+const PIXELS_PER_LINE: f32 = 20.0;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TouchPoint {
+    pub id: u64,
+    // Position in window pixels.
+    pub position: PhysicalPosition<f64>,
+    // Where the touch started, eg. to detect swipes.
+    pub start: PhysicalPosition<f64>,
+}
+
+// Mouse and touch state. Positions are in physical window pixels, with the origin at the top
+// left corner, same as winit; use `world_position` to find what the pointer is over.
+//
+// The wheel and touch changes are sampled by `Input::begin_tick`, like actions.
+#[derive(Clone, Debug)]
+pub struct Pointer {
+    position: Option<PhysicalPosition<f64>>,
+    scale_factor: f64,
+    wheel_since_tick: Vector2<f32>,
+    wheel: Vector2<f32>,
+    touches: BTreeMap<u64, TouchPoint>,
+    // The first finger down, until it's lifted. Ids say nothing about the order of touches.
+    primary: Option<u64>,
+    started_since_tick: Vec<TouchPoint>,
+    ended_since_tick: Vec<TouchPoint>,
+    started: Vec<TouchPoint>,
+    ended: Vec<TouchPoint>,
+}
+
+impl Default for Pointer {
+    fn default() -> Self {
+        Self {
+            position: None,
+            scale_factor: 1.0,
+            wheel_since_tick: Vector2::new(0.0, 0.0),
+            wheel: Vector2::new(0.0, 0.0),
+            touches: BTreeMap::new(),
+            primary: None,
+            started_since_tick: vec![],
+            ended_since_tick: vec![],
+            started: vec![],
+            ended: vec![],
+        }
+    }
+}
+
+impl Pointer {
+    pub(super) fn begin_tick(&mut self) {
+        self.wheel = std::mem::replace(&mut self.wheel_since_tick, Vector2::new(0.0, 0.0));
+        self.started = std::mem::take(&mut self.started_since_tick);
+        self.ended = std::mem::take(&mut self.ended_since_tick);
+    }
+
+    pub(super) fn move_to(&mut self, position: PhysicalPosition<f64>) {
+        self.position = Some(position);
+    }
+
+    pub(super) fn leave(&mut self) {
+        self.position = None;
+    }
+
+    // Restores the state recorded for a tick.
+    pub(super) fn replay(&mut self, position: Option<PhysicalPosition<f64>>, wheel: [f32; 2]) {
+        self.position = position;
+        self.wheel = wheel.into();
+    }
+
+    pub(super) fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor;
+    }
+
+    pub(super) fn scroll(&mut self, delta: MouseScrollDelta) {
+        self.wheel_since_tick += match delta {
+            MouseScrollDelta::LineDelta(x, y) => Vector2::new(x, y),
+            MouseScrollDelta::PixelDelta(delta) => Vector2::new(
+                delta.x as f32 / PIXELS_PER_LINE,
+                delta.y as f32 / PIXELS_PER_LINE,
+            ),
+        };
+    }
+
+    // Returns whether the touch is the primary one, which also drives the pointer position. The
+    // primary touch is the one that started while no other was down, so a second finger never
+    // takes over, even once the first is lifted.
+    pub(super) fn touch(&mut self, touch: &Touch) -> bool {
+        if touch.phase == TouchPhase::Started && self.touches.is_empty() {
+            self.primary = Some(touch.id);
+        }
+        let primary = self.primary == Some(touch.id);
+        match touch.phase {
+            TouchPhase::Started => {
+                let point = TouchPoint {
+                    id: touch.id,
+                    position: touch.location,
+                    start: touch.location,
+                };
+                self.touches.insert(touch.id, point);
+                self.started_since_tick.push(point);
+            }
+            TouchPhase::Moved => {
+                if let Some(point) = self.touches.get_mut(&touch.id) {
+                    point.position = touch.location;
+                }
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                if let Some(mut point) = self.touches.remove(&touch.id) {
+                    point.position = touch.location;
+                    self.ended_since_tick.push(point);
+                }
+                if primary {
+                    self.primary = None;
+                }
+            }
+        }
+        if primary {
+            self.position = Some(touch.location);
+        }
+        primary
+    }
+
+    // The position of the mouse or primary touch, or `None` when outside the window.
+    pub fn position(&self) -> Option<PhysicalPosition<f64>> {
+        self.position
+    }
+
+    // The position in logical pixels, which don't depend on the display's scale factor.
+    pub fn logical_position(&self) -> Option<LogicalPosition<f64>> {
+        self.position.map(|p| p.to_logical(self.scale_factor))
+    }
+
+    // The position in world coordinates for `camera`, rendering to `viewport`.
+    pub fn world_position(&self, camera: &Camera2d, viewport: ScreenRect) -> Option<Vector2<f32>> {
+        self.position
+            .map(|position| camera.screen_to_world(position, viewport))
+    }
+
+    // Lines scrolled since the previous tick. Positive `y` scrolls up.
+    pub fn wheel(&self) -> Vector2<f32> {
+        self.wheel
+    }
+
+    pub fn touches(&self) -> impl Iterator<Item = &TouchPoint> + '_ {
+        self.touches.values()
+    }
+
+    // Touches started since the previous tick.
+    pub fn touches_started(&self) -> &[TouchPoint] {
+        &self.started
+    }
+
+    // Touches lifted since the previous tick.
+    pub fn touches_ended(&self) -> &[TouchPoint] {
+        &self.ended
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use winit::event::DeviceId;
+
+    fn touch(pointer: &mut Pointer, id: u64, phase: TouchPhase, x: f64) -> bool {
+        pointer.touch(&Touch {
+            // Only used to tell devices apart, which the pointer doesn't do.
+            device_id: unsafe { DeviceId::dummy() },
+            phase,
+            location: PhysicalPosition::new(x, 0.0),
+            force: None,
+            id,
+        })
+    }
+
+    fn ids(touches: &[TouchPoint]) -> Vec<u64> {
+        touches.iter().map(|touch| touch.id).collect()
+    }
+
+    #[test]
+    fn first_touch_is_primary() {
+        let mut pointer = Pointer::default();
+        assert!(touch(&mut pointer, 5, TouchPhase::Started, 50.0));
+        assert!(!touch(&mut pointer, 2, TouchPhase::Started, 20.0));
+        assert!(!touch(&mut pointer, 2, TouchPhase::Moved, 25.0));
+        assert_eq!(pointer.position(), Some(PhysicalPosition::new(50.0, 0.0)));
+        assert!(touch(&mut pointer, 5, TouchPhase::Moved, 55.0));
+        assert_eq!(pointer.position(), Some(PhysicalPosition::new(55.0, 0.0)));
+
+        // The second finger doesn't take over once the first is lifted.
+        assert!(touch(&mut pointer, 5, TouchPhase::Ended, 56.0));
+        assert!(!touch(&mut pointer, 2, TouchPhase::Moved, 30.0));
+        assert!(!touch(&mut pointer, 7, TouchPhase::Started, 70.0));
+        assert_eq!(pointer.position(), Some(PhysicalPosition::new(56.0, 0.0)));
+        assert!(!touch(&mut pointer, 2, TouchPhase::Cancelled, 30.0));
+        assert!(!touch(&mut pointer, 7, TouchPhase::Ended, 70.0));
+
+        assert!(touch(&mut pointer, 9, TouchPhase::Started, 90.0));
+        assert_eq!(pointer.position(), Some(PhysicalPosition::new(90.0, 0.0)));
+    }
+
+    #[test]
+    fn touches_per_tick() {
+        let mut pointer = Pointer::default();
+        touch(&mut pointer, 3, TouchPhase::Started, 30.0);
+        touch(&mut pointer, 1, TouchPhase::Started, 10.0);
+        assert!(pointer.touches_started().is_empty());
+        pointer.begin_tick();
+        assert_eq!(ids(pointer.touches_started()), [3, 1]);
+        assert!(pointer.touches_ended().is_empty());
+        // Sorted by id.
+        assert_eq!(
+            pointer.touches().map(|touch| touch.id).collect::<Vec<_>>(),
+            [1, 3]
+        );
+
+        touch(&mut pointer, 3, TouchPhase::Moved, 35.0);
+        touch(&mut pointer, 3, TouchPhase::Ended, 40.0);
+        pointer.begin_tick();
+        assert!(pointer.touches_started().is_empty());
+        let ended = pointer.touches_ended();
+        assert_eq!(ids(ended), [3]);
+        assert_eq!(ended[0].start, PhysicalPosition::new(30.0, 0.0));
+        assert_eq!(ended[0].position, PhysicalPosition::new(40.0, 0.0));
+        assert_eq!(pointer.touches().count(), 1);
+
+        // Started and lifted within a tick.
+        touch(&mut pointer, 4, TouchPhase::Started, 40.0);
+        touch(&mut pointer, 4, TouchPhase::Ended, 40.0);
+        pointer.begin_tick();
+        assert_eq!(ids(pointer.touches_started()), [4]);
+        assert_eq!(ids(pointer.touches_ended()), [4]);
+        pointer.begin_tick();
+        assert!(pointer.touches_started().is_empty());
+        assert!(pointer.touches_ended().is_empty());
+    }
+
+    #[test]
+    fn world_position() {
+        let mut pointer = Pointer::default();
+        pointer.set_scale_factor(2.0);
+        assert_eq!(
+            pointer.world_position(&Camera2d::new(1.0, 1.0), ScreenRect::default()),
+            None
+        );
+
+        // A 320x180 camera drawn at 2x in a window with 40 pixel bars on the left and right.
+        let mut camera = Camera2d::new(320.0, 180.0);
+        camera.position = Vector2::new(100.0, 50.0);
+        let viewport = ScreenRect::new(40, 0, 640, 360);
+        pointer.move_to(PhysicalPosition::new(40.0, 360.0));
+        assert_eq!(
+            pointer.logical_position(),
+            Some(LogicalPosition::new(20.0, 180.0))
+        );
+        assert_eq!(
+            pointer.world_position(&camera, viewport),
+            Some(Vector2::new(100.0, 50.0))
+        );
+        pointer.move_to(PhysicalPosition::new(680.0, 0.0));
+        assert_eq!(
+            pointer.world_position(&camera, viewport),
+            Some(Vector2::new(420.0, 230.0))
+        );
+        pointer.move_to(PhysicalPosition::new(360.0, 90.0));
+        let world = pointer.world_position(&camera, viewport).unwrap();
+        assert_eq!(world, Vector2::new(260.0, 185.0));
+        assert_eq!(
+            camera.world_to_screen(world, viewport),
+            PhysicalPosition::new(360.0, 90.0)
+        );
+    }
+}
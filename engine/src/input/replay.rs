@@ -0,0 +1,228 @@
+use crate::input::bindings::Action;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use winit::dpi::PhysicalPosition;
+use winit::event::MouseButton;
+
+// Bumped whenever the file layout changes, so old recordings fail to load with a clear error.
+const FORMAT_VERSION: u32 = 2;
+
+// The actions, axes and pointer state sampled for a single tick. Feeding the same ticks back
+// through `Input::replay_tick` reproduces a session exactly, as long as the game doesn't read
+// touches, which aren't recorded.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct TickInput<A: Action> {
+    pub down: Vec<A>,
+    pub pressed: Vec<A>,
+    pub released: Vec<A>,
+    pub axes: Vec<(A, f32)>,
+    // See `Pointer::position`.
+    pub pointer: Option<PhysicalPosition<f64>>,
+    // Lines scrolled, see `Pointer::wheel`.
+    pub wheel: [f32; 2],
+    // Mouse buttons down, whether they're bound to an action or not.
+    pub mouse_buttons: Vec<MouseButton>,
+}
+
+impl<A: Action> Default for TickInput<A> {
+    fn default() -> Self {
+        Self {
+            down: vec![],
+            pressed: vec![],
+            released: vec![],
+            axes: vec![],
+            pointer: None,
+            wheel: [0.0; 2],
+            mouse_buttons: vec![],
+        }
+    }
+}
+
+// A recorded session: the input of every tick, plus what's needed to start the game in the same
+// state. Runs of identical ticks are stored once, which keeps files small since the input rarely
+// changes from one tick to the next.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Recording<A: Action> {
+    format: u32,
+    // Version of the game that made the recording. Replaying with a different build may diverge.
+    pub build_version: String,
+    // Seed of the game's `Rng`.
+    pub seed: u64,
+    // Seconds per tick.
+    pub tick_duration: f32,
+    ticks: Vec<(u32, TickInput<A>)>,
+}
+
+impl<A: Action> Recording<A> {
+    pub fn new(build_version: &str, seed: u64, tick_duration: f32) -> Self {
+        Self {
+            format: FORMAT_VERSION,
+            build_version: build_version.to_string(),
+            seed,
+            tick_duration,
+            ticks: vec![],
+        }
+    }
+
+    // Appends the input of the next tick, usually `Input::last_tick`.
+    pub fn record(&mut self, tick: &TickInput<A>) {
+        match self.ticks.last_mut() {
+            Some((count, last)) if last == tick => *count += 1,
+            _ => self.ticks.push((1, tick.clone())),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.ticks.iter().map(|(count, _)| *count as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ticks.is_empty()
+    }
+
+    pub fn ticks(&self) -> impl Iterator<Item = &TickInput<A>> + '_ {
+        self.ticks
+            .iter()
+            .flat_map(|(count, tick)| std::iter::repeat_n(tick, *count as usize))
+    }
+
+    pub fn to_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(bincode::serialize(self)?)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        // The format comes first, so it's checked before trying to decode the rest.
+        let format: u32 = bincode::deserialize(bytes)?;
+        if format != FORMAT_VERSION {
+            anyhow::bail!(
+                "Unsupported recording format {} (expected {})",
+                format,
+                FORMAT_VERSION
+            );
+        }
+        Ok(bincode::deserialize(bytes)?)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let bytes = fs::read(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+        Self::from_bytes(&bytes)
+            .map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", path.display(), e))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        fs::write(path, self.to_bytes()?)
+            .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))
+    }
+}
+
+// Plays a `Recording` back one tick at a time.
+pub struct Replay<A: Action> {
+    recording: Recording<A>,
+    run: usize,
+    repeated: u32,
+}
+
+impl<A: Action> Replay<A> {
+    pub fn new(recording: Recording<A>) -> Self {
+        Self {
+            recording,
+            run: 0,
+            repeated: 0,
+        }
+    }
+
+    pub fn recording(&self) -> &Recording<A> {
+        &self.recording
+    }
+
+    // The input of the next tick, or `None` once the recording is over.
+    pub fn next_tick(&mut self) -> Option<&TickInput<A>> {
+        let (count, _) = self.recording.ticks.get(self.run)?;
+        if self.repeated == *count {
+            self.run += 1;
+            self.repeated = 0;
+        }
+        let (_, tick) = self.recording.ticks.get(self.run)?;
+        self.repeated += 1;
+        Some(tick)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        match self.recording.ticks.get(self.run) {
+            Some((count, _)) => {
+                self.run == self.recording.ticks.len() - 1 && self.repeated == *count
+            }
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+    enum TestAction {
+        Jump,
+    }
+
+    fn tick(jump: bool) -> TickInput<TestAction> {
+        TickInput {
+            down: if jump { vec![TestAction::Jump] } else { vec![] },
+            ..TickInput::default()
+        }
+    }
+
+    #[test]
+    fn runs_of_ticks() {
+        let (a, b) = (tick(false), tick(true));
+        let mut recording = Recording::new("test", 7, 0.1);
+        assert!(recording.is_empty());
+        for tick in [&a, &a, &b] {
+            recording.record(tick);
+        }
+        assert_eq!(recording.len(), 3);
+        assert_eq!(recording.ticks.len(), 2);
+        assert_eq!(recording.ticks().collect::<Vec<_>>(), [&a, &a, &b]);
+
+        let recording = Recording::from_bytes(&recording.to_bytes().unwrap()).unwrap();
+        assert_eq!(
+            (recording.build_version.as_str(), recording.seed),
+            ("test", 7)
+        );
+        let mut replay = Replay::new(recording);
+        assert!(!replay.is_finished());
+        assert_eq!(replay.next_tick(), Some(&a));
+        assert_eq!(replay.next_tick(), Some(&a));
+        assert!(!replay.is_finished());
+        assert_eq!(replay.next_tick(), Some(&b));
+        assert!(replay.is_finished());
+        assert_eq!(replay.next_tick(), None);
+        assert!(replay.is_finished());
+
+        assert!(Replay::new(Recording::<TestAction>::new("test", 0, 0.1)).is_finished());
+    }
+
+    #[test]
+    fn other_formats_are_rejected() {
+        // A recording of the previous layout, which didn't have the pointer fields.
+        let mut bytes = bincode::serialize(&1u32).unwrap();
+        bytes.extend(bincode::serialize(&("test", 7u64, 0.1f32)).unwrap());
+        let error = Recording::<TestAction>::from_bytes(&bytes).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Unsupported recording format 1 (expected {})",
+                FORMAT_VERSION
+            )
+        );
+
+        assert!(Recording::<TestAction>::from_bytes(&[2, 0]).is_err());
+    }
+}
@@ -1,11 +1,32 @@
+pub mod animation;
+pub mod app;
+pub mod assets;
+pub mod audio;
+pub mod clock;
+pub mod collision;
+pub mod headless;
 pub mod input;
+pub mod random;
 pub mod rendering;
+pub mod scene;
+pub mod tween;
+pub mod window;
 
 pub mod prelude {
     pub use crate::rendering::{
+        blend::BlendMode,
+        camera::Camera2d,
+        color::Color,
+        lighting::{
+            Light,
+            LightKind
+        },
+        viewport::ScreenRect,
         Canvas,
         Graphics,
         shapes::{
+            Insets,
+            NineSlice,
             Sprite,
             Square
         }
@@ -0,0 +1,65 @@
+use wgpu::{BlendComponent, BlendFactor, BlendOperation, BlendState};
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum BlendMode {
+    /// Regular alpha blending for colours with straight (non-premultiplied) alpha.
+    #[default]
+    Alpha,
+    /// Like `Alpha`, but expects the colour to already be multiplied by its alpha.
+    Premultiplied,
+    /// Adds the colour on top of what is already drawn. Useful for glows.
+    Additive,
+    /// Multiplies the destination by the colour. Useful for shadows and tints.
+    Multiply,
+    /// Inverse of `Multiply`, lightens the destination.
+    Screen,
+    /// Overwrites the destination, ignoring alpha.
+    Replace,
+}
+
+impl BlendMode {
+    pub const ALL: [BlendMode; 6] = [
+        BlendMode::Alpha,
+        BlendMode::Premultiplied,
+        BlendMode::Additive,
+        BlendMode::Multiply,
+        BlendMode::Screen,
+        BlendMode::Replace,
+    ];
+
+    // The blend states below expect a premultiplied colour. For every mode but `Premultiplied`
+    // and `Replace` the shaders take care of multiplying the colour by its alpha.
+    pub(crate) fn fragment_entry_point(&self) -> &'static str {
+        match self {
+            BlendMode::Premultiplied | BlendMode::Replace => "fs_main",
+            _ => "fs_premultiply",
+        }
+    }
+
+    pub fn blend_state(&self) -> BlendState {
+        match self {
+            BlendMode::Alpha | BlendMode::Premultiplied => BlendState::PREMULTIPLIED_ALPHA_BLENDING,
+            BlendMode::Additive => BlendState {
+                color: component(BlendFactor::One, BlendFactor::One),
+                alpha: component(BlendFactor::Zero, BlendFactor::One),
+            },
+            BlendMode::Multiply => BlendState {
+                color: component(BlendFactor::Dst, BlendFactor::OneMinusSrcAlpha),
+                alpha: component(BlendFactor::Zero, BlendFactor::One),
+            },
+            BlendMode::Screen => BlendState {
+                color: component(BlendFactor::One, BlendFactor::OneMinusSrc),
+                alpha: component(BlendFactor::Zero, BlendFactor::One),
+            },
+            BlendMode::Replace => BlendState::REPLACE,
+        }
+    }
+}
+
+fn component(src_factor: BlendFactor, dst_factor: BlendFactor) -> BlendComponent {
+    BlendComponent {
+        src_factor,
+        dst_factor,
+        operation: BlendOperation::Add,
+    }
+}
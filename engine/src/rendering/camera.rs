@@ -1,6 +1,14 @@
-use cgmath::Vector2;
-use winit::dpi::PhysicalSize;
+use crate::rendering::viewport::ScreenRect;
 use cgmath::SquareMatrix;
+use cgmath::Vector2;
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+
+// Maximum number of cameras that can be used in a single frame, eg. one per viewport.
+pub const MAX_CAMERAS: usize = 16;
+
+// Dynamic uniform offsets must be aligned to `Limits::min_uniform_buffer_offset_alignment`,
+// which is 256 bytes by default.
+const CAMERA_SLOT_SIZE: wgpu::BufferAddress = 256;
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
@@ -23,6 +31,7 @@ impl Default for CameraUniform {
     }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Camera2d {
     pub position: Vector2<f32>,
     pub size: PhysicalSize<f32>,
@@ -45,4 +54,144 @@ impl Camera2d {
         let proj = cgmath::ortho(0.0, self.size.width, 0.0, self.size.height, 0.1, 100.0);
         proj * view
     }
+
+    // Converts a position in window pixels to world coordinates, for a camera rendering to
+    // `viewport`. Window coordinates grow downwards while the world is y-up. Use
+    // `Graphics::game_rect` as the viewport when the camera covers the whole game.
+    pub fn screen_to_world(
+        &self,
+        position: PhysicalPosition<f64>,
+        viewport: ScreenRect,
+    ) -> Vector2<f32> {
+        let u = (position.x as f32 - viewport.x as f32) / viewport.width.max(1) as f32;
+        let v = (position.y as f32 - viewport.y as f32) / viewport.height.max(1) as f32;
+        Vector2::new(
+            self.position.x + u * self.size.width,
+            self.position.y + (1.0 - v) * self.size.height,
+        )
+    }
+
+    // The inverse of `screen_to_world`.
+    pub fn world_to_screen(
+        &self,
+        position: Vector2<f32>,
+        viewport: ScreenRect,
+    ) -> PhysicalPosition<f64> {
+        let u = (position.x - self.position.x) / self.size.width;
+        let v = 1.0 - (position.y - self.position.y) / self.size.height;
+        PhysicalPosition::new(
+            (viewport.x as f32 + u * viewport.width as f32) as f64,
+            (viewport.y as f32 + v * viewport.height as f32) as f64,
+        )
+    }
+}
+
+// Uniform buffer holding the cameras for a frame, shared by all pipelines. Each camera lives in
+// its own slot, selected with a dynamic offset when setting the bind group.
+pub struct CameraBuffer {
+    pub buffer: wgpu::Buffer,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+}
+
+impl CameraBuffer {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Camera Buffer"),
+            size: CAMERA_SLOT_SIZE * MAX_CAMERAS as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: wgpu::BufferSize::new(
+                        std::mem::size_of::<CameraUniform>() as u64
+                    ),
+                },
+                count: None,
+            }],
+            label: Some("camera_bind_group_layout"),
+        });
+
+        Self {
+            buffer,
+            bind_group_layout,
+        }
+    }
+
+    pub fn create_bind_group(&self, device: &wgpu::Device) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &self.buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(std::mem::size_of::<CameraUniform>() as u64),
+                }),
+            }],
+            label: Some("camera_bind_group"),
+        })
+    }
+
+    pub fn write(&self, queue: &wgpu::Queue, cameras: &[Camera2d]) {
+        for (index, camera) in cameras.iter().take(MAX_CAMERAS).enumerate() {
+            let mut camera_uniform = CameraUniform::default();
+            camera_uniform.update_view_proj(camera);
+            queue.write_buffer(
+                &self.buffer,
+                Self::offset(index) as wgpu::BufferAddress,
+                bytemuck::cast_slice(&[camera_uniform]),
+            );
+        }
+    }
+
+    // The dynamic offset selecting the camera at `index`.
+    pub fn offset(index: usize) -> u32 {
+        (index as wgpu::BufferAddress * CAMERA_SLOT_SIZE) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn screen_to_world_and_back() {
+        let mut camera = Camera2d::new(100.0, 50.0);
+        camera.position = Vector2::new(-30.0, 5.0);
+        let viewport = ScreenRect::new(10, 20, 200, 100);
+
+        // The top left of the viewport is the top left of the camera, as the world is y-up.
+        assert_eq!(
+            camera.screen_to_world(PhysicalPosition::new(10.0, 20.0), viewport),
+            Vector2::new(-30.0, 55.0)
+        );
+        assert_eq!(
+            camera.screen_to_world(PhysicalPosition::new(210.0, 120.0), viewport),
+            Vector2::new(70.0, 5.0)
+        );
+        // Outside the viewport.
+        assert_eq!(
+            camera.screen_to_world(PhysicalPosition::new(0.0, 0.0), viewport),
+            Vector2::new(-35.0, 65.0)
+        );
+
+        for (x, y) in [(10.0, 20.0), (110.0, 70.0), (57.0, 31.0), (250.0, 0.0)] {
+            let screen = PhysicalPosition::new(x, y);
+            let world = camera.screen_to_world(screen, viewport);
+            let back = camera.world_to_screen(world, viewport);
+            assert!(
+                (back.x - x).abs() < 1e-3 && (back.y - y).abs() < 1e-3,
+                "{:?} is not {:?}",
+                back,
+                screen
+            );
+        }
+    }
 }
@@ -0,0 +1,310 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+// An RGBA colour, with components in the 0.0 - 1.0 range and in sRGB space, which is the space
+// colours are usually authored in. The engine takes care of converting to linear space when
+// rendering to an sRGB surface.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ParseColorError {
+    InvalidLength(usize),
+    InvalidDigit(char),
+}
+
+impl Display for ParseColorError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseColorError::InvalidLength(length) => write!(
+                f,
+                "expected 3, 4, 6 or 8 hex digits, found {} digits",
+                length
+            ),
+            ParseColorError::InvalidDigit(digit) => write!(f, "invalid hex digit '{}'", digit),
+        }
+    }
+}
+
+impl Error for ParseColorError {}
+
+impl Color {
+    pub const TRANSPARENT: Color = Color::new(0.0, 0.0, 0.0, 0.0);
+    pub const BLACK: Color = Color::rgb(0.0, 0.0, 0.0);
+    pub const WHITE: Color = Color::rgb(1.0, 1.0, 1.0);
+    pub const GRAY: Color = Color::rgb(0.5, 0.5, 0.5);
+    pub const RED: Color = Color::rgb(1.0, 0.0, 0.0);
+    pub const GREEN: Color = Color::rgb(0.0, 1.0, 0.0);
+    pub const BLUE: Color = Color::rgb(0.0, 0.0, 1.0);
+    pub const YELLOW: Color = Color::rgb(1.0, 1.0, 0.0);
+    pub const CYAN: Color = Color::rgb(0.0, 1.0, 1.0);
+    pub const MAGENTA: Color = Color::rgb(1.0, 0.0, 1.0);
+    pub const ORANGE: Color = Color::rgb(1.0, 0.5, 0.0);
+
+    pub const fn new(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self { r, g, b, a }
+    }
+
+    pub const fn rgb(r: f32, g: f32, b: f32) -> Self {
+        Self::new(r, g, b, 1.0)
+    }
+
+    pub fn from_rgba8(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self::new(
+            r as f32 / 255.0,
+            g as f32 / 255.0,
+            b as f32 / 255.0,
+            a as f32 / 255.0,
+        )
+    }
+
+    pub fn from_rgb8(r: u8, g: u8, b: u8) -> Self {
+        Self::from_rgba8(r, g, b, 255)
+    }
+
+    // Parses "#rgb", "#rgba", "#rrggbb" or "#rrggbbaa". The leading '#' is optional.
+    pub fn from_hex(hex: &str) -> Result<Self, ParseColorError> {
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        let digits = hex
+            .chars()
+            .map(|c| {
+                c.to_digit(16)
+                    .map(|d| d as u8)
+                    .ok_or(ParseColorError::InvalidDigit(c))
+            })
+            .collect::<Result<Vec<u8>, _>>()?;
+
+        let channels: Vec<u8> = match digits.len() {
+            3 | 4 => digits.iter().map(|d| d * 17).collect(),
+            6 | 8 => digits.chunks(2).map(|d| d[0] * 16 + d[1]).collect(),
+            length => return Err(ParseColorError::InvalidLength(length)),
+        };
+        let alpha = channels.get(3).copied().unwrap_or(255);
+        Ok(Self::from_rgba8(
+            channels[0],
+            channels[1],
+            channels[2],
+            alpha,
+        ))
+    }
+
+    // Hue is in degrees, saturation and value in the 0.0 - 1.0 range.
+    pub fn from_hsv(hue: f32, saturation: f32, value: f32) -> Self {
+        Self::from_hsva(hue, saturation, value, 1.0)
+    }
+
+    pub fn from_hsva(hue: f32, saturation: f32, value: f32, alpha: f32) -> Self {
+        let hue = hue.rem_euclid(360.0) / 60.0;
+        let chroma = value * saturation;
+        let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+        let (r, g, b) = match hue as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        let m = value - chroma;
+        Self::new(r + m, g + m, b + m, alpha)
+    }
+
+    // Returns (hue, saturation, value), with hue in degrees.
+    pub fn to_hsv(&self) -> (f32, f32, f32) {
+        let max = self.r.max(self.g).max(self.b);
+        let min = self.r.min(self.g).min(self.b);
+        let delta = max - min;
+        let hue = if delta == 0.0 {
+            0.0
+        } else if max == self.r {
+            60.0 * ((self.g - self.b) / delta).rem_euclid(6.0)
+        } else if max == self.g {
+            60.0 * ((self.b - self.r) / delta + 2.0)
+        } else {
+            60.0 * ((self.r - self.g) / delta + 4.0)
+        };
+        let saturation = if max == 0.0 { 0.0 } else { delta / max };
+        (hue, saturation, max)
+    }
+
+    pub fn with_alpha(self, a: f32) -> Self {
+        Self { a, ..self }
+    }
+
+    pub fn lerp(self, other: Color, t: f32) -> Self {
+        Self::new(
+            self.r + (other.r - self.r) * t,
+            self.g + (other.g - self.g) * t,
+            self.b + (other.b - self.b) * t,
+            self.a + (other.a - self.a) * t,
+        )
+    }
+
+    pub fn to_rgba8(&self) -> [u8; 4] {
+        let to_u8 = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        [to_u8(self.r), to_u8(self.g), to_u8(self.b), to_u8(self.a)]
+    }
+
+    // Converts the colour channels from sRGB to linear space. Alpha is always linear.
+    pub fn to_linear(&self) -> Self {
+        Self::new(
+            srgb_to_linear(self.r),
+            srgb_to_linear(self.g),
+            srgb_to_linear(self.b),
+            self.a,
+        )
+    }
+
+    pub fn from_linear(linear: Color) -> Self {
+        Self::new(
+            linear_to_srgb(linear.r),
+            linear_to_srgb(linear.g),
+            linear_to_srgb(linear.b),
+            linear.a,
+        )
+    }
+
+    // Values written to an sRGB surface are expected in linear space and are encoded by the
+    // GPU, while other formats take the sRGB values as they are.
+    pub fn for_format(&self, format: wgpu::TextureFormat) -> [f32; 4] {
+        let color = if format.is_srgb() {
+            self.to_linear()
+        } else {
+            *self
+        };
+        color.into()
+    }
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+impl FromStr for Color {
+    type Err = ParseColorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Color::from_hex(s)
+    }
+}
+
+impl From<(u8, u8, u8)> for Color {
+    fn from((r, g, b): (u8, u8, u8)) -> Self {
+        Color::from_rgb8(r, g, b)
+    }
+}
+
+impl From<(u8, u8, u8, u8)> for Color {
+    fn from((r, g, b, a): (u8, u8, u8, u8)) -> Self {
+        Color::from_rgba8(r, g, b, a)
+    }
+}
+
+impl From<Color> for [f32; 4] {
+    fn from(color: Color) -> Self {
+        [color.r, color.g, color.b, color.a]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Color, b: Color) {
+        let close = |x: f32, y: f32| (x - y).abs() < 1e-4;
+        assert!(
+            close(a.r, b.r) && close(a.g, b.g) && close(a.b, b.b) && close(a.a, b.a),
+            "{:?} != {:?}",
+            a,
+            b
+        );
+    }
+
+    #[test]
+    fn from_hex_digit_counts() {
+        assert_eq!(Color::from_hex("#f80"), Ok(Color::from_rgb8(255, 136, 0)));
+        assert_eq!(
+            Color::from_hex("f80c"),
+            Ok(Color::from_rgba8(255, 136, 0, 204))
+        );
+        assert_eq!(
+            Color::from_hex("#ff8800"),
+            Ok(Color::from_rgb8(255, 136, 0))
+        );
+        assert_eq!(
+            Color::from_hex("FF880080"),
+            Ok(Color::from_rgba8(255, 136, 0, 128))
+        );
+        assert_eq!("#000".parse(), Ok(Color::BLACK));
+    }
+
+    #[test]
+    fn from_hex_invalid() {
+        assert_eq!(Color::from_hex(""), Err(ParseColorError::InvalidLength(0)));
+        assert_eq!(
+            Color::from_hex("#12345"),
+            Err(ParseColorError::InvalidLength(5))
+        );
+        assert_eq!(
+            Color::from_hex("#12g"),
+            Err(ParseColorError::InvalidDigit('g'))
+        );
+        assert_eq!(
+            Color::from_hex("##123"),
+            Err(ParseColorError::InvalidDigit('#'))
+        );
+    }
+
+    #[test]
+    fn hsv_round_trip() {
+        for color in [
+            Color::RED,
+            Color::GREEN,
+            Color::BLUE,
+            Color::YELLOW,
+            Color::CYAN,
+            Color::MAGENTA,
+            Color::ORANGE,
+            Color::GRAY,
+            Color::rgb(0.2, 0.4, 0.9),
+        ] {
+            let (hue, saturation, value) = color.to_hsv();
+            assert_close(Color::from_hsv(hue, saturation, value), color);
+        }
+        assert_eq!(Color::RED.to_hsv(), (0.0, 1.0, 1.0));
+        assert_close(Color::from_hsv(480.0, 1.0, 1.0), Color::GREEN);
+    }
+
+    #[test]
+    fn srgb_linear_conversions() {
+        assert_close(Color::BLACK.to_linear(), Color::BLACK);
+        assert_close(Color::WHITE.to_linear(), Color::WHITE);
+        // Mid grey is about a fifth of the light in linear space, and alpha is untouched.
+        let grey = Color::new(0.5, 0.5, 0.5, 0.5).to_linear();
+        assert!((grey.r - 0.214).abs() < 1e-3);
+        assert_eq!(grey.a, 0.5);
+        // The linear segment near black.
+        assert!((Color::rgb(0.04, 0.0, 0.0).to_linear().r - 0.04 / 12.92).abs() < 1e-6);
+        for color in [Color::GRAY, Color::ORANGE, Color::rgb(0.01, 0.3, 0.99)] {
+            assert_close(Color::from_linear(color.to_linear()), color);
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use crate::rendering::color::Color;
+use cgmath::Vector2;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LightKind {
+    Point,
+    // Angles are in radians. `direction` is measured counter-clockwise from the x axis, and the
+    // light fades out between `inner_angle` and `outer_angle` away from it.
+    Spot {
+        direction: f32,
+        inner_angle: f32,
+        outer_angle: f32,
+    },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Light {
+    pub position: Vector2<f32>,
+    pub color: Color,
+    pub intensity: f32,
+    pub radius: f32,
+    // Exponent applied to the distance attenuation: 1.0 is linear, 2.0 is quadratic.
+    pub falloff: f32,
+    // Distance between the light and the scene. Only used when a normal map is set.
+    pub height: f32,
+    pub kind: LightKind,
+}
+
+impl Light {
+    pub fn point(position: Vector2<f32>, radius: f32, color: Color) -> Self {
+        Self {
+            position,
+            color,
+            intensity: 1.0,
+            radius,
+            falloff: 2.0,
+            height: radius / 4.0,
+            kind: LightKind::Point,
+        }
+    }
+
+    pub fn spot(
+        position: Vector2<f32>,
+        radius: f32,
+        color: Color,
+        direction: f32,
+        angle: f32,
+    ) -> Self {
+        Self {
+            kind: LightKind::Spot {
+                direction,
+                inner_angle: angle * 0.75,
+                outer_angle: angle,
+            },
+            ..Self::point(position, radius, color)
+        }
+    }
+
+    pub fn with_intensity(self, intensity: f32) -> Self {
+        Self { intensity, ..self }
+    }
+
+    pub fn with_falloff(self, falloff: f32) -> Self {
+        Self { falloff, ..self }
+    }
+
+    pub fn with_height(self, height: f32) -> Self {
+        Self { height, ..self }
+    }
+}
@@ -1,12 +1,24 @@
+pub mod blend;
 pub mod camera;
+pub mod color;
+pub mod lighting;
 pub mod pipelines;
 pub mod shapes;
 pub mod texture;
-
-use pipelines::{SpritePipeline, SquarePipeline};
-use shapes::{Sprite, Square};
+pub mod viewport;
+
+use crate::assets::Handle;
+use blend::BlendMode;
+use camera::{Camera2d, CameraBuffer, MAX_CAMERAS};
+use color::Color;
+use lighting::Light;
+use pipelines::{LightPipeline, LightView, SpritePipeline, SquarePipeline};
+use shapes::{NineSlice, Sprite, Square};
 use std::error::Error;
 use std::iter;
+use std::ops::Range;
+use texture::Texture;
+use viewport::ScreenRect;
 use wgpu::util::StagingBelt;
 use wgpu::{Adapter, Device, Instance, Queue, Surface, SurfaceConfiguration};
 use wgpu_glyph::{ab_glyph, GlyphBrush, GlyphBrushBuilder, Section, Text};
@@ -29,18 +41,23 @@ pub struct Graphics {
     pub device: Device,
     pub queue: Queue,
     pub configuration: SurfaceConfiguration,
+    pub camera: Camera2d,
+    pub camera_buffer: CameraBuffer,
     pub square_pipeline: SquarePipeline,
     pub sprite_pipeline: SpritePipeline,
-    pub glyph_brush: GlyphBrush<()>,
+    pub light_pipeline: LightPipeline,
+    // Text is only drawn once a font is set.
+    pub glyph_brush: Option<GlyphBrush<()>>,
+    // The font of the glyph brush, and the version it was built from.
+    font: Option<(Handle<ab_glyph::FontArc>, u32)>,
     pub staging_belt: StagingBelt,
+    pub clear_color: Color,
+    // The resolution the game is designed for, see `game_rect`.
+    pub virtual_resolution: Option<PhysicalSize<u32>>,
 }
 
 impl Graphics {
     pub async fn new(window: &Window) -> Result<Self, Box<dyn Error>> {
-        let inconsolata = ab_glyph::FontArc::try_from_slice(include_bytes!(
-            "../../../bomberjack/src/assets/Inconsolata-Regular.ttf"
-        ))?;
-
         let size = window.inner_size();
 
         // Create an instance of WebGPU.
@@ -74,7 +91,12 @@ impl Graphics {
 
         // Create the configuration.
         let swapchain_capabilities = surface.get_capabilities(&adapter);
-        let swapchain_format = swapchain_capabilities.formats[0];
+        let swapchain_format = swapchain_capabilities
+            .formats
+            .iter()
+            .copied()
+            .find(|format| format.is_srgb())
+            .unwrap_or(swapchain_capabilities.formats[0]);
         let configuration = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
             format: swapchain_format,
@@ -87,11 +109,17 @@ impl Graphics {
         surface.configure(&device, &configuration);
         let staging_belt = wgpu::util::StagingBelt::new(1024);
 
-        let glyph_brush =
-            GlyphBrushBuilder::using_font(inconsolata).build(&device, configuration.format);
-
-        let square_pipeline = SquarePipeline::new(&mut device, &mut queue, &configuration);
-        let sprite_pipeline = SpritePipeline::new(&mut device, &mut queue, &configuration);
+        let camera_buffer = CameraBuffer::new(&device);
+        let square_pipeline =
+            SquarePipeline::new(&mut device, &mut queue, &configuration, &camera_buffer);
+        let sprite_pipeline =
+            SpritePipeline::new(&mut device, &mut queue, &configuration, &camera_buffer);
+        let light_pipeline = LightPipeline::new(
+            &mut device,
+            &configuration,
+            &sprite_pipeline,
+            &camera_buffer,
+        );
 
         Ok(Graphics {
             size,
@@ -101,10 +129,16 @@ impl Graphics {
             device,
             queue,
             configuration,
+            camera: Camera2d::new(600.0, 650.0),
+            camera_buffer,
             square_pipeline,
             sprite_pipeline,
-            glyph_brush,
+            light_pipeline,
+            glyph_brush: None,
+            font: None,
             staging_belt,
+            clear_color: Color::rgb(0.1, 0.2, 0.3),
+            virtual_resolution: None,
         })
     }
 
@@ -114,6 +148,77 @@ impl Graphics {
             self.configuration.width = new_size.width;
             self.configuration.height = new_size.height;
             self.surface.configure(&self.device, &self.configuration);
+            self.light_pipeline
+                .resize(&self.device, new_size.width, new_size.height);
+        }
+    }
+
+    // Sets the texture sprites are drawn from.
+    pub fn set_texture(&mut self, texture: Handle<Texture>) {
+        self.sprite_pipeline.set_texture(&self.device, texture);
+    }
+
+    // Sets the normal map the lighting pass uses for sprites drawn from `texture`.
+    pub fn set_normal_map(
+        &mut self,
+        texture: &Handle<Texture>,
+        bytes: &[u8],
+    ) -> anyhow::Result<()> {
+        self.sprite_pipeline
+            .set_normal_map(&self.device, &self.queue, texture, bytes)
+    }
+
+    pub fn set_font(&mut self, font: Handle<ab_glyph::FontArc>) {
+        let brush = GlyphBrushBuilder::using_font((*font.get()).clone());
+        self.glyph_brush = Some(brush.build(&self.device, self.configuration.format));
+        self.font = Some((font.clone(), font.version()));
+    }
+
+    // Picks up the texture and font if they were reloaded.
+    fn refresh_assets(&mut self) {
+        self.sprite_pipeline.refresh(&self.device);
+        if let Some((font, version)) = &self.font {
+            if font.version() != *version {
+                self.set_font(font.clone());
+            }
+        }
+    }
+
+    // The whole surface, as a rect.
+    pub fn screen_rect(&self) -> ScreenRect {
+        ScreenRect::new(0, 0, self.size.width, self.size.height)
+    }
+
+    // The part of the surface the game is drawn to: the whole surface, or the largest rect with
+    // the aspect ratio of `virtual_resolution`, centred, when it's set.
+    pub fn game_rect(&self) -> ScreenRect {
+        let screen = self.screen_rect();
+        let Some(virtual_size) = self.virtual_resolution else {
+            return screen;
+        };
+        if virtual_size.width == 0 || virtual_size.height == 0 {
+            return screen;
+        }
+        let scale = (screen.width as f32 / virtual_size.width as f32)
+            .min(screen.height as f32 / virtual_size.height as f32);
+        let width = (virtual_size.width as f32 * scale).round() as u32;
+        let height = (virtual_size.height as f32 * scale).round() as u32;
+        ScreenRect::new(
+            (screen.width - width) / 2,
+            (screen.height - height) / 2,
+            width,
+            height,
+        )
+    }
+
+    // Converts a colour to the space expected by the surface format.
+    pub fn wgpu_color(&self, color: Color) -> wgpu::Color {
+        let [r, g, b, a] = color.for_format(self.configuration.format);
+        wgpu::Color {
+            r: r as f64,
+            g: g as f64,
+            b: b as f64,
+            a: a as f64,
         }
     }
 
@@ -123,7 +228,11 @@ impl Graphics {
         sprites: &[Sprite],
         score: u32,
     ) -> Result<(), wgpu::SurfaceError> {
+        self.refresh_assets();
+        self.camera_buffer.write(&self.queue, &[self.camera]);
+
         // Setup render.
+        let clear_color = self.wgpu_color(self.clear_color);
         let output = self.surface.get_current_texture()?;
         let view = &output
             .texture
@@ -140,12 +249,7 @@ impl Graphics {
                 view,
                 resolve_target: None,
                 ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(wgpu::Color {
-                        r: 0.1,
-                        g: 0.2,
-                        b: 0.3,
-                        a: 1.0,
-                    }),
+                    load: wgpu::LoadOp::Clear(clear_color),
                     store: true,
                 },
             })],
@@ -163,25 +267,27 @@ impl Graphics {
         // Submit to screen.
         drop(render_pass);
 
-        self.glyph_brush.queue(Section {
-            screen_position: (350.0, 10.0),
-            bounds: (self.size.width as f32, self.size.height as f32),
-            text: vec![Text::new(&format!("{}", score))
-                .with_color([1.0, 0.0, 0.0, 1.0])
-                .with_scale(40.0)],
-            ..Section::default()
-        });
+        if let Some(glyph_brush) = &mut self.glyph_brush {
+            glyph_brush.queue(Section {
+                screen_position: (350.0, 10.0),
+                bounds: (self.size.width as f32, self.size.height as f32),
+                text: vec![Text::new(&format!("{}", score))
+                    .with_color([1.0, 0.0, 0.0, 1.0])
+                    .with_scale(40.0)],
+                ..Section::default()
+            });
 
-        self.glyph_brush
-            .draw_queued(
-                &self.device,
-                &mut self.staging_belt,
-                &mut encoder,
-                view,
-                self.size.width,
-                self.size.height,
-            )
-            .expect("Draw queued");
+            glyph_brush
+                .draw_queued(
+                    &self.device,
+                    &mut self.staging_belt,
+                    &mut encoder,
+                    view,
+                    self.size.width,
+                    self.size.height,
+                )
+                .expect("Draw queued");
+        }
 
         self.staging_belt.finish();
         self.queue.submit(iter::once(encoder.finish()));
@@ -190,27 +296,168 @@ impl Graphics {
     }
 }
 
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum Primitive {
+    Sprite,
+    Rect,
+}
+
+// The state a draw call is made with. Consecutive draws sharing the same state are batched.
+#[derive(Copy, Clone, Eq, PartialEq)]
+struct DrawState {
+    primitive: Primitive,
+    blend_mode: BlendMode,
+    camera: usize,
+    viewport: Option<ScreenRect>,
+    clip: Option<ScreenRect>,
+}
+
+struct Batch {
+    state: DrawState,
+    instances: Range<u32>,
+}
+
 pub struct Canvas<'a> {
     graphics: &'a mut Graphics,
     sprites: Vec<Sprite>,
     rects: Vec<Square>,
+    batches: Vec<Batch>,
+    blend_mode: BlendMode,
+    cameras: Vec<Camera2d>,
+    camera: usize,
+    viewport: Option<ScreenRect>,
+    clip_stack: Vec<ScreenRect>,
+    lights: Vec<Light>,
+    ambient_light: Option<Color>,
 }
 
 impl<'a> Canvas<'a> {
     pub fn new(graphics: &'a mut Graphics) -> Self {
+        let camera = graphics.camera;
         Self {
             graphics,
             sprites: vec![],
             rects: vec![],
+            batches: vec![],
+            blend_mode: BlendMode::default(),
+            cameras: vec![camera],
+            camera: 0,
+            viewport: None,
+            clip_stack: vec![],
+            lights: vec![],
+            ambient_light: None,
         }
     }
 
+    pub fn graphics(&mut self) -> &mut Graphics {
+        self.graphics
+    }
+
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    // Sets the blend mode used by the following draw calls.
+    pub fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        self.blend_mode = blend_mode;
+    }
+
     pub fn draw_sprite(&mut self, sprite: &Sprite) {
         self.sprites.push(*sprite);
+        self.push_instance(Primitive::Sprite, self.sprites.len() as u32 - 1);
+    }
+
+    // Draws the nine pieces of the panel as sprites.
+    pub fn draw_nine_slice(&mut self, nine_slice: &NineSlice) {
+        for sprite in nine_slice.sprites() {
+            self.draw_sprite(&sprite);
+        }
     }
 
     pub fn draw_rect(&mut self, rect: &Square) {
         self.rects.push(*rect);
+        self.push_instance(Primitive::Rect, self.rects.len() as u32 - 1);
+    }
+
+    // Sets the camera used by the following draw calls. Past `MAX_CAMERAS` different cameras in
+    // a frame, the last one is replaced instead, moving what was drawn with it.
+    pub fn set_camera(&mut self, camera: Camera2d) {
+        self.camera = match self.cameras.iter().position(|c| *c == camera) {
+            Some(index) => index,
+            None if self.cameras.len() < MAX_CAMERAS => {
+                self.cameras.push(camera);
+                self.cameras.len() - 1
+            }
+            None => {
+                log::warn!(
+                    "At most {} cameras can be used in a frame, replacing the last one",
+                    MAX_CAMERAS
+                );
+                self.cameras[MAX_CAMERAS - 1] = camera;
+                MAX_CAMERAS - 1
+            }
+        };
+    }
+
+    // Renders the following draw calls into `rect` of the surface, seen through `camera`. Drawing
+    // the world once per viewport allows for split-screen and minimaps.
+    pub fn set_viewport(&mut self, rect: ScreenRect, camera: Camera2d) {
+        self.viewport = Some(rect);
+        self.set_camera(camera);
+    }
+
+    // Goes back to rendering to `Graphics::game_rect` with the default camera.
+    pub fn reset_viewport(&mut self) {
+        self.viewport = None;
+        self.camera = 0;
+    }
+
+    // Restricts the following draw calls to `rect`, intersected with the current clip rect.
+    pub fn push_clip(&mut self, rect: ScreenRect) {
+        let rect = match self.clip_stack.last() {
+            Some(current) => current.intersection(&rect),
+            None => rect,
+        };
+        self.clip_stack.push(rect);
+    }
+
+    pub fn pop_clip(&mut self) {
+        self.clip_stack.pop();
+    }
+
+    // Enables the lighting pass, darkening everything not reached by a light to `color`.
+    pub fn set_ambient_light(&mut self, color: Color) {
+        self.ambient_light = Some(color);
+    }
+
+    // Adds a light to the lighting pass. Lights are drawn over all sprites and rects drawn on
+    // the canvas, regardless of the order of the calls, and are seen through the camera of each
+    // viewport.
+    pub fn draw_light(&mut self, light: &Light) {
+        self.lights.push(*light);
+    }
+
+    fn lighting_enabled(&self) -> bool {
+        self.ambient_light.is_some() || !self.lights.is_empty()
+    }
+
+    fn push_instance(&mut self, primitive: Primitive, index: u32) {
+        let state = DrawState {
+            primitive,
+            blend_mode: self.blend_mode,
+            camera: self.camera,
+            viewport: self.viewport,
+            clip: self.clip_stack.last().copied(),
+        };
+        match self.batches.last_mut() {
+            Some(batch) if batch.state == state => {
+                batch.instances.end = index + 1;
+            }
+            _ => self.batches.push(Batch {
+                state,
+                instances: index..index + 1,
+            }),
+        }
     }
 
     pub fn done(self) {
@@ -218,7 +465,29 @@ impl<'a> Canvas<'a> {
     }
 
     fn render(&mut self) {
+        self.graphics.refresh_assets();
+
+        // Upload instances.
+        self.graphics
+            .sprite_pipeline
+            .prepare(&mut self.graphics.queue, &self.sprites);
+        self.graphics
+            .square_pipeline
+            .prepare(&mut self.graphics.queue, &self.rects);
+        self.graphics
+            .camera_buffer
+            .write(&self.graphics.queue, &self.cameras);
+        if self.lighting_enabled() {
+            let use_normals = self.graphics.sprite_pipeline.normal_map().is_some();
+            self.graphics.light_pipeline.prepare(
+                &mut self.graphics.queue,
+                &self.lights,
+                use_normals,
+            );
+        }
+
         // Setup render.
+        let clear_color = self.graphics.wgpu_color(self.graphics.clear_color);
         let output = self.graphics.surface.get_current_texture().unwrap();
         let view = &output
             .texture
@@ -236,12 +505,7 @@ impl<'a> Canvas<'a> {
                 view,
                 resolve_target: None,
                 ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(wgpu::Color {
-                        r: 0.1,
-                        g: 0.2,
-                        b: 0.3,
-                        a: 1.0,
-                    }),
+                    load: wgpu::LoadOp::Clear(clear_color),
                     store: true,
                 },
             })],
@@ -249,46 +513,93 @@ impl<'a> Canvas<'a> {
         });
 
         // Render pipelines.
-        self.graphics.sprite_pipeline.render(
-            &mut render_pass,
-            &mut self.graphics.queue,
-            &self.sprites,
-        );
-
-        if !self.rects.is_empty() {
-            self.graphics.square_pipeline.render(
-                &mut render_pass,
-                &mut self.graphics.queue,
-                &self.rects,
+        let screen = self.graphics.screen_rect();
+        let game_rect = self.graphics.game_rect();
+        let sprite_pipeline = &self.graphics.sprite_pipeline;
+        let square_pipeline = &self.graphics.square_pipeline;
+        let mut light_views = vec![];
+        for batch in &self.batches {
+            let state = &batch.state;
+            let viewport = state.viewport.unwrap_or(game_rect).intersection(&screen);
+            let clip = state.clip.unwrap_or(screen).intersection(&viewport);
+            if clip.is_empty() {
+                continue;
+            }
+            render_pass.set_viewport(
+                viewport.x as f32,
+                viewport.y as f32,
+                viewport.width as f32,
+                viewport.height as f32,
+                0.0,
+                1.0,
             );
+            render_pass.set_scissor_rect(clip.x, clip.y, clip.width, clip.height);
+
+            let camera_offset = CameraBuffer::offset(state.camera);
+            light_views.push(LightView {
+                camera_offset,
+                viewport,
+                clip,
+                sprites: match state.primitive {
+                    Primitive::Sprite => batch.instances.clone(),
+                    Primitive::Rect => 0..0,
+                },
+            });
+            match state.primitive {
+                Primitive::Sprite => sprite_pipeline.draw(
+                    &mut render_pass,
+                    camera_offset,
+                    state.blend_mode,
+                    batch.instances.clone(),
+                ),
+                Primitive::Rect => square_pipeline.draw(
+                    &mut render_pass,
+                    camera_offset,
+                    state.blend_mode,
+                    batch.instances.clone(),
+                ),
+            }
         }
 
         // Submit to screen.
         drop(render_pass);
 
-        self.graphics.glyph_brush.queue(Section {
-            screen_position: (350.0, 10.0),
-            bounds: (
-                self.graphics.size.width as f32,
-                self.graphics.size.height as f32,
-            ),
-            text: vec![Text::new(&format!("{}", 1000))
-                .with_color([1.0, 0.0, 0.0, 1.0])
-                .with_scale(40.0)],
-            ..Section::default()
-        });
-
-        self.graphics
-            .glyph_brush
-            .draw_queued(
-                &self.graphics.device,
-                &mut self.graphics.staging_belt,
+        if self.lighting_enabled() {
+            let ambient = self
+                .graphics
+                .wgpu_color(self.ambient_light.unwrap_or(Color::WHITE));
+            self.graphics.light_pipeline.render(
                 &mut encoder,
                 view,
-                self.graphics.size.width,
-                self.graphics.size.height,
-            )
-            .expect("Draw queued");
+                &self.graphics.sprite_pipeline,
+                &light_views,
+                ambient,
+                game_rect,
+            );
+        }
+
+        let graphics = &mut *self.graphics;
+        if let Some(glyph_brush) = &mut graphics.glyph_brush {
+            glyph_brush.queue(Section {
+                screen_position: (350.0, 10.0),
+                bounds: (graphics.size.width as f32, graphics.size.height as f32),
+                text: vec![Text::new(&format!("{}", 1000))
+                    .with_color([1.0, 0.0, 0.0, 1.0])
+                    .with_scale(40.0)],
+                ..Section::default()
+            });
+
+            glyph_brush
+                .draw_queued(
+                    &graphics.device,
+                    &mut graphics.staging_belt,
+                    &mut encoder,
+                    view,
+                    graphics.size.width,
+                    graphics.size.height,
+                )
+                .expect("Draw queued");
+        }
 
         self.graphics.staging_belt.finish();
         self.graphics.queue.submit(iter::once(encoder.finish()));
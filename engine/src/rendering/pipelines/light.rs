@@ -0,0 +1,605 @@
+use crate::rendering::camera::CameraBuffer;
+use crate::rendering::lighting::{Light, LightKind};
+use crate::rendering::pipelines::sprite::{SpriteInstance, MAX_INSTANCES};
+use crate::rendering::pipelines::{SpritePipeline, Vertex, SQUARE_INDICES, SQUARE_VERTICES};
+use crate::rendering::viewport::ScreenRect;
+use std::ops::Range;
+use wgpu::util::DeviceExt;
+use wgpu::{
+    BindGroup, BindGroupLayout, BufferAddress, BufferDescriptor, CommandEncoder, Device, Queue,
+    RenderPass, RenderPipeline, SurfaceConfiguration, TextureFormat, TextureView, VertexAttribute,
+};
+
+const MAX_LIGHTS: usize = 256;
+
+// Lights can add up to more than 1.0, so the light map uses a float format.
+const LIGHT_MAP_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+const NORMAL_MAP_FORMAT: TextureFormat = TextureFormat::Rgba8Unorm;
+
+// A normal pointing straight out of the screen.
+const FLAT_NORMAL: wgpu::Color = wgpu::Color {
+    r: 0.5,
+    g: 0.5,
+    b: 1.0,
+    a: 1.0,
+};
+
+// A part of the frame drawn through the same camera, viewport and clip rect. Lights are drawn
+// once for each of them, and the sprites drawn in it again to build the normals.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LightView {
+    pub camera_offset: u32,
+    pub viewport: ScreenRect,
+    pub clip: ScreenRect,
+    pub sprites: Range<u32>,
+}
+
+impl LightView {
+    fn same_area(&self, other: &LightView) -> bool {
+        self.camera_offset == other.camera_offset
+            && self.viewport == other.viewport
+            && self.clip == other.clip
+    }
+
+    fn apply(&self, render_pass: &mut RenderPass) {
+        render_pass.set_viewport(
+            self.viewport.x as f32,
+            self.viewport.y as f32,
+            self.viewport.width as f32,
+            self.viewport.height as f32,
+            0.0,
+            1.0,
+        );
+        render_pass.set_scissor_rect(self.clip.x, self.clip.y, self.clip.width, self.clip.height);
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LightInstance {
+    light: [f32; 4],
+    color: [f32; 4],
+    spot: [f32; 4],
+}
+
+impl LightInstance {
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        use std::mem;
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<LightInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[
+                VertexAttribute {
+                    offset: 0,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                VertexAttribute {
+                    offset: mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                VertexAttribute {
+                    offset: mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+
+    pub fn from_light(light: &Light, format: TextureFormat) -> Self {
+        let [r, g, b, _] = light.color.for_format(format);
+        let spot = match light.kind {
+            // A cosine below -1.0 disables the cone in the shader.
+            LightKind::Point => [1.0, 0.0, -2.0, -2.0],
+            LightKind::Spot {
+                direction,
+                inner_angle,
+                outer_angle,
+            } => [
+                direction.cos(),
+                direction.sin(),
+                outer_angle.cos(),
+                inner_angle.cos(),
+            ],
+        };
+        Self {
+            light: [
+                light.position.x,
+                light.position.y,
+                light.radius,
+                light.height,
+            ],
+            color: [
+                r * light.intensity,
+                g * light.intensity,
+                b * light.intensity,
+                light.falloff,
+            ],
+            spot,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct LightSettingsUniform {
+    use_normals: u32,
+    _padding: [u32; 3],
+}
+
+// The off-screen textures the lighting is rendered to. They match the size of the surface.
+struct LightTargets {
+    _light_map: wgpu::Texture,
+    light_map_view: TextureView,
+    _normal_map: wgpu::Texture,
+    normal_map_view: TextureView,
+    light_bind_group: BindGroup,
+    composite_bind_group: BindGroup,
+}
+
+// Renders lights to a light map, which is then multiplied over the scene.
+pub struct LightPipeline {
+    light_pipeline: RenderPipeline,
+    normal_pipeline: RenderPipeline,
+    composite_pipeline: RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    instance_buffer: wgpu::Buffer,
+    num_indices: u32,
+    num_lights: u32,
+    camera_bind_group: BindGroup,
+    settings_buffer: wgpu::Buffer,
+    light_bind_group_layout: BindGroupLayout,
+    composite_bind_group_layout: BindGroupLayout,
+    targets: LightTargets,
+    format: TextureFormat,
+}
+
+impl LightPipeline {
+    pub fn new(
+        device: &mut Device,
+        configuration: &SurfaceConfiguration,
+        sprite_pipeline: &SpritePipeline,
+        cameras: &CameraBuffer,
+    ) -> Self {
+        let light_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Light Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/light.wgsl").into()),
+        });
+        let composite_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Light Composite Shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                include_str!("../shaders/light_composite.wgsl").into(),
+            ),
+        });
+        let sprite_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/sprite.wgsl").into()),
+        });
+
+        // Camera Uniform
+        let camera_bind_group = cameras.create_bind_group(device);
+
+        let settings_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Light Settings Buffer"),
+            size: std::mem::size_of::<LightSettingsUniform>() as BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let light_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+                label: Some("light_bind_group_layout"),
+            });
+
+        let composite_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    },
+                    count: None,
+                }],
+                label: Some("composite_bind_group_layout"),
+            });
+
+        // Render Pipelines
+        let light_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Light Pipeline Layout"),
+                bind_group_layouts: &[&cameras.bind_group_layout, &light_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let light_pipeline = create_pipeline(
+            device,
+            &light_pipeline_layout,
+            &light_shader,
+            &[Vertex::desc(), LightInstance::desc()],
+            LIGHT_MAP_FORMAT,
+            // Lights add up.
+            wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::One,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent::REPLACE,
+            },
+        );
+
+        // The normal pass draws the sprites again, with the normal map bound instead of the
+        // sprite texture.
+        let normal_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Normal Pipeline Layout"),
+                bind_group_layouts: &[
+                    &cameras.bind_group_layout,
+                    &sprite_pipeline.texture_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            });
+        let normal_pipeline = create_pipeline(
+            device,
+            &normal_pipeline_layout,
+            &sprite_shader,
+            &[Vertex::desc(), SpriteInstance::desc()],
+            NORMAL_MAP_FORMAT,
+            wgpu::BlendState::ALPHA_BLENDING,
+        );
+
+        let composite_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Light Composite Pipeline Layout"),
+                bind_group_layouts: &[&composite_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let composite_pipeline = create_pipeline(
+            device,
+            &composite_pipeline_layout,
+            &composite_shader,
+            &[],
+            configuration.format,
+            // Multiplies the scene by the light map.
+            wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::Dst,
+                    dst_factor: wgpu::BlendFactor::Zero,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::Zero,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+            },
+        );
+
+        // Vertex & Index Buffers
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(SQUARE_VERTICES),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Index Buffer"),
+            contents: bytemuck::cast_slice(SQUARE_INDICES),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        let num_indices = SQUARE_INDICES.len() as u32;
+
+        let instance_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Light Instance Buffer"),
+            size: (std::mem::size_of::<LightInstance>() * MAX_LIGHTS) as BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let targets = create_targets(
+            device,
+            configuration.width,
+            configuration.height,
+            &light_bind_group_layout,
+            &composite_bind_group_layout,
+            &settings_buffer,
+        );
+
+        Self {
+            light_pipeline,
+            normal_pipeline,
+            composite_pipeline,
+            vertex_buffer,
+            index_buffer,
+            instance_buffer,
+            num_indices,
+            num_lights: 0,
+            camera_bind_group,
+            settings_buffer,
+            light_bind_group_layout,
+            composite_bind_group_layout,
+            targets,
+            format: configuration.format,
+        }
+    }
+
+    pub fn resize(&mut self, device: &Device, width: u32, height: u32) {
+        self.targets = create_targets(
+            device,
+            width,
+            height,
+            &self.light_bind_group_layout,
+            &self.composite_bind_group_layout,
+            &self.settings_buffer,
+        );
+    }
+
+    pub fn prepare(&mut self, queue: &mut Queue, lights: &[Light], use_normals: bool) {
+        let instance_data: Vec<LightInstance> = lights
+            .iter()
+            .take(MAX_LIGHTS)
+            .map(|light| LightInstance::from_light(light, self.format))
+            .collect();
+        queue.write_buffer(
+            &self.instance_buffer,
+            0,
+            bytemuck::cast_slice(&instance_data),
+        );
+        queue.write_buffer(
+            &self.settings_buffer,
+            0,
+            bytemuck::cast_slice(&[LightSettingsUniform {
+                use_normals: use_normals as u32,
+                _padding: [0; 3],
+            }]),
+        );
+        self.num_lights = instance_data.len() as u32;
+    }
+
+    // Renders the light map and multiplies it over the `game_rect` of `view`, which should
+    // already contain the scene. The sprites prepared on the sprite pipeline are drawn again to
+    // build the normals when their texture has a normal map.
+    pub fn render(
+        &self,
+        encoder: &mut CommandEncoder,
+        view: &TextureView,
+        sprite_pipeline: &SpritePipeline,
+        light_views: &[LightView],
+        ambient: wgpu::Color,
+        game_rect: ScreenRect,
+    ) {
+        if let Some(normal_bind_group) = sprite_pipeline.normal_map() {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Normal Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.targets.normal_map_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(FLAT_NORMAL),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            render_pass.set_pipeline(&self.normal_pipeline);
+            render_pass.set_bind_group(1, normal_bind_group, &[]);
+            render_pass.set_vertex_buffer(0, sprite_pipeline.vertex_buffer.slice(..));
+            render_pass.set_vertex_buffer(1, sprite_pipeline.instance_buffer.slice(..));
+            render_pass.set_index_buffer(
+                sprite_pipeline.index_buffer.slice(..),
+                wgpu::IndexFormat::Uint16,
+            );
+            for light_view in light_views {
+                let sprites = light_view.sprites.start.min(MAX_INSTANCES as u32)
+                    ..light_view.sprites.end.min(MAX_INSTANCES as u32);
+                if sprites.is_empty() {
+                    continue;
+                }
+                light_view.apply(&mut render_pass);
+                render_pass.set_bind_group(0, &self.camera_bind_group, &[light_view.camera_offset]);
+                render_pass.draw_indexed(0..sprite_pipeline.num_indices, 0, sprites);
+            }
+        }
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Light Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.targets.light_map_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(ambient),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            if self.num_lights > 0 {
+                render_pass.set_pipeline(&self.light_pipeline);
+                render_pass.set_bind_group(1, &self.targets.light_bind_group, &[]);
+                render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+                render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+                render_pass
+                    .set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+                for (index, light_view) in light_views.iter().enumerate() {
+                    // Lights add up, so each area is only lit once however many batches it has.
+                    if light_views[..index]
+                        .iter()
+                        .any(|other| other.same_area(light_view))
+                    {
+                        continue;
+                    }
+                    light_view.apply(&mut render_pass);
+                    render_pass.set_bind_group(
+                        0,
+                        &self.camera_bind_group,
+                        &[light_view.camera_offset],
+                    );
+                    render_pass.draw_indexed(0..self.num_indices, 0, 0..self.num_lights);
+                }
+            }
+        }
+
+        if game_rect.is_empty() {
+            return;
+        }
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Light Composite Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        // Leaves the letterboxing around the game as it is.
+        render_pass.set_scissor_rect(game_rect.x, game_rect.y, game_rect.width, game_rect.height);
+        render_pass.set_pipeline(&self.composite_pipeline);
+        render_pass.set_bind_group(0, &self.targets.composite_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+fn create_pipeline(
+    device: &Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    buffers: &[wgpu::VertexBufferLayout],
+    format: TextureFormat,
+    blend: wgpu::BlendState,
+) -> RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Render Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers,
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(blend),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: None,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+    })
+}
+
+fn create_target(
+    device: &Device,
+    label: &str,
+    format: TextureFormat,
+    width: u32,
+    height: u32,
+) -> (wgpu::Texture, TextureView) {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size: wgpu::Extent3d {
+            width: width.max(1),
+            height: height.max(1),
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    (texture, view)
+}
+
+fn create_targets(
+    device: &Device,
+    width: u32,
+    height: u32,
+    light_bind_group_layout: &BindGroupLayout,
+    composite_bind_group_layout: &BindGroupLayout,
+    settings_buffer: &wgpu::Buffer,
+) -> LightTargets {
+    let (light_map, light_map_view) =
+        create_target(device, "Light Map", LIGHT_MAP_FORMAT, width, height);
+    let (normal_map, normal_map_view) =
+        create_target(device, "Normal Map", NORMAL_MAP_FORMAT, width, height);
+
+    let light_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout: light_bind_group_layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&normal_map_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: settings_buffer.as_entire_binding(),
+            },
+        ],
+        label: Some("light_bind_group"),
+    });
+    let composite_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout: composite_bind_group_layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::TextureView(&light_map_view),
+        }],
+        label: Some("composite_bind_group"),
+    });
+
+    LightTargets {
+        _light_map: light_map,
+        light_map_view,
+        _normal_map: normal_map,
+        normal_map_view,
+        light_bind_group,
+        composite_bind_group,
+    }
+}
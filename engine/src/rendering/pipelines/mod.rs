@@ -1,8 +1,12 @@
-use wgpu::VertexAttribute;
+use crate::rendering::blend::BlendMode;
+use std::collections::HashMap;
+use wgpu::{Device, PipelineLayout, RenderPipeline, ShaderModule, TextureFormat, VertexAttribute};
 
+mod light;
 mod sprite;
 mod square;
 
+pub use light::{LightPipeline, LightView};
 pub use sprite::SpritePipeline;
 pub use square::SquarePipeline;
 
@@ -41,3 +45,58 @@ impl Vertex {
         }
     }
 }
+
+// Creates one render pipeline for each blend mode, so they can be switched between draws.
+fn create_render_pipelines(
+    device: &Device,
+    layout: &PipelineLayout,
+    shader: &ShaderModule,
+    buffers: &[wgpu::VertexBufferLayout],
+    format: TextureFormat,
+) -> HashMap<BlendMode, RenderPipeline> {
+    BlendMode::ALL
+        .iter()
+        .map(|blend_mode| {
+            let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Render Pipeline"),
+                layout: Some(layout),
+                vertex: wgpu::VertexState {
+                    module: shader,
+                    entry_point: "vs_main",
+                    buffers,
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: shader,
+                    entry_point: blend_mode.fragment_entry_point(),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: Some(blend_mode.blend_state()),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: Some(wgpu::Face::Back),
+                    // Setting this to anything other than Fill requires Features::NON_FILL_POLYGON_MODE
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    // Requires Features::DEPTH_CLIP_CONTROL
+                    unclipped_depth: false,
+                    // Requires Features::CONSERVATIVE_RASTERIZATION
+                    conservative: false,
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                // If the pipeline will be used with a multiview render pass, this
+                // indicates how many array layers the attachments will have.
+                multiview: None,
+            });
+            (*blend_mode, render_pipeline)
+        })
+        .collect()
+}
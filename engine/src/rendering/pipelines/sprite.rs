@@ -1,15 +1,22 @@
-use crate::rendering::camera::{Camera2d, CameraUniform};
-use crate::rendering::pipelines::{Vertex, SQUARE_INDICES, SQUARE_VERTICES};
+use crate::assets::Handle;
+use crate::rendering::blend::BlendMode;
+use crate::rendering::camera::CameraBuffer;
+use crate::rendering::pipelines::{
+    create_render_pipelines, Vertex, SQUARE_INDICES, SQUARE_VERTICES,
+};
 use crate::rendering::shapes::Sprite;
 use crate::rendering::texture::Texture;
 use cgmath::Matrix4;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::PathBuf;
 use wgpu::util::DeviceExt;
 use wgpu::{
     BindGroup, BufferAddress, BufferDescriptor, Device, Queue, RenderPass, SurfaceConfiguration,
     VertexAttribute,
 };
 
-const MAX_INSTANCES: usize = 1000;
+pub(super) const MAX_INSTANCES: usize = 1000;
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
@@ -19,7 +26,7 @@ pub struct SpriteInstance {
 }
 
 impl SpriteInstance {
-    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+    pub(super) fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
         use std::mem;
         wgpu::VertexBufferLayout {
             array_stride: mem::size_of::<SpriteInstance>() as wgpu::BufferAddress,
@@ -70,14 +77,19 @@ impl SpriteInstance {
 }
 
 pub struct SpritePipeline {
-    pub render_pipeline: wgpu::RenderPipeline,
+    pub render_pipelines: HashMap<BlendMode, wgpu::RenderPipeline>,
     pub vertex_buffer: wgpu::Buffer,
     pub index_buffer: wgpu::Buffer,
     pub instance_buffer: wgpu::Buffer,
     pub num_indices: u32,
     pub camera_bind_group: BindGroup,
-    pub diffuse_texture: Texture,
+    pub diffuse_texture: Handle<Texture>,
+    // Version of `diffuse_texture` the bind group was created for.
+    diffuse_version: u32,
     pub diffuse_bind_group: wgpu::BindGroup,
+    pub texture_bind_group_layout: wgpu::BindGroupLayout,
+    // Normal maps for the lighting pass, by the path of the texture they belong to.
+    normal_maps: HashMap<PathBuf, (Texture, wgpu::BindGroup)>,
 }
 
 impl<'a> SpritePipeline {
@@ -85,13 +97,28 @@ impl<'a> SpritePipeline {
         device: &mut Device,
         queue: &mut Queue,
         configuration: &SurfaceConfiguration,
+        cameras: &CameraBuffer,
     ) -> Self {
-        let diffuse_bytes = include_bytes!("../../../../bomberjack/src/assets/texture.png");
-        let diffuse_texture =
-            Texture::from_bytes(device, queue, diffuse_bytes, "texture.png").unwrap();
+        // Plain white until the game sets its texture.
+        let white = image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+            1,
+            1,
+            image::Rgba([255; 4]),
+        ));
+        let diffuse_texture = Handle::new(
+            "white",
+            Texture::from_image(
+                device,
+                queue,
+                &white,
+                Some("white"),
+                Texture::format_for_surface(configuration.format),
+            )
+            .unwrap(),
+        );
 
         let texture_bind_group_layout =
-            &device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 entries: &[
                     wgpu::BindGroupLayoutEntry {
                         binding: 0,
@@ -113,20 +140,12 @@ impl<'a> SpritePipeline {
                 label: Some("texture_bind_group_layout"),
             });
 
-        let diffuse_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: texture_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&diffuse_texture.view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&diffuse_texture.sampler),
-                },
-            ],
-            label: Some("diffuse_bind_group"),
-        });
+        let diffuse_bind_group = create_texture_bind_group(
+            device,
+            &texture_bind_group_layout,
+            &diffuse_texture.get(),
+            "diffuse_bind_group",
+        );
 
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Shader"),
@@ -134,90 +153,23 @@ impl<'a> SpritePipeline {
         });
 
         // Camera Uniform
-        let camera2d = Camera2d::new(600.0, 650.0);
-        let mut camera_uniform = CameraUniform::default();
-        camera_uniform.update_view_proj(&camera2d);
-
-        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Camera Buffer"),
-            contents: bytemuck::cast_slice(&[camera_uniform]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
-
-        let camera_bind_group_layout =
-            &device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                }],
-                label: Some("camera_bind_group_layout"),
-            });
-
-        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: camera_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: camera_buffer.as_entire_binding(),
-            }],
-            label: Some("camera_bind_group"),
-        });
+        let camera_bind_group = cameras.create_bind_group(device);
 
         // Render Pipeline
         let render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[camera_bind_group_layout, texture_bind_group_layout],
+                bind_group_layouts: &[&cameras.bind_group_layout, &texture_bind_group_layout],
                 push_constant_ranges: &[],
             });
 
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[Vertex::desc(), SpriteInstance::desc()],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: configuration.format,
-                    blend: Some(wgpu::BlendState {
-                        color: wgpu::BlendComponent::OVER,
-                        alpha: wgpu::BlendComponent::REPLACE,
-                    }),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                // Setting this to anything other than Fill requires Features::NON_FILL_POLYGON_MODE
-                polygon_mode: wgpu::PolygonMode::Fill,
-                // Requires Features::DEPTH_CLIP_CONTROL
-                unclipped_depth: false,
-                // Requires Features::CONSERVATIVE_RASTERIZATION
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            // If the pipeline will be used with a multiview render pass, this
-            // indicates how many array layers the attachments will have.
-            multiview: None,
-        });
+        let render_pipelines = create_render_pipelines(
+            device,
+            &render_pipeline_layout,
+            &shader,
+            &[Vertex::desc(), SpriteInstance::desc()],
+            configuration.format,
+        );
 
         // Vertex & Index Buffers
         let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -240,37 +192,148 @@ impl<'a> SpritePipeline {
         });
 
         Self {
-            render_pipeline,
+            render_pipelines,
             vertex_buffer,
             index_buffer,
             instance_buffer,
             num_indices,
             camera_bind_group,
+            diffuse_version: diffuse_texture.version(),
             diffuse_texture,
             diffuse_bind_group,
+            texture_bind_group_layout,
+            normal_maps: HashMap::new(),
+        }
+    }
+
+    // Sets the texture all sprites are drawn from.
+    pub fn set_texture(&mut self, device: &Device, texture: Handle<Texture>) {
+        self.diffuse_texture = texture;
+        self.update_bind_group(device);
+    }
+
+    // Follows reloads of the texture. Must be called before rendering.
+    pub fn refresh(&mut self, device: &Device) {
+        if self.diffuse_texture.version() != self.diffuse_version {
+            self.update_bind_group(device);
         }
     }
 
+    fn update_bind_group(&mut self, device: &Device) {
+        self.diffuse_bind_group = create_texture_bind_group(
+            device,
+            &self.texture_bind_group_layout,
+            &self.diffuse_texture.get(),
+            "diffuse_bind_group",
+        );
+        self.diffuse_version = self.diffuse_texture.version();
+    }
+
+    // Sets the normal map of `texture`, matching its layout. The lighting pass uses it while
+    // sprites are drawn from `texture`.
+    pub fn set_normal_map(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        texture: &Handle<Texture>,
+        bytes: &[u8],
+    ) -> anyhow::Result<()> {
+        let label = format!("{} normals", texture.path().display());
+        // Normals are stored as they are, not as colours, so the texture must not be sRGB.
+        let normal_map = Texture::from_bytes(
+            device,
+            queue,
+            bytes,
+            &label,
+            wgpu::TextureFormat::Rgba8Unorm,
+        )?;
+        let bind_group = create_texture_bind_group(
+            device,
+            &self.texture_bind_group_layout,
+            &normal_map,
+            "normal_bind_group",
+        );
+        self.normal_maps
+            .insert(texture.path().to_path_buf(), (normal_map, bind_group));
+        Ok(())
+    }
+
+    // The bind group of the normal map of the texture sprites are drawn from, if it has one.
+    pub fn normal_map(&self) -> Option<&wgpu::BindGroup> {
+        self.normal_maps
+            .get(self.diffuse_texture.path())
+            .map(|(_, bind_group)| bind_group)
+    }
+
     pub fn render(
         &'a mut self,
         render_pass: &mut RenderPass<'a>,
         queue: &mut Queue,
         sprites: &[Sprite],
     ) {
-        let instance_data: Vec<SpriteInstance> =
-            sprites.iter().map(SpriteInstance::from_sprite).collect();
+        self.prepare(queue, sprites);
+        self.draw(
+            render_pass,
+            0,
+            BlendMode::default(),
+            0..sprites.len() as u32,
+        );
+    }
+
+    // Uploads the instances for the frame. Must be called once, before any call to `draw`.
+    pub fn prepare(&mut self, queue: &mut Queue, sprites: &[Sprite]) {
+        let instance_data: Vec<SpriteInstance> = sprites
+            .iter()
+            .take(MAX_INSTANCES)
+            .map(SpriteInstance::from_sprite)
+            .collect();
         queue.write_buffer(
             &self.instance_buffer,
             0,
             bytemuck::cast_slice(&instance_data),
         );
+    }
 
-        render_pass.set_pipeline(&self.render_pipeline);
-        render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+    pub fn draw(
+        &'a self,
+        render_pass: &mut RenderPass<'a>,
+        camera_offset: u32,
+        blend_mode: BlendMode,
+        instances: Range<u32>,
+    ) {
+        let instances =
+            instances.start.min(MAX_INSTANCES as u32)..instances.end.min(MAX_INSTANCES as u32);
+        if instances.is_empty() {
+            return;
+        }
+        render_pass.set_pipeline(&self.render_pipelines[&blend_mode]);
+        render_pass.set_bind_group(0, &self.camera_bind_group, &[camera_offset]);
         render_pass.set_bind_group(1, &self.diffuse_bind_group, &[]);
         render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
         render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
         render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-        render_pass.draw_indexed(0..self.num_indices, 0, 0..instance_data.len() as u32);
+        render_pass.draw_indexed(0..self.num_indices, 0, instances);
     }
 }
+
+fn create_texture_bind_group(
+    device: &Device,
+    layout: &wgpu::BindGroupLayout,
+    texture: &Texture,
+    label: &str,
+) -> BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&texture.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&texture.sampler),
+            },
+        ],
+        label: Some(label),
+    })
+}
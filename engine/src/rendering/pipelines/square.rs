@@ -1,11 +1,16 @@
-use crate::rendering::camera::{Camera2d, CameraUniform};
-use crate::rendering::pipelines::{Vertex, SQUARE_INDICES, SQUARE_VERTICES};
+use crate::rendering::blend::BlendMode;
+use crate::rendering::camera::CameraBuffer;
+use crate::rendering::pipelines::{
+    create_render_pipelines, Vertex, SQUARE_INDICES, SQUARE_VERTICES,
+};
 use crate::rendering::shapes::Square;
 use cgmath::Matrix4;
+use std::collections::HashMap;
+use std::ops::Range;
 use wgpu::util::DeviceExt;
 use wgpu::{
     BindGroup, BufferAddress, BufferDescriptor, Device, Queue, RenderPass, SurfaceConfiguration,
-    VertexAttribute,
+    TextureFormat, VertexAttribute,
 };
 
 const MAX_INSTANCES: usize = 1000;
@@ -13,7 +18,7 @@ const MAX_INSTANCES: usize = 1000;
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct SquareInstance {
-    color: [f32; 3],
+    color: [f32; 4],
     transform: [[f32; 4]; 4],
 }
 
@@ -27,25 +32,25 @@ impl SquareInstance {
                 VertexAttribute {
                     offset: 0,
                     shader_location: 1,
-                    format: wgpu::VertexFormat::Float32x3,
+                    format: wgpu::VertexFormat::Float32x4,
                 },
                 VertexAttribute {
-                    offset: mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    offset: mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
                     shader_location: 2,
                     format: wgpu::VertexFormat::Float32x4,
                 },
                 VertexAttribute {
-                    offset: mem::size_of::<[f32; 7]>() as wgpu::BufferAddress,
+                    offset: mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
                     shader_location: 3,
                     format: wgpu::VertexFormat::Float32x4,
                 },
                 VertexAttribute {
-                    offset: mem::size_of::<[f32; 11]>() as wgpu::BufferAddress,
+                    offset: mem::size_of::<[f32; 12]>() as wgpu::BufferAddress,
                     shader_location: 4,
                     format: wgpu::VertexFormat::Float32x4,
                 },
                 VertexAttribute {
-                    offset: mem::size_of::<[f32; 15]>() as wgpu::BufferAddress,
+                    offset: mem::size_of::<[f32; 16]>() as wgpu::BufferAddress,
                     shader_location: 5,
                     format: wgpu::VertexFormat::Float32x4,
                 },
@@ -53,9 +58,9 @@ impl SquareInstance {
         }
     }
 
-    pub fn from_square(square: &Square) -> Self {
+    pub fn from_square(square: &Square, format: TextureFormat) -> Self {
         Self {
-            color: square.color.into(),
+            color: square.color.for_format(format),
             transform: (Matrix4::from_translation(
                 (square.position.x, square.position.y, 0.0).into(),
             ) * Matrix4::from_nonuniform_scale(
@@ -69,12 +74,13 @@ impl SquareInstance {
 }
 
 pub struct SquarePipeline {
-    render_pipeline: wgpu::RenderPipeline,
+    render_pipelines: HashMap<BlendMode, wgpu::RenderPipeline>,
     vertex_buffer: wgpu::Buffer,
     index_buffer: wgpu::Buffer,
     instance_buffer: wgpu::Buffer,
     num_indices: u32,
     camera_bind_group: BindGroup,
+    format: TextureFormat,
 }
 
 impl<'a> SquarePipeline {
@@ -82,6 +88,7 @@ impl<'a> SquarePipeline {
         device: &mut Device,
         _queue: &mut Queue,
         configuration: &SurfaceConfiguration,
+        cameras: &CameraBuffer,
     ) -> Self {
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Shader"),
@@ -89,90 +96,23 @@ impl<'a> SquarePipeline {
         });
 
         // Camera Uniform
-        let camera2d = Camera2d::new(600.0, 650.0);
-        let mut camera_uniform = CameraUniform::default();
-        camera_uniform.update_view_proj(&camera2d);
-
-        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Camera Buffer"),
-            contents: bytemuck::cast_slice(&[camera_uniform]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
-
-        let camera_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                }],
-                label: Some("camera_bind_group_layout"),
-            });
-
-        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &camera_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: camera_buffer.as_entire_binding(),
-            }],
-            label: Some("camera_bind_group"),
-        });
+        let camera_bind_group = cameras.create_bind_group(device);
 
         // Render Pipeline
         let render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[&camera_bind_group_layout],
+                bind_group_layouts: &[&cameras.bind_group_layout],
                 push_constant_ranges: &[],
             });
 
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[Vertex::desc(), SquareInstance::desc()],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: configuration.format,
-                    blend: Some(wgpu::BlendState {
-                        color: wgpu::BlendComponent::REPLACE,
-                        alpha: wgpu::BlendComponent::REPLACE,
-                    }),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                // Setting this to anything other than Fill requires Features::NON_FILL_POLYGON_MODE
-                polygon_mode: wgpu::PolygonMode::Fill,
-                // Requires Features::DEPTH_CLIP_CONTROL
-                unclipped_depth: false,
-                // Requires Features::CONSERVATIVE_RASTERIZATION
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            // If the pipeline will be used with a multiview render pass, this
-            // indicates how many array layers the attachments will have.
-            multiview: None,
-        });
+        let render_pipelines = create_render_pipelines(
+            device,
+            &render_pipeline_layout,
+            &shader,
+            &[Vertex::desc(), SquareInstance::desc()],
+            configuration.format,
+        );
 
         // Vertex & Index Buffers
         let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -195,12 +135,13 @@ impl<'a> SquarePipeline {
         });
 
         Self {
-            render_pipeline,
+            render_pipelines,
             vertex_buffer,
             index_buffer,
             instance_buffer,
             num_indices,
             camera_bind_group,
+            format: configuration.format,
         }
     }
 
@@ -210,19 +151,46 @@ impl<'a> SquarePipeline {
         queue: &mut Queue,
         squares: &[Square],
     ) {
-        let instance_data: Vec<SquareInstance> =
-            squares.iter().map(SquareInstance::from_square).collect();
+        self.prepare(queue, squares);
+        self.draw(
+            render_pass,
+            0,
+            BlendMode::default(),
+            0..squares.len() as u32,
+        );
+    }
+
+    // Uploads the instances for the frame. Must be called once, before any call to `draw`.
+    pub fn prepare(&mut self, queue: &mut Queue, squares: &[Square]) {
+        let instance_data: Vec<SquareInstance> = squares
+            .iter()
+            .take(MAX_INSTANCES)
+            .map(|square| SquareInstance::from_square(square, self.format))
+            .collect();
         queue.write_buffer(
             &self.instance_buffer,
             0,
             bytemuck::cast_slice(&instance_data),
         );
+    }
 
-        render_pass.set_pipeline(&self.render_pipeline);
-        render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+    pub fn draw(
+        &'a self,
+        render_pass: &mut RenderPass<'a>,
+        camera_offset: u32,
+        blend_mode: BlendMode,
+        instances: Range<u32>,
+    ) {
+        let instances =
+            instances.start.min(MAX_INSTANCES as u32)..instances.end.min(MAX_INSTANCES as u32);
+        if instances.is_empty() {
+            return;
+        }
+        render_pass.set_pipeline(&self.render_pipelines[&blend_mode]);
+        render_pass.set_bind_group(0, &self.camera_bind_group, &[camera_offset]);
         render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
         render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
         render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-        render_pass.draw_indexed(0..self.num_indices, 0, 0..instance_data.len() as u32);
+        render_pass.draw_indexed(0..self.num_indices, 0, instances);
     }
 }
@@ -0,0 +1,78 @@
+// Vertex shader
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct LightSettings {
+    use_normals: u32,
+};
+
+@group(1) @binding(0)
+var t_normal: texture_2d<f32>;
+
+@group(1) @binding(1)
+var<uniform> settings: LightSettings;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+};
+
+struct InstanceInput {
+    @location(1) light: vec4<f32>, // x, y, radius, height
+    @location(2) color: vec4<f32>, // r, g, b (already multiplied by the intensity), falloff
+    @location(3) spot: vec4<f32>, // direction x, direction y, cos(outer angle), cos(inner angle)
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) offset: vec2<f32>,
+    @location(1) light: vec4<f32>,
+    @location(2) color: vec4<f32>,
+    @location(3) spot: vec4<f32>,
+};
+
+@vertex
+fn vs_main(
+    model: VertexInput,
+    instance: InstanceInput,
+) -> VertexOutput {
+    let radius = instance.light.z;
+    let offset = (model.position.xy * 2.0 - 1.0) * radius;
+    let world_position = instance.light.xy + offset;
+
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * vec4<f32>(world_position, 0.0, 1.0);
+    out.offset = offset;
+    out.light = instance.light;
+    out.color = instance.color;
+    out.spot = instance.spot;
+    return out;
+}
+
+// Fragment shader
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let distance = length(in.offset) / in.light.z;
+    if (distance >= 1.0) {
+        discard;
+    }
+    var attenuation = pow(1.0 - distance, in.color.a);
+
+    // Point lights have a cone covering every direction. The centre of a spot light has no
+    // direction and is always lit.
+    if (in.spot.z > -1.0 && length(in.offset) > 0.0) {
+        let angle = dot(normalize(in.offset), in.spot.xy);
+        attenuation *= smoothstep(in.spot.z, in.spot.w, angle);
+    }
+
+    if (settings.use_normals != 0u) {
+        let normal = textureLoad(t_normal, vec2<i32>(in.clip_position.xy), 0).xyz * 2.0 - 1.0;
+        let to_light = normalize(vec3<f32>(-in.offset, in.light.w));
+        attenuation *= max(dot(normalize(normal), to_light), 0.0);
+    }
+
+    return vec4<f32>(in.color.rgb * attenuation, 1.0);
+}
@@ -0,0 +1,23 @@
+// Vertex shader
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+// Draws a single triangle covering the whole screen.
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((in_vertex_index << 1u) & 2u), f32(in_vertex_index & 2u));
+
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+    return out;
+}
+
+@group(0) @binding(0)
+var t_light: texture_2d<f32>;
+
+// Fragment shader
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureLoad(t_light, vec2<i32>(in.clip_position.xy), 0);
+}
@@ -12,7 +12,7 @@ struct VertexInput {
 };
 
 struct InstanceInput {
-    @location(1) color: vec3<f32>,
+    @location(1) color: vec4<f32>,
     @location(2) transform_1: vec4<f32>,
     @location(3) transform_2: vec4<f32>,
     @location(4) transform_3: vec4<f32>,
@@ -21,7 +21,7 @@ struct InstanceInput {
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
-    @location(0) color: vec3<f32>,
+    @location(0) color: vec4<f32>,
 };
 
 @vertex
@@ -44,5 +44,10 @@ fn vs_main(
 // Fragment shader
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return vec4<f32>(in.color, 1.0);
+    return in.color;
+}
+
+@fragment
+fn fs_premultiply(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(in.color.rgb * in.color.a, in.color.a);
 }
@@ -1,11 +1,12 @@
-use cgmath::{Vector2, Vector3, Vector4};
+use crate::rendering::color::Color;
+use cgmath::{Vector2, Vector4};
 use winit::dpi::{LogicalSize, PhysicalSize};
 
 #[derive(Copy, Clone)]
 pub struct Square {
     pub position: Vector2<f32>,
     pub size: PhysicalSize<f32>,
-    pub color: Vector3<f32>,
+    pub color: Color,
 }
 
 #[derive(Copy, Clone)]
@@ -14,3 +15,194 @@ pub struct Sprite {
     pub size: LogicalSize<f32>,
     pub texture: Vector4<f32>, //x0, y0, x1, y1 - or (0.0, 0.0, 1.0, 1.0)
 }
+
+// Border sizes, in pixels of the texture. Borders are drawn at the same size in the world.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Insets {
+    pub left: f32,
+    pub right: f32,
+    pub top: f32,
+    pub bottom: f32,
+}
+
+impl Insets {
+    pub fn uniform(inset: f32) -> Self {
+        Self {
+            left: inset,
+            right: inset,
+            top: inset,
+            bottom: inset,
+        }
+    }
+}
+
+// A frame that can be stretched to any size without distorting its corners. The corners are
+// drawn as they are, the edges are stretched along one axis and the centre along both.
+#[derive(Copy, Clone)]
+pub struct NineSlice {
+    pub position: Vector2<f32>,
+    pub size: LogicalSize<f32>,
+    pub texture: Vector4<f32>, //x0, y0, x1, y1 - or (0.0, 0.0, 1.0, 1.0)
+    pub texture_size: LogicalSize<f32>, // size of the frame in the texture, in pixels.
+    pub insets: Insets,
+}
+
+impl NineSlice {
+    // The sprites to draw, row by row from the bottom left. When the panel is smaller than its
+    // borders, the borders shrink in proportion and the slices left without room are dropped,
+    // so a tiny panel is only its four corners.
+    pub fn sprites(&self) -> Vec<Sprite> {
+        // Borders are shrunk when the panel is smaller than them.
+        let scale_x = (self.size.width / (self.insets.left + self.insets.right)).min(1.0);
+        let scale_y = (self.size.height / (self.insets.top + self.insets.bottom)).min(1.0);
+        let (left, right) = (self.insets.left * scale_x, self.insets.right * scale_x);
+        let (top, bottom) = (self.insets.top * scale_y, self.insets.bottom * scale_y);
+
+        let (x, y) = (self.position.x, self.position.y);
+        let (width, height) = (self.size.width, self.size.height);
+        let xs = [x, x + left, x + width - right, x + width];
+        // From the bottom, as the world's y axis points up.
+        let ys = [y, y + bottom, y + height - top, y + height];
+
+        let [u0, v0, u1, v1]: [f32; 4] = self.texture.into();
+        let du = (u1 - u0) / self.texture_size.width;
+        let dv = (v1 - v0) / self.texture_size.height;
+        let us = [
+            u0,
+            u0 + self.insets.left * du,
+            u1 - self.insets.right * du,
+            u1,
+        ];
+        // The texture's v axis points down, so rows are matched in reverse.
+        let vs = [
+            v1,
+            v1 - self.insets.bottom * dv,
+            v0 + self.insets.top * dv,
+            v0,
+        ];
+
+        let mut sprites = Vec::with_capacity(9);
+        for row in 0..3 {
+            for column in 0..3 {
+                let size = LogicalSize::new(xs[column + 1] - xs[column], ys[row + 1] - ys[row]);
+                if size.width <= 0.0 || size.height <= 0.0 {
+                    continue;
+                }
+                sprites.push(Sprite {
+                    position: (xs[column], ys[row]).into(),
+                    size,
+                    texture: (us[column], vs[row + 1], us[column + 1], vs[row]).into(),
+                });
+            }
+        }
+        sprites
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn panel(width: f32, height: f32) -> NineSlice {
+        NineSlice {
+            position: Vector2::new(10.0, 20.0),
+            size: LogicalSize::new(width, height),
+            // 16x16 pixels, so one pixel is 1/64 of the texture.
+            texture: Vector4::new(0.5, 0.25, 0.75, 0.5),
+            texture_size: LogicalSize::new(16.0, 16.0),
+            insets: Insets {
+                left: 4.0,
+                right: 2.0,
+                top: 3.0,
+                bottom: 1.0,
+            },
+        }
+    }
+
+    fn sizes(sprites: &[Sprite]) -> Vec<(f32, f32)> {
+        sprites
+            .iter()
+            .map(|sprite| (sprite.size.width, sprite.size.height))
+            .collect()
+    }
+
+    #[test]
+    fn corners_keep_their_size() {
+        for (width, height) in [(6.5, 4.5), (100.0, 50.0), (1000.0, 7.0)] {
+            let sprites = panel(width, height).sprites();
+            assert_eq!(sprites.len(), 9);
+            let sizes = sizes(&sprites);
+            assert_eq!(sizes[0], (4.0, 1.0));
+            assert_eq!(sizes[2], (2.0, 1.0));
+            assert_eq!(sizes[6], (4.0, 3.0));
+            assert_eq!(sizes[8], (2.0, 3.0));
+            assert_eq!(sizes[4], (width - 6.0, height - 4.0));
+
+            assert_eq!(sprites[0].position, Vector2::new(10.0, 20.0));
+            let top_right = sprites[8].position + Vector2::new(2.0, 3.0);
+            assert_eq!(top_right, Vector2::new(10.0 + width, 20.0 + height));
+        }
+    }
+
+    #[test]
+    fn texture_coordinates_line_up() {
+        let sprites = panel(100.0, 50.0).sprites();
+        let pixel = 1.0 / 64.0;
+        assert_eq!(
+            sprites[4].texture,
+            Vector4::new(
+                0.5 + 4.0 * pixel,
+                0.25 + 3.0 * pixel,
+                0.75 - 2.0 * pixel,
+                0.5 - pixel
+            )
+        );
+        // The bottom left corner is at the bottom left of the texture, where v is largest.
+        assert_eq!(
+            sprites[0].texture,
+            Vector4::new(0.5, 0.5 - pixel, 0.5 + 4.0 * pixel, 0.5)
+        );
+        for row in 0..3 {
+            for column in 0..2 {
+                let (a, b) = (sprites[row * 3 + column], sprites[row * 3 + column + 1]);
+                assert_eq!(a.texture.z, b.texture.x);
+                assert_eq!((a.texture.y, a.texture.w), (b.texture.y, b.texture.w));
+            }
+        }
+        for row in 0..2 {
+            for column in 0..3 {
+                let (below, above) = (sprites[row * 3 + column], sprites[row * 3 + column + 3]);
+                assert_eq!(below.texture.y, above.texture.w);
+                assert_eq!(below.texture.x, above.texture.x);
+            }
+        }
+    }
+
+    #[test]
+    fn smaller_than_its_insets() {
+        let sprites = panel(3.0, 2.0).sprites();
+        // Only the corners, at half their size, which still show the whole corner.
+        assert_eq!(
+            sizes(&sprites),
+            [(2.0, 0.5), (1.0, 0.5), (2.0, 1.5), (1.0, 1.5)]
+        );
+        let full = panel(100.0, 50.0).sprites();
+        for (sprite, corner) in sprites.iter().zip([0, 2, 6, 8]) {
+            assert_eq!(sprite.texture, full[corner].texture);
+        }
+        assert_eq!(sprites[3].position, Vector2::new(12.0, 20.5));
+
+        // Too narrow only: the middle column is dropped.
+        assert_eq!(
+            sizes(&panel(3.0, 50.0).sprites()),
+            [
+                (2.0, 1.0),
+                (1.0, 1.0),
+                (2.0, 46.0),
+                (1.0, 46.0),
+                (2.0, 3.0),
+                (1.0, 3.0)
+            ]
+        );
+    }
+}
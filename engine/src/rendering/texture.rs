@@ -13,9 +13,31 @@ impl Texture {
         queue: &wgpu::Queue,
         bytes: &[u8],
         label: &str,
+        format: wgpu::TextureFormat,
     ) -> Result<Self> {
         let img = image::load_from_memory(bytes)?;
-        Self::from_image(device, queue, &img, Some(label))
+        Self::from_image(device, queue, &img, Some(label), format)
+    }
+
+    // Texture format matching the surface: on an sRGB surface the texels are decoded to linear
+    // when sampled and encoded back when written, otherwise they are passed through as they are.
+    pub fn format_for_surface(surface_format: wgpu::TextureFormat) -> wgpu::TextureFormat {
+        if surface_format.is_srgb() {
+            wgpu::TextureFormat::Rgba8UnormSrgb
+        } else {
+            wgpu::TextureFormat::Rgba8Unorm
+        }
+    }
+
+    // Replaces the texels with `img`, keeping the same GPU texture so bind groups using it stay
+    // valid. Returns false, leaving the texture as it was, if the sizes differ.
+    pub fn write(&self, queue: &wgpu::Queue, img: &image::DynamicImage) -> bool {
+        let size = self.texture.size();
+        if img.dimensions() != (size.width, size.height) {
+            return false;
+        }
+        write_image(queue, &self.texture, img);
+        true
     }
 
     pub fn from_image(
@@ -23,8 +45,8 @@ impl Texture {
         queue: &wgpu::Queue,
         img: &image::DynamicImage,
         label: Option<&str>,
+        format: wgpu::TextureFormat,
     ) -> Result<Self> {
-        let rgba = img.to_rgba8();
         let dimensions = img.dimensions();
 
         let size = wgpu::Extent3d {
@@ -38,26 +60,12 @@ impl Texture {
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            format,
             usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-            view_formats: &vec![],
+            view_formats: &[],
         });
 
-        queue.write_texture(
-            wgpu::ImageCopyTexture {
-                aspect: wgpu::TextureAspect::All,
-                texture: &texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-            },
-            &rgba,
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: Some(4 * dimensions.0),
-                rows_per_image: Some(dimensions.1),
-            },
-            size,
-        );
+        write_image(queue, &texture, img);
 
         let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
         let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
@@ -77,3 +85,23 @@ impl Texture {
         })
     }
 }
+
+fn write_image(queue: &wgpu::Queue, texture: &wgpu::Texture, img: &image::DynamicImage) {
+    let rgba = img.to_rgba8();
+    let dimensions = img.dimensions();
+    queue.write_texture(
+        wgpu::ImageCopyTexture {
+            aspect: wgpu::TextureAspect::All,
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+        },
+        &rgba,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(4 * dimensions.0),
+            rows_per_image: Some(dimensions.1),
+        },
+        texture.size(),
+    );
+}
@@ -0,0 +1,62 @@
+// A rectangle on the surface, in physical pixels with the origin at the top left corner. Used for
+// viewports and clipping.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ScreenRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ScreenRect {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    pub fn right(&self) -> u32 {
+        self.x + self.width
+    }
+
+    pub fn bottom(&self) -> u32 {
+        self.y + self.height
+    }
+
+    // The area covered by both rectangles, which is empty if they don't overlap.
+    pub fn intersection(&self, other: &ScreenRect) -> ScreenRect {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = self.right().min(other.right());
+        let bottom = self.bottom().min(other.bottom());
+        ScreenRect {
+            x,
+            y,
+            width: right.saturating_sub(x),
+            height: bottom.saturating_sub(y),
+        }
+    }
+
+    // Splits the rectangle into `count` columns of the same width, eg. for split-screen.
+    pub fn split_horizontally(&self, count: u32) -> Vec<ScreenRect> {
+        let width = self.width / count.max(1);
+        (0..count)
+            .map(|i| ScreenRect::new(self.x + i * width, self.y, width, self.height))
+            .collect()
+    }
+
+    // Splits the rectangle into `count` rows of the same height.
+    pub fn split_vertically(&self, count: u32) -> Vec<ScreenRect> {
+        let height = self.height / count.max(1);
+        (0..count)
+            .map(|i| ScreenRect::new(self.x, self.y + i * height, self.width, height))
+            .collect()
+    }
+}
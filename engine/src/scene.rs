@@ -0,0 +1,308 @@
+use crate::app::Game;
+use crate::clock::Time;
+use crate::input::bindings::Action;
+use crate::input::Input;
+use crate::rendering::Canvas;
+
+// What the scene stack should do after a scene's update.
+pub enum Transition<A: Action> {
+    None,
+    // Pauses the current scene and puts a new one on top of it.
+    Push(Box<dyn Scene<A>>),
+    // Removes the current scene, resuming the one below.
+    Pop,
+    // Swaps the current scene for another one.
+    Replace(Box<dyn Scene<A>>),
+    // Removes every scene and starts over with a new one, eg. back to the title screen.
+    ReplaceAll(Box<dyn Scene<A>>),
+    // Removes every scene, which closes the app.
+    Quit,
+}
+
+// A screen of the game, eg. the title screen, a level or the pause menu. Only the scene at the top
+// of the stack is updated; scenes below it are frozen.
+pub trait Scene<A: Action> {
+    fn update(&mut self, input: &Input<A>, time: &Time) -> Transition<A>;
+
+    fn render(&mut self, canvas: &mut Canvas, alpha: f32);
+
+    // Transparent scenes are rendered over the scene below them, like a pause overlay over the
+    // frozen level.
+    fn is_transparent(&self) -> bool {
+        false
+    }
+
+    // Called when the scene is added to the stack.
+    fn on_enter(&mut self) {}
+
+    // Called when the scene is removed from the stack.
+    fn on_exit(&mut self) {}
+
+    // Called when another scene is pushed on top of this one.
+    fn on_pause(&mut self) {}
+
+    // Called when the scene becomes the top one again.
+    fn on_resume(&mut self) {}
+}
+
+pub struct SceneStack<A: Action> {
+    scenes: Vec<Box<dyn Scene<A>>>,
+}
+
+impl<A: Action> SceneStack<A> {
+    pub fn new(mut initial: Box<dyn Scene<A>>) -> Self {
+        initial.on_enter();
+        Self {
+            scenes: vec![initial],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.scenes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scenes.is_empty()
+    }
+
+    pub fn push(&mut self, mut scene: Box<dyn Scene<A>>) {
+        if let Some(top) = self.scenes.last_mut() {
+            top.on_pause();
+        }
+        scene.on_enter();
+        self.scenes.push(scene);
+    }
+
+    pub fn pop(&mut self) -> Option<Box<dyn Scene<A>>> {
+        let mut scene = self.scenes.pop()?;
+        scene.on_exit();
+        if let Some(top) = self.scenes.last_mut() {
+            top.on_resume();
+        }
+        Some(scene)
+    }
+
+    pub fn replace(&mut self, mut scene: Box<dyn Scene<A>>) {
+        if let Some(mut top) = self.scenes.pop() {
+            top.on_exit();
+        }
+        scene.on_enter();
+        self.scenes.push(scene);
+    }
+
+    pub fn clear(&mut self) {
+        while let Some(mut scene) = self.scenes.pop() {
+            scene.on_exit();
+        }
+    }
+
+    pub fn apply(&mut self, transition: Transition<A>) {
+        match transition {
+            Transition::None => {}
+            Transition::Push(scene) => self.push(scene),
+            Transition::Pop => {
+                self.pop();
+            }
+            Transition::Replace(scene) => self.replace(scene),
+            Transition::ReplaceAll(mut scene) => {
+                self.clear();
+                scene.on_enter();
+                self.scenes.push(scene);
+            }
+            Transition::Quit => self.clear(),
+        }
+    }
+
+    // Updates the top scene and applies the transition it returns.
+    pub fn update(&mut self, input: &Input<A>, time: &Time) {
+        if let Some(top) = self.scenes.last_mut() {
+            let transition = top.update(input, time);
+            self.apply(transition);
+        }
+    }
+
+    // Renders the top scene, and the scenes below it as long as the ones above are transparent.
+    pub fn render(&mut self, canvas: &mut Canvas, alpha: f32) {
+        let first_visible = self.first_visible();
+        for scene in &mut self.scenes[first_visible..] {
+            scene.render(canvas, alpha);
+        }
+    }
+
+    // Index of the bottom scene to render.
+    fn first_visible(&self) -> usize {
+        self.scenes
+            .iter()
+            .rposition(|scene| !scene.is_transparent())
+            .unwrap_or(0)
+    }
+}
+
+impl<A: Action> Game for SceneStack<A> {
+    type Action = A;
+
+    fn update(&mut self, input: &Input<A>, time: &Time) {
+        SceneStack::update(self, input, time);
+    }
+
+    fn render(&mut self, canvas: &mut Canvas, alpha: f32) {
+        SceneStack::render(self, canvas, alpha);
+    }
+
+    fn on_exit(&mut self) {
+        self.clear();
+    }
+
+    fn should_exit(&self) -> bool {
+        self.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::bindings::InputMap;
+    use serde::{Deserialize, Serialize};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+    enum TestAction {}
+
+    type Log = Rc<RefCell<Vec<String>>>;
+
+    // Logs its hooks, and returns `next` from its next update.
+    struct TestScene {
+        name: &'static str,
+        transparent: bool,
+        log: Log,
+        next: Option<Transition<TestAction>>,
+    }
+
+    impl Scene<TestAction> for TestScene {
+        fn update(&mut self, _input: &Input<TestAction>, _time: &Time) -> Transition<TestAction> {
+            self.log.borrow_mut().push(format!("{} update", self.name));
+            self.next.take().unwrap_or(Transition::None)
+        }
+
+        fn render(&mut self, _canvas: &mut Canvas, _alpha: f32) {}
+
+        fn is_transparent(&self) -> bool {
+            self.transparent
+        }
+
+        fn on_enter(&mut self) {
+            self.log.borrow_mut().push(format!("{} enter", self.name));
+        }
+
+        fn on_exit(&mut self) {
+            self.log.borrow_mut().push(format!("{} exit", self.name));
+        }
+
+        fn on_pause(&mut self) {
+            self.log.borrow_mut().push(format!("{} pause", self.name));
+        }
+
+        fn on_resume(&mut self) {
+            self.log.borrow_mut().push(format!("{} resume", self.name));
+        }
+    }
+
+    fn scene(name: &'static str, log: &Log) -> Box<TestScene> {
+        Box::new(TestScene {
+            name,
+            transparent: false,
+            log: log.clone(),
+            next: None,
+        })
+    }
+
+    fn overlay(name: &'static str, log: &Log) -> Box<TestScene> {
+        Box::new(TestScene {
+            transparent: true,
+            ..*scene(name, log)
+        })
+    }
+
+    // The hooks called since the previous call.
+    fn take(log: &Log) -> Vec<String> {
+        std::mem::take(&mut *log.borrow_mut())
+    }
+
+    #[test]
+    fn push_and_pop() {
+        let log = Log::default();
+        let mut stack = SceneStack::new(scene("level", &log));
+        assert_eq!(take(&log), ["level enter"]);
+
+        stack.apply(Transition::Push(scene("pause", &log)));
+        assert_eq!(take(&log), ["level pause", "pause enter"]);
+        assert_eq!(stack.len(), 2);
+
+        stack.apply(Transition::Pop);
+        assert_eq!(take(&log), ["pause exit", "level resume"]);
+        stack.apply(Transition::Pop);
+        assert_eq!(take(&log), ["level exit"]);
+        assert!(stack.is_empty());
+        stack.apply(Transition::Pop);
+        assert!(take(&log).is_empty());
+    }
+
+    #[test]
+    fn replace() {
+        let log = Log::default();
+        let mut stack = SceneStack::new(scene("level", &log));
+        stack.push(scene("pause", &log));
+        take(&log);
+
+        // Only the top scene is replaced, the one below stays paused.
+        stack.apply(Transition::Replace(scene("options", &log)));
+        assert_eq!(take(&log), ["pause exit", "options enter"]);
+        assert_eq!(stack.len(), 2);
+
+        stack.apply(Transition::ReplaceAll(scene("title", &log)));
+        assert_eq!(take(&log), ["options exit", "level exit", "title enter"]);
+        assert_eq!(stack.len(), 1);
+
+        stack.apply(Transition::Quit);
+        assert_eq!(take(&log), ["title exit"]);
+        assert!(stack.should_exit());
+    }
+
+    #[test]
+    fn only_the_top_scene_updates() {
+        let log = Log::default();
+        let mut stack = SceneStack::new(scene("level", &log));
+        let mut pause = scene("pause", &log);
+        pause.next = Some(Transition::Pop);
+        stack.push(pause);
+        take(&log);
+
+        let input = Input::new(InputMap::new());
+        let time = Time::new(Duration::from_millis(10));
+        SceneStack::update(&mut stack, &input, &time);
+        assert_eq!(take(&log), ["pause update", "pause exit", "level resume"]);
+        SceneStack::update(&mut stack, &input, &time);
+        assert_eq!(take(&log), ["level update"]);
+    }
+
+    #[test]
+    fn render_from_the_last_opaque_scene() {
+        let log = Log::default();
+        // The bottom scene is drawn even if it's transparent.
+        let mut stack = SceneStack::new(overlay("fade", &log));
+        assert_eq!(stack.first_visible(), 0);
+
+        stack.push(scene("level", &log));
+        stack.push(overlay("hud", &log));
+        stack.push(overlay("pause", &log));
+        assert_eq!(stack.first_visible(), 1);
+
+        stack.push(scene("options", &log));
+        assert_eq!(stack.first_visible(), 4);
+        stack.pop();
+        stack.pop();
+        assert_eq!(stack.first_visible(), 1);
+    }
+}
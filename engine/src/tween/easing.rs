@@ -0,0 +1,179 @@
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+// How a tween's progress maps to how far its value is from the start to the end, following
+// the usual curves (see easings.net). "In" curves start slowly, "out" curves end slowly.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Easing {
+    #[default]
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    // Overshoots slightly past the start or end.
+    BackIn,
+    BackOut,
+    BackInOut,
+    // Springs past the start or end.
+    ElasticIn,
+    ElasticOut,
+    ElasticInOut,
+    // Bounces off the start or end, like a dropped ball.
+    BounceIn,
+    BounceOut,
+    BounceInOut,
+}
+
+const BACK: f32 = 1.70158;
+const BACK_IN_OUT: f32 = BACK * 1.525;
+const ELASTIC: f32 = 2.0 * PI / 3.0;
+const ELASTIC_IN_OUT: f32 = 2.0 * PI / 4.5;
+
+impl Easing {
+    // `t` goes from 0.0 to 1.0, and so does the result, though back and elastic curves go
+    // beyond.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::QuadIn => t * t,
+            Easing::QuadOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::QuadInOut => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(2) / 2.0
+                }
+            }
+            Easing::CubicIn => t * t * t,
+            Easing::CubicOut => 1.0 - (1.0 - t).powi(3),
+            Easing::CubicInOut => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+            Easing::BackIn => (BACK + 1.0) * t * t * t - BACK * t * t,
+            Easing::BackOut => 1.0 + (BACK + 1.0) * (t - 1.0).powi(3) + BACK * (t - 1.0).powi(2),
+            Easing::BackInOut => {
+                if t < 0.5 {
+                    (2.0 * t).powi(2) * ((BACK_IN_OUT + 1.0) * 2.0 * t - BACK_IN_OUT) / 2.0
+                } else {
+                    ((2.0 * t - 2.0).powi(2)
+                        * ((BACK_IN_OUT + 1.0) * (2.0 * t - 2.0) + BACK_IN_OUT)
+                        + 2.0)
+                        / 2.0
+                }
+            }
+            Easing::ElasticIn => {
+                if t == 0.0 || t == 1.0 {
+                    t
+                } else {
+                    -(2.0f32.powf(10.0 * t - 10.0)) * ((10.0 * t - 10.75) * ELASTIC).sin()
+                }
+            }
+            Easing::ElasticOut => {
+                if t == 0.0 || t == 1.0 {
+                    t
+                } else {
+                    2.0f32.powf(-10.0 * t) * ((10.0 * t - 0.75) * ELASTIC).sin() + 1.0
+                }
+            }
+            Easing::ElasticInOut => {
+                if t == 0.0 || t == 1.0 {
+                    t
+                } else if t < 0.5 {
+                    -(2.0f32.powf(20.0 * t - 10.0) * ((20.0 * t - 11.125) * ELASTIC_IN_OUT).sin())
+                        / 2.0
+                } else {
+                    2.0f32.powf(-20.0 * t + 10.0) * ((20.0 * t - 11.125) * ELASTIC_IN_OUT).sin()
+                        / 2.0
+                        + 1.0
+                }
+            }
+            Easing::BounceIn => 1.0 - bounce_out(1.0 - t),
+            Easing::BounceOut => bounce_out(t),
+            Easing::BounceInOut => {
+                if t < 0.5 {
+                    (1.0 - bounce_out(1.0 - 2.0 * t)) / 2.0
+                } else {
+                    (1.0 + bounce_out(2.0 * t - 1.0)) / 2.0
+                }
+            }
+        }
+    }
+}
+
+fn bounce_out(t: f32) -> f32 {
+    const N: f32 = 7.5625;
+    const D: f32 = 2.75;
+    if t < 1.0 / D {
+        N * t * t
+    } else if t < 2.0 / D {
+        let t = t - 1.5 / D;
+        N * t * t + 0.75
+    } else if t < 2.5 / D {
+        let t = t - 2.25 / D;
+        N * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / D;
+        N * t * t + 0.984375
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [Easing; 16] = [
+        Easing::Linear,
+        Easing::QuadIn,
+        Easing::QuadOut,
+        Easing::QuadInOut,
+        Easing::CubicIn,
+        Easing::CubicOut,
+        Easing::CubicInOut,
+        Easing::BackIn,
+        Easing::BackOut,
+        Easing::BackInOut,
+        Easing::ElasticIn,
+        Easing::ElasticOut,
+        Easing::ElasticInOut,
+        Easing::BounceIn,
+        Easing::BounceOut,
+        Easing::BounceInOut,
+    ];
+
+    #[test]
+    fn start_and_end() {
+        for easing in ALL {
+            for (t, expected) in [(0.0, 0.0), (1.0, 1.0), (-1.0, 0.0), (2.0, 1.0)] {
+                let value = easing.apply(t);
+                assert!(
+                    (value - expected).abs() < 1e-5,
+                    "{:?} at {} is {}",
+                    easing,
+                    t,
+                    value
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn in_out_halfway() {
+        for easing in [
+            Easing::QuadInOut,
+            Easing::CubicInOut,
+            Easing::BackInOut,
+            Easing::ElasticInOut,
+            Easing::BounceInOut,
+        ] {
+            assert!((easing.apply(0.5) - 0.5).abs() < 1e-5, "{:?}", easing);
+        }
+    }
+}
@@ -0,0 +1,442 @@
+pub mod easing;
+
+pub use easing::Easing;
+
+use crate::clock::Time;
+use crate::rendering::color::Color;
+use cgmath::{Vector2, Vector3, VectorSpace};
+
+// Values a tween can go through.
+pub trait Lerp: Copy {
+    // The value `t` of the way from `self` to `other`.
+    fn lerp(self, other: Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(self, other: f32, t: f32) -> f32 {
+        self + (other - self) * t
+    }
+}
+
+impl Lerp for Vector2<f32> {
+    fn lerp(self, other: Vector2<f32>, t: f32) -> Vector2<f32> {
+        VectorSpace::lerp(self, other, t)
+    }
+}
+
+impl Lerp for Vector3<f32> {
+    fn lerp(self, other: Vector3<f32>, t: f32) -> Vector3<f32> {
+        VectorSpace::lerp(self, other, t)
+    }
+}
+
+impl Lerp for Color {
+    fn lerp(self, other: Color, t: f32) -> Color {
+        Color::lerp(self, other, t)
+    }
+}
+
+// Something that plays over time. Tuples and vectors of timelines play them all at once.
+pub trait Timeline {
+    // Advances by `dt` seconds, returning how much of it was left over once finished.
+    fn advance(&mut self, dt: f32) -> f32;
+
+    fn is_finished(&self) -> bool;
+
+    // Goes back to the start.
+    fn reset(&mut self);
+
+    // Advances by one tick of game time.
+    fn update(&mut self, time: &Time) {
+        self.advance(time.delta());
+    }
+}
+
+// Goes from one value to another over time.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Tween<T: Lerp> {
+    from: T,
+    to: T,
+    // Of one play, in seconds.
+    duration: f32,
+    easing: Easing,
+    delay: f32,
+    // How many times it plays, forever if `None`.
+    repeat: Option<u32>,
+    // Whether every other play goes backwards.
+    yoyo: bool,
+    // Including the delay.
+    elapsed: f32,
+}
+
+impl<T: Lerp> Tween<T> {
+    // Plays once, linearly.
+    pub fn new(from: T, to: T, duration: f32) -> Self {
+        Self {
+            from,
+            to,
+            duration: duration.max(0.0),
+            easing: Easing::Linear,
+            delay: 0.0,
+            repeat: Some(1),
+            yoyo: false,
+            elapsed: 0.0,
+        }
+    }
+
+    // Stays on `value`, eg. to wait between the steps of a sequence.
+    pub fn hold(value: T, duration: f32) -> Self {
+        Self::new(value, value, duration)
+    }
+
+    pub fn with_easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    // Waits `delay` seconds on the start value before playing, only once however many times
+    // it repeats.
+    pub fn with_delay(mut self, delay: f32) -> Self {
+        self.delay = delay.max(0.0);
+        self
+    }
+
+    pub fn with_repeat(mut self, repeat: u32) -> Self {
+        self.repeat = Some(repeat.max(1));
+        self
+    }
+
+    pub fn looping(mut self) -> Self {
+        self.repeat = None;
+        self
+    }
+
+    // Plays backwards every other time, so with 2 repeats it goes there and back.
+    pub fn with_yoyo(mut self) -> Self {
+        self.yoyo = true;
+        self
+    }
+
+    pub fn from(&self) -> T {
+        self.from
+    }
+
+    pub fn to(&self) -> T {
+        self.to
+    }
+
+    // Including the delay, infinite when looping.
+    pub fn total_duration(&self) -> f32 {
+        match self.repeat {
+            Some(repeat) => self.delay + self.duration * repeat as f32,
+            None => f32::INFINITY,
+        }
+    }
+
+    // The current value.
+    pub fn value(&self) -> T {
+        let time = self.elapsed - self.delay;
+        if time <= 0.0 {
+            return self.from;
+        }
+        let (play, progress) = if self.duration == 0.0 || time >= self.total_duration() - self.delay
+        {
+            (self.repeat.unwrap_or(1).saturating_sub(1), 1.0)
+        } else {
+            let play = (time / self.duration).floor();
+            (play as u32, time / self.duration - play)
+        };
+        let progress = if self.yoyo && play % 2 == 1 {
+            1.0 - progress
+        } else {
+            progress
+        };
+        self.from.lerp(self.to, self.easing.apply(progress))
+    }
+
+    // The value once finished, or after one play when looping.
+    fn end_value(&self) -> T {
+        let mut end = *self;
+        end.repeat = Some(self.repeat.unwrap_or(1));
+        end.elapsed = end.total_duration();
+        end.value()
+    }
+}
+
+impl<T: Lerp> Timeline for Tween<T> {
+    fn advance(&mut self, dt: f32) -> f32 {
+        let total = self.total_duration();
+        if self.elapsed >= total {
+            return dt;
+        }
+        self.elapsed += dt;
+        if self.elapsed >= total {
+            let left = self.elapsed - total;
+            self.elapsed = total;
+            left
+        } else {
+            0.0
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.elapsed >= self.total_duration()
+    }
+
+    fn reset(&mut self) {
+        self.elapsed = 0.0;
+    }
+}
+
+// Tweens of the same value played one after the other.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sequence<T: Lerp> {
+    steps: Vec<Tween<T>>,
+    current: usize,
+    // How many times it plays, forever if `None`.
+    repeat: Option<u32>,
+    plays: u32,
+}
+
+impl<T: Lerp> Sequence<T> {
+    pub fn new(first: Tween<T>) -> Self {
+        Self {
+            steps: vec![first],
+            current: 0,
+            repeat: Some(1),
+            plays: 0,
+        }
+    }
+
+    pub fn then(mut self, step: Tween<T>) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    // Stays on the value the sequence is at for `duration` seconds.
+    pub fn then_wait(self, duration: f32) -> Self {
+        let value = self.steps[self.steps.len() - 1].end_value();
+        self.then(Tween::hold(value, duration))
+    }
+
+    pub fn with_repeat(mut self, repeat: u32) -> Self {
+        self.repeat = Some(repeat.max(1));
+        self
+    }
+
+    pub fn looping(mut self) -> Self {
+        self.repeat = None;
+        self
+    }
+
+    pub fn value(&self) -> T {
+        self.steps[self.current].value()
+    }
+
+    fn cycle_duration(&self) -> f32 {
+        self.steps.iter().map(Tween::total_duration).sum()
+    }
+}
+
+impl<T: Lerp> Timeline for Sequence<T> {
+    fn advance(&mut self, mut dt: f32) -> f32 {
+        if self.is_finished() {
+            return dt;
+        }
+        loop {
+            dt = self.steps[self.current].advance(dt);
+            if !self.steps[self.current].is_finished() {
+                return 0.0;
+            }
+            if self.current + 1 < self.steps.len() {
+                self.current += 1;
+                continue;
+            }
+            self.plays += 1;
+            if Some(self.plays) == self.repeat {
+                return dt;
+            }
+            // Looping steps that take no time would never use up `dt`.
+            if self.cycle_duration() <= 0.0 {
+                return 0.0;
+            }
+            self.steps.iter_mut().for_each(Tween::reset);
+            self.current = 0;
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        Some(self.plays) == self.repeat
+    }
+
+    fn reset(&mut self) {
+        self.steps.iter_mut().for_each(Tween::reset);
+        self.current = 0;
+        self.plays = 0;
+    }
+}
+
+impl<T: Timeline> Timeline for Vec<T> {
+    fn advance(&mut self, dt: f32) -> f32 {
+        self.iter_mut()
+            .map(|timeline| timeline.advance(dt))
+            .fold(dt, f32::min)
+    }
+
+    fn is_finished(&self) -> bool {
+        self.iter().all(Timeline::is_finished)
+    }
+
+    fn reset(&mut self) {
+        self.iter_mut().for_each(Timeline::reset);
+    }
+}
+
+macro_rules! parallel {
+    ($($name:ident: $index:tt),+) => {
+        impl<$($name: Timeline),+> Timeline for ($($name,)+) {
+            fn advance(&mut self, dt: f32) -> f32 {
+                dt$(.min(self.$index.advance(dt)))+
+            }
+
+            fn is_finished(&self) -> bool {
+                $(self.$index.is_finished())&&+
+            }
+
+            fn reset(&mut self) {
+                $(self.$index.reset();)+
+            }
+        }
+    };
+}
+
+parallel!(A: 0, B: 1);
+parallel!(A: 0, B: 1, C: 2);
+parallel!(A: 0, B: 1, C: 2, D: 3);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-5,
+            "{} is not {}",
+            actual,
+            expected
+        );
+    }
+
+    // Advances `sequence` by `dt` and checks its value.
+    fn sequence_at(sequence: &mut Sequence<f32>, dt: f32, expected: f32) {
+        assert_close(sequence.advance(dt), 0.0);
+        assert_close(sequence.value(), expected);
+    }
+
+    #[test]
+    fn delay() {
+        let mut tween = Tween::new(0.0, 10.0, 1.0).with_delay(0.5);
+        assert_close(tween.total_duration(), 1.5);
+        assert_close(tween.advance(0.25), 0.0);
+        assert_close(tween.value(), 0.0);
+        tween.advance(0.75);
+        assert_close(tween.value(), 5.0);
+        assert_close(tween.advance(1.0), 0.5);
+        assert!(tween.is_finished());
+        assert_close(tween.value(), 10.0);
+        // Nothing is used up once finished.
+        assert_close(tween.advance(0.25), 0.25);
+
+        tween.reset();
+        assert!(!tween.is_finished());
+        assert_close(tween.value(), 0.0);
+    }
+
+    #[test]
+    fn repeat() {
+        let mut tween = Tween::new(0.0, 10.0, 1.0).with_repeat(3);
+        tween.advance(2.5);
+        assert_close(tween.value(), 5.0);
+        assert_close(tween.advance(1.0), 0.5);
+        assert_close(tween.value(), 10.0);
+
+        let mut tween = Tween::new(0.0, 10.0, 1.0).looping();
+        assert_close(tween.advance(100.25), 0.0);
+        assert_close(tween.value(), 2.5);
+        assert!(!tween.is_finished());
+    }
+
+    #[test]
+    fn yoyo() {
+        let mut tween = Tween::new(0.0, 10.0, 1.0)
+            .with_easing(Easing::QuadIn)
+            .with_yoyo()
+            .with_repeat(2);
+        tween.advance(0.5);
+        assert_close(tween.value(), 2.5);
+        tween.advance(1.0);
+        assert_close(tween.value(), 2.5);
+        tween.advance(0.25);
+        assert_close(tween.value(), 0.625);
+        assert_close(tween.advance(1.0), 0.75);
+        assert_close(tween.value(), 0.0);
+    }
+
+    #[test]
+    fn sequence() {
+        let mut sequence = Sequence::new(Tween::new(0.0, 10.0, 1.0))
+            .then_wait(0.5)
+            .then(Tween::new(10.0, 0.0, 2.0));
+        sequence_at(&mut sequence, 0.5, 5.0);
+        sequence_at(&mut sequence, 0.75, 10.0);
+        // Through the end of the wait and into the last step.
+        sequence_at(&mut sequence, 0.75, 7.5);
+        assert_close(sequence.advance(2.0), 0.5);
+        assert!(sequence.is_finished());
+        assert_close(sequence.value(), 0.0);
+    }
+
+    #[test]
+    fn large_steps_span_several_segments() {
+        let mut sequence = Sequence::new(Tween::new(0.0, 10.0, 1.0))
+            .then_wait(0.5)
+            .then(Tween::new(10.0, 0.0, 2.0))
+            .with_repeat(2);
+        // All of the first play, and into the last step of the second.
+        sequence_at(&mut sequence, 5.5, 7.5);
+        assert!(!sequence.is_finished());
+        assert_close(sequence.advance(10.0), 8.5);
+        assert!(sequence.is_finished());
+        assert_close(sequence.value(), 0.0);
+
+        sequence.reset();
+        sequence_at(&mut sequence, 0.5, 5.0);
+    }
+
+    #[test]
+    fn looping_sequence_of_nothing() {
+        let mut sequence = Sequence::new(Tween::hold(1.0, 0.0)).looping();
+        assert_close(sequence.advance(1.0), 0.0);
+        assert!(!sequence.is_finished());
+        assert_close(sequence.value(), 1.0);
+    }
+
+    #[test]
+    fn parallel() {
+        let mut tweens = (Tween::new(0.0, 1.0, 1.0), Tween::new(0.0, 1.0, 2.0));
+        assert_close(tweens.advance(1.5), 0.0);
+        assert!(tweens.0.is_finished());
+        assert!(!tweens.is_finished());
+        assert_close(tweens.1.value(), 0.75);
+        assert_close(tweens.advance(1.0), 0.5);
+        assert!(tweens.is_finished());
+
+        let mut tweens = vec![Tween::new(0.0, 1.0, 1.0), Tween::new(0.0, 1.0, 0.25)];
+        assert_close(tweens.advance(0.5), 0.0);
+        assert!(!tweens.is_finished());
+        assert_close(tweens.advance(0.75), 0.25);
+        assert!(tweens.is_finished());
+        tweens.reset();
+        assert!(!tweens[1].is_finished());
+    }
+}
@@ -0,0 +1,116 @@
+use winit::dpi::{LogicalSize, PhysicalSize};
+use winit::event_loop::EventLoop;
+use winit::window::{CursorIcon, Fullscreen, Icon, Window, WindowBuilder};
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FullscreenMode {
+    // A window covering the whole monitor, which is quick to switch to and from.
+    Borderless,
+    // Takes over the monitor, using its best video mode.
+    Exclusive,
+}
+
+// winit can't make a cursor from an image, so custom cursors are limited to the system ones. Games
+// wanting their own can hide the cursor and draw a sprite at `Pointer::world_position`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Cursor {
+    Visible(CursorIcon),
+    Hidden,
+}
+
+#[derive(Clone, Debug)]
+pub struct WindowConfig {
+    pub title: String,
+    pub size: LogicalSize<f32>,
+    pub min_size: Option<LogicalSize<f32>>,
+    pub resizable: bool,
+    // An encoded image, eg. `include_bytes!("assets/icon.png")`.
+    pub icon: Option<Vec<u8>>,
+    pub start_fullscreen: bool,
+    pub fullscreen_mode: FullscreenMode,
+    // Whether Alt+Enter switches between windowed and fullscreen.
+    pub fullscreen_toggle: bool,
+    pub cursor: Cursor,
+    // Pauses the game time while the window isn't focused.
+    pub pause_on_focus_loss: bool,
+    // The resolution the game is designed for. When set, the game is scaled to fit the window
+    // keeping its aspect ratio, with bars filling the rest, see `Graphics::game_rect`.
+    pub virtual_resolution: Option<PhysicalSize<u32>>,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            title: "Game".to_string(),
+            size: LogicalSize::new(800.0, 600.0),
+            min_size: None,
+            resizable: true,
+            icon: None,
+            start_fullscreen: false,
+            fullscreen_mode: FullscreenMode::Borderless,
+            fullscreen_toggle: true,
+            cursor: Cursor::Visible(CursorIcon::Default),
+            pause_on_focus_loss: true,
+            virtual_resolution: None,
+        }
+    }
+}
+
+impl WindowConfig {
+    pub fn build<T>(&self, event_loop: &EventLoop<T>) -> anyhow::Result<Window> {
+        let mut builder = WindowBuilder::new()
+            .with_title(&self.title)
+            .with_inner_size(self.size)
+            .with_resizable(self.resizable);
+        if let Some(min_size) = self.min_size {
+            builder = builder.with_min_inner_size(min_size);
+        }
+        if let Some(icon) = &self.icon {
+            builder = builder.with_window_icon(Some(load_icon(icon)?));
+        }
+        let window = builder.build(event_loop)?;
+
+        if self.start_fullscreen {
+            self.set_fullscreen(&window, true);
+        }
+        match self.cursor {
+            Cursor::Visible(icon) => window.set_cursor_icon(icon),
+            Cursor::Hidden => window.set_cursor_visible(false),
+        }
+        Ok(window)
+    }
+
+    pub fn set_fullscreen(&self, window: &Window, fullscreen: bool) {
+        if !fullscreen {
+            window.set_fullscreen(None);
+            return;
+        }
+        let fullscreen = match self.fullscreen_mode {
+            FullscreenMode::Borderless => Some(Fullscreen::Borderless(None)),
+            FullscreenMode::Exclusive => window
+                .current_monitor()
+                .and_then(|monitor| {
+                    monitor.video_modes().max_by_key(|mode| {
+                        let size = mode.size();
+                        (size.width * size.height, mode.refresh_rate_millihertz())
+                    })
+                })
+                .map(Fullscreen::Exclusive)
+                // Some platforms don't list video modes.
+                .or(Some(Fullscreen::Borderless(None))),
+        };
+        window.set_fullscreen(fullscreen);
+    }
+
+    pub fn toggle_fullscreen(&self, window: &Window) {
+        self.set_fullscreen(window, window.fullscreen().is_none());
+    }
+}
+
+fn load_icon(bytes: &[u8]) -> anyhow::Result<Icon> {
+    let image = image::load_from_memory(bytes)
+        .map_err(|e| anyhow::anyhow!("Failed to load window icon: {}", e))?
+        .to_rgba8();
+    let (width, height) = image.dimensions();
+    Ok(Icon::from_rgba(image.into_raw(), width, height)?)
+}